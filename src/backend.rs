@@ -4,31 +4,84 @@ mod syn;
 #[cfg(feature = "syn")]
 pub use self::syn::SynBackend as Backend;
 
-#[cfg(feature = "venial")]
+// Syn is used when both are enabled, as with `--all-features`.
+#[cfg(all(feature = "venial", not(feature = "syn")))]
 mod venial;
 
-#[cfg(feature = "venial")]
+#[cfg(all(feature = "venial", not(feature = "syn")))]
 pub use self::venial::VenialBackend as Backend;
 
+mod span;
+pub use span::{FileId, LineColumn, SourceMap, Span};
+
+pub mod visit;
+pub use visit::{Fold, Visit, VisitMut};
+
+pub mod format;
+pub mod macros;
+pub use macros::{Macro, Macros};
+pub mod sexpr;
+
 #[derive(Debug, thiserror::Error)]
 pub enum BackendError {
 	#[cfg(feature = "syn")]
 	#[error("Internal Syn Error: {0}")]
 	Syn(#[from] ::syn::Error),
 
-	#[cfg(feature = "venial")]
+	#[cfg(all(feature = "venial", not(feature = "syn")))]
 	#[error("Internal Venial Error: {0}")]
 	Venial(#[from] ::venial::Error),
 }
 
 pub type Result<T> = std::result::Result<T, BackendError>;
 
+#[derive(Debug, Default)]
+pub struct Options {
+	/// File spans of the generated items will point into.
+	pub file: FileId,
+	pub macros: Macros,
+	/// Wraps the results of integer arithmetic into the range of their type, when it's known, so `u8` and `i32`
+	/// overflow the same way they do in a release build. Targets only have 64-bit integers otherwise.
+	pub strict_integers: bool,
+	pub chars: CharRepr,
+}
+
+/// What `'c'`, `b'c'` and `b"bytes"` literals are lowered to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CharRepr {
+	/// Strings, so chars print and concatenate like text. Byte strings have to be valid UTF-8.
+	#[default]
+	String,
+	/// Integers, the code point of a char or the value of a byte, with byte strings being arrays of bytes. This is
+	/// how Rust sees them, so they can be compared, cast and used in arithmetic.
+	Code,
+}
+
 pub trait Ast: Sized {
-	fn generate(code: impl AsRef<[u8]>) -> Result<Self>;
+	fn generate(code: impl AsRef<[u8]>) -> Result<Self> {
+		Self::generate_with(code, &Options::default())
+	}
+
+	fn generate_with(code: impl AsRef<[u8]>, options: &Options) -> Result<Self>;
+
+	/// Wraps IR that didn't come from this backend, eg. deserialized from JSON.
+	fn from_items(items: Vec<Item>) -> Self;
+
 	fn items(&self) -> &Vec<Item>;
 }
 
-#[derive(Debug)]
+#[cfg(feature = "serde")]
+pub fn to_json(items: &[Item]) -> serde_json::Result<String> {
+	serde_json::to_string_pretty(items)
+}
+
+#[cfg(feature = "serde")]
+pub fn from_json(json: impl AsRef<[u8]>) -> serde_json::Result<Vec<Item>> {
+	serde_json::from_slice(json.as_ref())
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfElif {
 	pub condition: Box<Item>,
 	pub stmts: Vec<Item>,
@@ -37,13 +90,155 @@ pub struct IfElif {
 	pub else_stmts: Option<Vec<Item>>,
 }
 
-#[derive(Debug)]
+/// What a `let` or `if let` binds its value to. `mut` and `ref` are dropped, every binding is a plain local.
+///
+/// Refutable patterns check the value has their shape before binding it: literals compare equal, slices have
+/// enough elements and every element matches. Struct paths aren't checked, since enums aren't supported.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+	Ident(String),
+	/// `_`
+	Wildcard,
+	/// `..` in a tuple or slice, skipping any number of elements.
+	Rest,
+	Tuple(Vec<Pattern>),
+	/// `Point(x, y)`, whose value is a tuple.
+	TupleStruct { path: String, elements: Vec<Pattern> },
+	/// `Point { x, y: (a, b), .. }`
+	Struct { path: String, fields: Vec<(String, Pattern)> },
+	Slice(Vec<Pattern>),
+	/// A literal the value has to be equal to, like `1` or `"a"`.
+	Literal(Box<Item>),
+	/// `a | b`, whose alternatives can't bind anything.
+	Or(Vec<Pattern>),
+	/// `Some(x)`, `None`, `Ok(x)` or `Err(e)`, with a pattern for the value of all but `None`.
+	Variant { variant: Variant, value: Option<Box<Pattern>> },
+}
+
+impl Pattern {
+	/// Every name bound by the pattern, in order.
+	pub fn bindings(&self) -> Vec<&String> {
+		match self {
+			Self::Ident(name) => vec![name],
+			Self::Wildcard | Self::Rest | Self::Literal(_) => vec![],
+			Self::Tuple(elements) | Self::TupleStruct { elements, .. } | Self::Slice(elements) | Self::Or(elements) => {
+				elements.iter().flat_map(Self::bindings).collect()
+			}
+			Self::Struct { fields, .. } => fields.iter().flat_map(|(_, pattern)| pattern.bindings()).collect(),
+			Self::Variant { value, .. } => value.iter().flat_map(|pattern| pattern.bindings()).collect(),
+		}
+	}
+
+	pub fn bindings_mut(&mut self) -> Vec<&mut String> {
+		match self {
+			Self::Ident(name) => vec![name],
+			Self::Wildcard | Self::Rest | Self::Literal(_) => vec![],
+			Self::Tuple(elements) | Self::TupleStruct { elements, .. } | Self::Slice(elements) | Self::Or(elements) => {
+				elements.iter_mut().flat_map(Self::bindings_mut).collect()
+			}
+			Self::Struct { fields, .. } => fields.iter_mut().flat_map(|(_, pattern)| pattern.bindings_mut()).collect(),
+			Self::Variant { value, .. } => value.iter_mut().flat_map(|pattern| pattern.bindings_mut()).collect(),
+		}
+	}
+
+	/// Whether the pattern can fail to match a value of the right type. Slices are assumed to be arrays of the
+	/// right length, which is all Rust allows outside of `if let` anyway.
+	pub fn is_refutable(&self) -> bool {
+		match self {
+			Self::Ident(_) | Self::Wildcard | Self::Rest => false,
+			Self::Literal(_) | Self::Or(_) | Self::Variant { .. } => true,
+			Self::Tuple(elements) | Self::TupleStruct { elements, .. } | Self::Slice(elements) => {
+				elements.iter().any(Self::is_refutable)
+			}
+			Self::Struct { fields, .. } => fields.iter().any(|(_, pattern)| pattern.is_refutable()),
+		}
+	}
+}
+
+/// Prefix of the temporaries lowering declares itself, like `$__left` for the operands of `assert_eq!`. No Rust
+/// identifier starts with it, so they can't clash with the user's names, and generators write them without it.
+pub const TEMPORARY: char = '$';
+
+/// Functions the std macros are lowered to, which every generator maps to its own way of printing and failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Builtin {
+	/// `print(values...)` prints its values separated by tabs, followed by a newline.
+	Print,
+	/// `write(s)` writes a string to stdout.
+	Write,
+	/// `ewrite(s)` writes a string to stderr.
+	Ewrite,
+	/// `fwrite(dst, s)` writes a string to `dst`, anything with a `write` method.
+	Fwrite,
+	/// `panic(message)` stops the program.
+	Panic,
+	/// `assert(cond, message)` stops the program if `cond` is false.
+	Assert,
+}
+
+impl Builtin {
+	pub const ALL: [Builtin; 6] = [Builtin::Print, Builtin::Write, Builtin::Ewrite, Builtin::Fwrite, Builtin::Panic, Builtin::Assert];
+
+	/// What it's called in the IR, and what hosts register it as.
+	pub fn name(self) -> &'static str {
+		match self {
+			Builtin::Print => "print",
+			Builtin::Write => "write",
+			Builtin::Ewrite => "ewrite",
+			Builtin::Fwrite => "fwrite",
+			Builtin::Panic => "panic",
+			Builtin::Assert => "assert",
+		}
+	}
+
+	pub fn parse(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+	}
+}
+
+/// A variant of `Option` or `Result`, the only enums generated code knows about. Generators pick how they're
+/// represented, and implement the methods the backend leaves as [ItemKind::ExprMethodCall] for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+	Some,
+	None,
+	Ok,
+	Err,
+}
+
+impl Variant {
+	pub const ALL: [Variant; 4] = [Variant::Some, Variant::None, Variant::Ok, Variant::Err];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Variant::Some => "Some",
+			Variant::None => "None",
+			Variant::Ok => "Ok",
+			Variant::Err => "Err",
+		}
+	}
+
+	/// Whether it holds a value, which all but `None` do.
+	pub fn has_value(self) -> bool {
+		self != Variant::None
+	}
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOp {
 	Add,
 	Sub,
 	Mul,
+	/// `/` of floats, or of operands whose type isn't known.
 	Div,
+	/// `/` of integers, which rounds towards zero.
+	IntDiv,
 
+	/// `%`, whose result has the sign of the left operand.
 	Mod,
 	And,
 	Or,
@@ -61,20 +256,129 @@ pub enum BinaryOp {
 	Ge,
 	Gt,
 
-	AddEq,
-	SubEq,
-	MulEq,
-	DivEq,
+	// `u64`s above `i64::MAX` are stored as the negative `i64` with the same bits, so they're divided and
+	// compared with these.
+	/// `/` of `u64`s.
+	UDiv,
+	/// `%` of `u64`s.
+	UMod,
+	ULt,
+	ULe,
+	UGe,
+	UGt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryOp {
+	Neg,
+	/// `!` of a bool.
+	Not,
+	/// `!` of an integer, flipping every bit.
+	BitNot,
+}
+
+/// Number types, as in casts and literal suffixes. Integers are 64 bits at most, with `u64` values above
+/// `i64::MAX` stored as the `i64` with the same bits, and 128-bit types being the same as 64-bit ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NumType {
+	I8,
+	I16,
+	I32,
+	I64,
+	I128,
+	Isize,
+	U8,
+	U16,
+	U32,
+	U64,
+	U128,
+	Usize,
+	F32,
+	F64,
+}
+
+impl NumType {
+	pub const ALL: [NumType; 14] = [
+		Self::I8,
+		Self::I16,
+		Self::I32,
+		Self::I64,
+		Self::I128,
+		Self::Isize,
+		Self::U8,
+		Self::U16,
+		Self::U32,
+		Self::U64,
+		Self::U128,
+		Self::Usize,
+		Self::F32,
+		Self::F64,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::I8 => "i8",
+			Self::I16 => "i16",
+			Self::I32 => "i32",
+			Self::I64 => "i64",
+			Self::I128 => "i128",
+			Self::Isize => "isize",
+			Self::U8 => "u8",
+			Self::U16 => "u16",
+			Self::U32 => "u32",
+			Self::U64 => "u64",
+			Self::U128 => "u128",
+			Self::Usize => "usize",
+			Self::F32 => "f32",
+			Self::F64 => "f64",
+		}
+	}
+
+	pub fn parse(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|ty| ty.name() == name)
+	}
 
-	ModEq,
+	pub fn is_float(self) -> bool {
+		matches!(self, Self::F32 | Self::F64)
+	}
 
-	BxorEq,
-	BandEq,
-	BorEq,
-	BshlEq,
-	BshrEq,
+	pub fn is_signed(self) -> bool {
+		matches!(self, Self::I8 | Self::I16 | Self::I32 | Self::I64 | Self::I128 | Self::Isize | Self::F32 | Self::F64)
+	}
+
+	/// How many bits it's stored in, which is 64 for sizes and 128-bit integers.
+	pub fn bits(self) -> u32 {
+		match self {
+			Self::I8 | Self::U8 => 8,
+			Self::I16 | Self::U16 => 16,
+			Self::I32 | Self::U32 | Self::F32 => 32,
+			_ => 64,
+		}
+	}
+
+	/// An integer wrapped into the range of this integer type, like `as` does.
+	pub fn wrap(self, i: i64) -> i64 {
+		match self.bits() {
+			64 => i,
+			bits if self.is_signed() => (i << (64 - bits)) >> (64 - bits),
+			bits => i & ((1 << bits) - 1),
+		}
+	}
+
+	/// A float converted to this integer type like `as` does, rounding towards zero and saturating at its bounds.
+	pub fn saturate(self, x: f64) -> i64 {
+		match (self.bits(), self.is_signed()) {
+			(64, true) => x as i64,
+			(64, false) => x as u64 as i64,
+			(bits, true) => (x as i64).clamp(-1 << (bits - 1), (1 << (bits - 1)) - 1),
+			(bits, false) => (x as u64).min((1 << bits) - 1) as i64,
+		}
+	}
 }
 
+/// Compound assignments map to the operator they apply, as in [ItemKind::Assign].
 #[cfg(feature = "syn")]
 impl From<::syn::BinOp> for BinaryOp {
 	fn from(x: ::syn::BinOp) -> Self {
@@ -97,64 +401,146 @@ impl From<::syn::BinOp> for BinaryOp {
 			::syn::BinOp::Ne(_) => Self::Ne,
 			::syn::BinOp::Ge(_) => Self::Ge,
 			::syn::BinOp::Gt(_) => Self::Gt,
-			::syn::BinOp::AddEq(_) => Self::AddEq,
-			::syn::BinOp::SubEq(_) => Self::SubEq,
-			::syn::BinOp::MulEq(_) => Self::MulEq,
-			::syn::BinOp::DivEq(_) => Self::DivEq,
-			::syn::BinOp::RemEq(_) => Self::ModEq,
-			::syn::BinOp::BitXorEq(_) => Self::BxorEq,
-			::syn::BinOp::BitAndEq(_) => Self::BandEq,
-			::syn::BinOp::BitOrEq(_) => Self::BorEq,
-			::syn::BinOp::ShlEq(_) => Self::BshlEq,
-			::syn::BinOp::ShrEq(_) => Self::BshrEq,
+			::syn::BinOp::AddEq(_) => Self::Add,
+			::syn::BinOp::SubEq(_) => Self::Sub,
+			::syn::BinOp::MulEq(_) => Self::Mul,
+			::syn::BinOp::DivEq(_) => Self::Div,
+			::syn::BinOp::RemEq(_) => Self::Mod,
+			::syn::BinOp::BitXorEq(_) => Self::Bxor,
+			::syn::BinOp::BitAndEq(_) => Self::Band,
+			::syn::BinOp::BitOrEq(_) => Self::Bor,
+			::syn::BinOp::ShlEq(_) => Self::Bshl,
+			::syn::BinOp::ShrEq(_) => Self::Bshr,
 		}
 	}
 }
 
-#[derive(Debug)]
-pub enum Item {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Item {
+	pub kind: ItemKind,
+	pub span: Option<Span>,
+}
+
+impl Item {
+	pub fn new(kind: ItemKind, span: Option<Span>) -> Self {
+		Self { kind, span }
+	}
+}
+
+impl From<ItemKind> for Item {
+	fn from(kind: ItemKind) -> Self {
+		Self { kind, span: None }
+	}
+}
+
+impl ItemKind {
+	/// Whether this is one of the `Expr*` variants, which produce a value.
+	pub fn is_expr(&self) -> bool {
+		matches!(
+			self,
+			Self::ExprCall { .. }
+				| Self::ExprBuiltinCall { .. }
+				| Self::ExprIdent(_)
+				| Self::ExprDecimal(_)
+				| Self::ExprInteger(..)
+				| Self::ExprString(_)
+				| Self::ExprBool(_)
+				| Self::ExprClosure { .. }
+				| Self::ExprArray { .. }
+				| Self::ExprTuple { .. }
+				| Self::ExprStruct { .. }
+				| Self::ExprBinary { .. }
+				| Self::ExprUnary { .. }
+				| Self::ExprCast { .. }
+				| Self::ExprWrap { .. }
+				| Self::ExprField { .. }
+				| Self::ExprIndex { .. }
+				| Self::ExprFString { .. }
+				| Self::ExprVariant { .. }
+				| Self::ExprTry(_)
+				| Self::ExprMethodCall { .. }
+		)
+	}
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ItemKind {
 	FunctionDefinition {
 		name: String,
+		// Whether it's visible outside of the file, ie. `pub`. Private functions can be inlined or removed.
+		public: bool,
 		params: Vec<String>,
-		stmts: Vec<Self>,
+		stmts: Vec<Item>,
 	},
 
 	While {
-		condition: Box<Self>,
-		stmts: Vec<Self>,
+		condition: Box<Item>,
+		stmts: Vec<Item>,
 	},
 
 	ForIn {
 		// for {var} in {expr}
 		var: String,
-		expr: Box<Self>,
-		stmts: Vec<Self>
+		expr: Box<Item>,
+		stmts: Vec<Item>
 	},
 
 	ForRange {
 		// for i = 1, 2, 3
+		// Both bounds are inclusive.
 		var: String,
-		min: Box<Self>,
-		max: Box<Self>,
-		jump: Option<Box<Self>>,
-		stmts: Vec<Self>
+		min: Box<Item>,
+		max: Box<Item>,
+		jump: Option<Box<Item>>,
+		stmts: Vec<Item>
 	},
 
 	// C style for loops are equivalent to while.
 
 	IfElif(IfElif),
+	// `if let`, with `stmts` seeing the pattern's bindings. `while let` is a loop around one.
+	IfLet {
+		pattern: Pattern,
+		expr: Box<Item>,
+		stmts: Vec<Item>,
+		else_stmts: Option<Vec<Item>>,
+	},
+
+	// A scope, `do ... end` in Lua. Used as an expression, its value is the last statement if that's an expression.
+	Block {
+		stmts: Vec<Item>,
+	},
 
 	VarSet {
 		name: String,
-		expr: Box<Self>,
+		expr: Box<Item>,
+	},
+	// `place = expr`, or `place op= expr` when there's an `op`. Places are fields and indexes, variables are
+	// set with VarSet. Generators evaluate the place's sub-expressions once, even when it's read back for `op`.
+	Assign {
+		place: Box<Item>,
+		op: Option<BinaryOp>,
+		expr: Box<Item>,
 	},
 	VarDecl {
 		name: String,
-		expr: Box<Self>,
+		expr: Box<Item>,
+	},
+	// A `let` with any other pattern than a name, or without a value.
+	// `let ... else` runs `else_stmts` when the pattern doesn't match, which never continue after the `let`.
+	Let {
+		pattern: Pattern,
+		expr: Option<Box<Item>>,
+		else_stmts: Option<Vec<Item>>,
 	},
 
 	Break,
 	Continue,
+	Return {
+		expr: Option<Box<Item>>,
+	},
 
 	Externs {
 		functions: Vec<String>,
@@ -162,44 +548,112 @@ pub enum Item {
 
 	Mod {
 		name: String,
-		items: Vec<Self>,
+		items: Vec<Item>,
 	},
 
 	// Expressions
 	ExprCall {
-		func: Box<Self>,
-		args: Vec<Self>,
+		func: Box<Item>,
+		args: Vec<Item>,
+	},
+	// A call to a builtin. [crate::resolve] turns calls of a builtin's name into these unless a local or item of
+	// that name hides it, so a function of the same name is never mistaken for the builtin.
+	ExprBuiltinCall {
+		builtin: Builtin,
+		args: Vec<Item>,
 	},
+	// Paths are joined with `::`. See [crate::resolve] for what they refer to.
 	ExprIdent(String),
 
 	ExprDecimal(f64),
-	ExprInteger(i64),
+	// The type is the literal's suffix, if it has one, which tells how to print `u64` values above `i64::MAX`.
+	ExprInteger(i64, Option<NumType>),
 
 	ExprString(String),
 	ExprBool(bool),
 
 	ExprClosure {
 		params: Vec<String>,
-		stmts: Vec<Self>,
+		stmts: Vec<Item>,
 	},
 
 	ExprArray {
-		elements: Vec<Self>,
+		elements: Vec<Item>,
+	},
+
+	ExprTuple {
+		elements: Vec<Item>,
+	},
+
+	// Tuple structs are constructed by calling a function the backend defines for them.
+	ExprStruct {
+		path: String,
+		fields: Vec<(String, Item)>,
 	},
 
 	ExprBinary {
-		lhs: Box<Self>,
-		rhs: Box<Self>,
+		lhs: Box<Item>,
+		rhs: Box<Item>,
 		op: BinaryOp,
 	},
 
+	// References and derefs aren't in the IR, everything but numbers, bools and strings being shared already.
+	ExprUnary {
+		op: UnaryOp,
+		expr: Box<Item>,
+	},
+
+	// `expr as ty`, from a number or a bool. Integers are wrapped into the range of `ty` and floats rounded
+	// towards zero, saturating at its bounds.
+	ExprCast {
+		expr: Box<Item>,
+		ty: NumType,
+	},
+
+	// An integer wrapped into the range of `ty`, which is how casts between integers and arithmetic with strict
+	// integers are lowered. Unlike [ItemKind::ExprCast], `expr` has to be an integer.
+	ExprWrap {
+		expr: Box<Item>,
+		ty: NumType,
+	},
+
+	// `expr.field` of a struct. Tuple fields are indexes, like tuples are arrays.
+	ExprField {
+		expr: Box<Item>,
+		field: String,
+	},
+
+	// `expr[index]`, counting from 0 like in Rust.
+	ExprIndex {
+		expr: Box<Item>,
+		index: Box<Item>,
+	},
+
 	ExprFString {
+		// Literal pieces, one more than there are arguments.
 		strings: Vec<String>,
 
-		// Vector of numbers, which correspond to which value to insert in the string gap.
-		replacements: Vec<u16>,
+		// What goes in each gap between the strings. See [format].
+		args: Vec<format::FormatArg>,
+
+		// Expressions to insert inside of the string, each evaluated once in order.
+		values: Vec<Item>,
+	},
+
+	// `Some(x)`, `None`, `Ok(x)` or `Err(e)`. `value` is only None for `None`.
+	ExprVariant {
+		variant: Variant,
+		value: Option<Box<Item>>,
+	},
+
+	// `expr?`, returning `None` or the `Err` from the enclosing function or closure. Errors aren't converted.
+	ExprTry(Box<Item>),
 
-		// Expressions to insert inside of the string
-		values: Vec<Self>,
+	// `receiver.method(args)`. Generators implement the common methods of `Option` and `Result`, anything
+	// else is up to the host.
+	ExprMethodCall {
+		receiver: Box<Item>,
+		method: String,
+		args: Vec<Item>,
 	},
 }
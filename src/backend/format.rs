@@ -0,0 +1,343 @@
+//! Format strings, as used by `format!` and `println!`.
+//!
+//! [parse] follows the grammar of [std::fmt], turning `"{x:>8.2}"` into the literal pieces around each
+//! argument and a [FormatArg] describing how to render it. Arguments refer to the values of an
+//! [ItemKind::ExprFString](super::ItemKind::ExprFString) by index, whether they were positional, named or
+//! captured from the enclosing scope, so generators don't have to care which.
+
+/// A `{...}` of a format string.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatArg {
+	/// Index of the formatted value.
+	pub value: usize,
+	pub spec: FormatSpec,
+}
+
+/// Everything after the `:` of an argument.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatSpec {
+	pub fill: char,
+	/// Numbers are aligned to the right and everything else to the left if it's not given.
+	pub align: Option<Align>,
+	/// `+`, which prints the sign of positive numbers too.
+	pub sign: bool,
+	/// `#`, which prefixes numbers with their radix and pretty prints debug output.
+	pub alternate: bool,
+	/// `0`, which pads numbers with zeroes after their sign.
+	pub zero: bool,
+	pub width: Option<Count>,
+	pub precision: Option<Count>,
+	pub kind: FormatKind,
+}
+
+impl Default for FormatSpec {
+	fn default() -> Self {
+		Self {
+			fill: ' ',
+			align: None,
+			sign: false,
+			alternate: false,
+			zero: false,
+			width: None,
+			precision: None,
+			kind: FormatKind::Display,
+		}
+	}
+}
+
+impl FormatSpec {
+	/// Whether this is a plain `{}`.
+	pub fn is_default(&self) -> bool {
+		*self == Self::default()
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Align {
+	Left,
+	Center,
+	Right,
+}
+
+/// A width or precision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Count {
+	Is(usize),
+	/// Index of the value holding it, as in `{:1$}` or `{:.*}`.
+	Arg(usize),
+}
+
+/// The formatting trait, picked by the last character of the spec.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatKind {
+	Display,
+	Debug,
+	LowerHex,
+	UpperHex,
+	Octal,
+	Binary,
+	LowerExp,
+	UpperExp,
+}
+
+impl FormatKind {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Display => "",
+			Self::Debug => "?",
+			Self::LowerHex => "x",
+			Self::UpperHex => "X",
+			Self::Octal => "o",
+			Self::Binary => "b",
+			Self::LowerExp => "e",
+			Self::UpperExp => "E",
+		}
+	}
+}
+
+/// Whether a value is printed as a `u64`, whose values above `i64::MAX` are stored as negative integers. Lowering
+/// marks them by wrapping them into their type, which does nothing to a 64-bit value.
+pub fn unsigned(value: &super::Item) -> bool {
+	let ty = match value.kind {
+		super::ItemKind::ExprInteger(i, Some(ty)) if i < 0 => ty,
+		super::ItemKind::ExprWrap { ty, .. } => ty,
+		_ => return false,
+	};
+
+	ty.bits() == 64 && !ty.is_signed()
+}
+
+/// How an argument refers to its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Argument<'a> {
+	/// `{0}`, or `{}` which counts up from the previous implicit one.
+	Index(usize),
+	/// `{name}`, either a named argument or a variable captured from the scope.
+	Name(&'a str),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid format string: {0}")]
+pub struct FormatError(pub String);
+
+/// Splits a format string into its literal pieces and arguments, with one more piece than there are arguments.
+/// `{{` and `}}` are unescaped. Every argument is turned into a value index by `resolve`.
+pub fn parse(
+	format: &str,
+	mut resolve: impl FnMut(Argument<'_>) -> Result<usize, FormatError>,
+) -> Result<(Vec<String>, Vec<FormatArg>), FormatError> {
+	let mut strings = vec![String::new()];
+	let mut args = vec![];
+	let mut next = 0;
+
+	let mut chars = format.char_indices().peekable();
+	while let Some((start, c)) = chars.next() {
+		match c {
+			'{' if chars.next_if(|&(_, c)| c == '{').is_some() => strings.last_mut().unwrap().push('{'),
+			'}' if chars.next_if(|&(_, c)| c == '}').is_some() => strings.last_mut().unwrap().push('}'),
+			'}' => return Err(FormatError("unmatched `}`, use `}}` to print it".into())),
+
+			'{' => {
+				let end = format[start..]
+					.find('}')
+					.map(|end| start + end)
+					.ok_or_else(|| FormatError("expected `}`, use `{{` to print a `{`".into()))?;
+
+				while chars.next_if(|&(i, _)| i <= end).is_some() {}
+
+				args.push(argument(&format[start + 1..end], &mut next, &mut resolve)?);
+				strings.push(String::new());
+			}
+
+			c => strings.last_mut().unwrap().push(c),
+		}
+	}
+
+	Ok((strings, args))
+}
+
+/// Parses the inside of `{...}`.
+fn argument(
+	inner: &str,
+	next: &mut usize,
+	resolve: &mut impl FnMut(Argument<'_>) -> Result<usize, FormatError>,
+) -> Result<FormatArg, FormatError> {
+	let (name, spec) = inner.split_once(':').unwrap_or((inner, ""));
+	let name = name.trim();
+
+	let mut spec = Spec { rest: spec };
+	let mut out = FormatSpec::default();
+
+	// [[fill]align]
+	let mut fill = spec.rest.chars();
+	match (fill.next(), fill.next().and_then(align)) {
+		(Some(c), Some(align)) => {
+			out.fill = c;
+			out.align = Some(align);
+			spec.rest = fill.as_str();
+		}
+		(Some(c), _) if align(c).is_some() => {
+			out.align = align(c);
+			spec.rest = &spec.rest[1..];
+		}
+		_ => (),
+	}
+
+	out.sign = spec.eat("+");
+	// `-` is accepted but unused, like in Rust.
+	if !out.sign {
+		spec.eat("-");
+	}
+
+	out.alternate = spec.eat("#");
+	// A `0` followed by `$` is a width argument, not the flag.
+	if spec.rest.starts_with('0') && !spec.rest[1..].starts_with('$') {
+		spec.eat("0");
+		out.zero = true;
+	}
+
+	out.width = spec.count(resolve)?;
+
+	// `.*` takes the precision from the next implicit argument, before the value itself.
+	if spec.eat(".") {
+		out.precision = match spec.eat("*") {
+			true => Some(Count::Arg(implicit(next, resolve)?)),
+			false => Some(spec.count(resolve)?.ok_or_else(|| FormatError("expected a precision after `.`".into()))?),
+		};
+	}
+
+	out.kind = match spec.rest {
+		"" => FormatKind::Display,
+		"?" | "x?" | "X?" => FormatKind::Debug,
+		"x" => FormatKind::LowerHex,
+		"X" => FormatKind::UpperHex,
+		"o" => FormatKind::Octal,
+		"b" => FormatKind::Binary,
+		"e" => FormatKind::LowerExp,
+		"E" => FormatKind::UpperExp,
+		other => return Err(FormatError(format!("unknown format trait `{other}`"))),
+	};
+
+	let value = match name {
+		"" => implicit(next, resolve)?,
+		name => resolve(reference(name)?)?,
+	};
+
+	Ok(FormatArg { value, spec: out })
+}
+
+/// The position of a `{}`, counting up from the previous one.
+fn implicit(next: &mut usize, resolve: &mut impl FnMut(Argument<'_>) -> Result<usize, FormatError>) -> Result<usize, FormatError> {
+	*next += 1;
+	resolve(Argument::Index(*next - 1))
+}
+
+fn align(c: char) -> Option<Align> {
+	match c {
+		'<' => Some(Align::Left),
+		'^' => Some(Align::Center),
+		'>' => Some(Align::Right),
+		_ => None,
+	}
+}
+
+fn reference(name: &str) -> Result<Argument<'_>, FormatError> {
+	if let Ok(index) = name.parse() {
+		return Ok(Argument::Index(index));
+	}
+
+	let ident = name.strip_prefix("r#").unwrap_or(name);
+	let mut chars = ident.chars();
+	let valid = chars.next().is_some_and(|c| c == '_' || c.is_alphabetic())
+		&& chars.all(|c| c == '_' || c.is_alphanumeric());
+
+	match valid {
+		true => Ok(Argument::Name(ident)),
+		false => Err(FormatError(format!("invalid argument name `{name}`"))),
+	}
+}
+
+struct Spec<'a> {
+	rest: &'a str,
+}
+
+impl Spec<'_> {
+	fn eat(&mut self, prefix: &str) -> bool {
+		match self.rest.strip_prefix(prefix) {
+			Some(rest) => {
+				self.rest = rest;
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// An integer, or an argument followed by `$`.
+	fn count(&mut self, resolve: &mut impl FnMut(Argument<'_>) -> Result<usize, FormatError>) -> Result<Option<Count>, FormatError> {
+		let end = self.rest.find(|c: char| !(c == '_' || c.is_alphanumeric())).unwrap_or(self.rest.len());
+		let (word, rest) = self.rest.split_at(end);
+
+		if let Some(rest) = rest.strip_prefix('$') {
+			self.rest = rest;
+			return Ok(Some(Count::Arg(resolve(reference(word)?)?)));
+		}
+
+		let digits = word.find(|c: char| !c.is_ascii_digit()).unwrap_or(word.len());
+		if digits == 0 {
+			return Ok(None);
+		}
+
+		let count = word[..digits].parse().map_err(|_| FormatError(format!("invalid count `{}`", &word[..digits])))?;
+		self.rest = &self.rest[digits..];
+		Ok(Some(Count::Is(count)))
+	}
+}
+
+/// Writes the argument back as `{value:spec}`, referring to counts by index.
+impl std::fmt::Display for FormatArg {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let count = |count: Count| match count {
+			Count::Is(n) => n.to_string(),
+			Count::Arg(i) => format!("{i}$"),
+		};
+
+		let spec = &self.spec;
+		write!(f, "{{{}", self.value)?;
+		if !spec.is_default() {
+			f.write_str(":")?;
+			if let Some(align) = spec.align {
+				let align = match align {
+					Align::Left => '<',
+					Align::Center => '^',
+					Align::Right => '>',
+				};
+				if spec.fill != ' ' {
+					write!(f, "{}", spec.fill)?;
+				}
+				write!(f, "{align}")?;
+			}
+			if spec.sign {
+				f.write_str("+")?;
+			}
+			if spec.alternate {
+				f.write_str("#")?;
+			}
+			if spec.zero {
+				f.write_str("0")?;
+			}
+			if let Some(width) = spec.width {
+				f.write_str(&count(width))?;
+			}
+			if let Some(precision) = spec.precision {
+				write!(f, ".{}", count(precision))?;
+			}
+			f.write_str(spec.kind.as_str())?;
+		}
+		f.write_str("}")
+	}
+}
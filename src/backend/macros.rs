@@ -0,0 +1,43 @@
+//! Translation of project specific macros.
+//!
+//! The std macros are lowered by the backend itself. Anything else can be registered in a [Macros] table,
+//! usually to call a function of the host, so `log_info!("{} items", n)` can become `Log.info("3 items")`.
+
+use std::collections::HashMap;
+
+/// How a macro is lowered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Macro {
+	/// Parses the arguments as comma separated expressions, passing them to the host function.
+	Call(String),
+	/// Formats the arguments like `format!`, passing the string to the host function.
+	Format(String),
+}
+
+impl Macro {
+	/// The host function, as an absolute path. Both `Log.info` and `Log::info` are accepted.
+	pub fn function(&self) -> String {
+		let (Self::Call(function) | Self::Format(function)) = self;
+		let function = function.strip_prefix("::").unwrap_or(function);
+		format!("::{}", function.replace('.', "::"))
+	}
+}
+
+/// Macros by the path they're called with, like `log_info` or `metrics::record`.
+/// They take precedence over the std macros of the same name.
+#[derive(Debug, Clone, Default)]
+pub struct Macros(HashMap<String, Macro>);
+
+impl Macros {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn insert(&mut self, path: impl Into<String>, rule: Macro) -> Option<Macro> {
+		self.0.insert(path.into(), rule)
+	}
+
+	pub fn get(&self, path: &str) -> Option<&Macro> {
+		self.0.get(path)
+	}
+}
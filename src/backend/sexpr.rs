@@ -0,0 +1,1030 @@
+//! Textual form of the IR, as S-expressions.
+//!
+//! ```text
+//! (fn main ()
+//!     (let x 5)
+//!     (while (< x 10)
+//!         (set x (+ x 1)))
+//!     (call print x))
+//! ```
+//!
+//! Identifiers are bare symbols, `_` marks a missing optional child and `(fn pub name ...)` an exported function.
+//! Destructuring writes the pattern as a list, as in `(let (tuple a _ ..) x)`, and `(declare x)` is a `let`
+//! without a value. Literals in patterns are written as they are and `(or 1 2)` matches either, for
+//! `(if-let pat expr (then ...) (else ...))` and `(let pat expr (else ...))`. `Option` and `Result` variants
+//! are `(some x)`, `(none)`, `(ok x)` and `(err e)`, both as patterns and values. Fields and elements are
+//! `(field p x)` and `(index a 0)`, assigned to with `(set (field p x) 1)` or an operator like `(+= (index a 0) 1)`.
+//! Unary operators are `(neg x)`, `(not b)` and `(bnot i)`, the last two both being `!` in Rust. Casts are
+//! `(as u8 x)`, and `(wrap u8 x)` wraps an integer into the range of its type. `/` of integers is `(// a b)`,
+//! rounding towards zero, while `(/ a b)` is left to the types of its operands. Operators of `u64`s are prefixed
+//! with `u`, as in `(u// a b)` and `(u< a b)`. Calls of the builtins the std
+//! macros are lowered to are `(builtin print x)`, and `(call print x)` calls whatever `print` resolves to.
+//! Integers keep their suffix, as in `1u8`, and unsigned ones are printed as such. Spans aren't printed.
+
+use super::format::{self, FormatArg};
+use super::{BinaryOp, Builtin, IfElif, Item, ItemKind, NumType, Pattern, UnaryOp, Variant};
+
+#[derive(Debug, thiserror::Error)]
+#[error("line {line}: {message}")]
+pub struct ParseError {
+	pub line: usize,
+	pub message: String,
+}
+
+pub fn to_sexpr(items: &[Item]) -> String {
+	let mut printer = Printer {
+		buf: String::new(),
+		indent: 0,
+	};
+
+	for item in items {
+		printer.item(item);
+		printer.buf.push('\n');
+	}
+
+	printer.buf
+}
+
+pub fn from_sexpr(src: &str) -> Result<Vec<Item>, ParseError> {
+	let mut lexer = Lexer {
+		chars: src.chars().peekable(),
+		line: 1,
+	};
+
+	let mut items = vec![];
+	while let Some(sexp) = lexer.sexp()? {
+		items.push(sexp.item()?);
+	}
+
+	Ok(items)
+}
+
+fn variant_tag(variant: Variant) -> &'static str {
+	match variant {
+		Variant::Some => "some",
+		Variant::None => "none",
+		Variant::Ok => "ok",
+		Variant::Err => "err",
+	}
+}
+
+fn tag_variant(tag: &str) -> Option<Variant> {
+	Variant::ALL.into_iter().find(|variant| variant_tag(*variant) == tag)
+}
+
+fn unary_tag(op: UnaryOp) -> &'static str {
+	match op {
+		UnaryOp::Neg => "neg",
+		UnaryOp::Not => "not",
+		UnaryOp::BitNot => "bnot",
+	}
+}
+
+fn tag_unary(tag: &str) -> Option<UnaryOp> {
+	[UnaryOp::Neg, UnaryOp::Not, UnaryOp::BitNot].into_iter().find(|op| unary_tag(*op) == tag)
+}
+
+fn op_symbol(op: &BinaryOp) -> &'static str {
+	match op {
+		BinaryOp::Add => "+",
+		BinaryOp::Sub => "-",
+		BinaryOp::Mul => "*",
+		BinaryOp::Div => "/",
+		BinaryOp::IntDiv => "//",
+		BinaryOp::Mod => "%",
+		BinaryOp::And => "&&",
+		BinaryOp::Or => "||",
+		BinaryOp::Bxor => "^",
+		BinaryOp::Band => "&",
+		BinaryOp::Bor => "|",
+		BinaryOp::Bshl => "<<",
+		BinaryOp::Bshr => ">>",
+		BinaryOp::Eq => "==",
+		BinaryOp::Lt => "<",
+		BinaryOp::Le => "<=",
+		BinaryOp::Ne => "!=",
+		BinaryOp::Ge => ">=",
+		BinaryOp::Gt => ">",
+		BinaryOp::UDiv => "u//",
+		BinaryOp::UMod => "u%",
+		BinaryOp::ULt => "u<",
+		BinaryOp::ULe => "u<=",
+		BinaryOp::UGe => "u>=",
+		BinaryOp::UGt => "u>",
+	}
+}
+
+/// An integer, with the suffix of its type if it has one.
+fn integer(atom: &str) -> Option<ItemKind> {
+	if let Ok(i) = atom.parse::<i64>() {
+		return Some(ItemKind::ExprInteger(i, None));
+	}
+
+	let ty = NumType::ALL.into_iter().filter(|ty| !ty.is_float()).find(|ty| atom.ends_with(ty.name()))?;
+	let digits = &atom[..atom.len() - ty.name().len()];
+	let value = digits.parse::<i64>().ok().or_else(|| digits.parse::<u64>().ok().map(|u| u as i64))?;
+	Some(ItemKind::ExprInteger(value, Some(ty)))
+}
+
+fn symbol_op(sym: &str) -> Option<BinaryOp> {
+	Some(match sym {
+		"+" => BinaryOp::Add,
+		"-" => BinaryOp::Sub,
+		"*" => BinaryOp::Mul,
+		"/" => BinaryOp::Div,
+		"//" => BinaryOp::IntDiv,
+		"%" => BinaryOp::Mod,
+		"&&" => BinaryOp::And,
+		"||" => BinaryOp::Or,
+		"^" => BinaryOp::Bxor,
+		"&" => BinaryOp::Band,
+		"|" => BinaryOp::Bor,
+		"<<" => BinaryOp::Bshl,
+		">>" => BinaryOp::Bshr,
+		"==" => BinaryOp::Eq,
+		"<" => BinaryOp::Lt,
+		"<=" => BinaryOp::Le,
+		"!=" => BinaryOp::Ne,
+		">=" => BinaryOp::Ge,
+		">" => BinaryOp::Gt,
+		"u//" => BinaryOp::UDiv,
+		"u%" => BinaryOp::UMod,
+		"u<" => BinaryOp::ULt,
+		"u<=" => BinaryOp::ULe,
+		"u>=" => BinaryOp::UGe,
+		"u>" => BinaryOp::UGt,
+		_ => return None,
+	})
+}
+
+struct Printer {
+	buf: String,
+	indent: usize,
+}
+
+impl Printer {
+	fn newline(&mut self) {
+		self.buf.push('\n');
+		for _ in 0..self.indent {
+			self.buf.push('\t');
+		}
+	}
+
+	fn open(&mut self, tag: &str) {
+		self.buf.push('(');
+		self.buf.push_str(tag);
+	}
+
+	fn close(&mut self) {
+		self.buf.push(')');
+	}
+
+	fn sym(&mut self, sym: &str) {
+		self.buf.push(' ');
+		self.buf.push_str(sym);
+	}
+
+	fn syms(&mut self, syms: &[String]) {
+		self.buf.push_str(" (");
+		self.buf.push_str(&syms.join(" "));
+		self.buf.push(')');
+	}
+
+	fn strs(&mut self, strs: &[String]) {
+		self.buf.push_str(" (");
+		for (i, s) in strs.iter().enumerate() {
+			if i != 0 {
+				self.buf.push(' ');
+			}
+			self.str(s);
+		}
+		self.buf.push(')');
+	}
+
+	fn str(&mut self, s: &str) {
+		self.buf.push('"');
+		for c in s.chars() {
+			match c {
+				'"' => self.buf.push_str("\\\""),
+				'\\' => self.buf.push_str("\\\\"),
+				'\n' => self.buf.push_str("\\n"),
+				'\r' => self.buf.push_str("\\r"),
+				'\t' => self.buf.push_str("\\t"),
+				c if c.is_control() => self.buf.push_str(&format!("\\u{{{:x}}}", c as u32)),
+				c => self.buf.push(c),
+			}
+		}
+		self.buf.push('"');
+	}
+
+	fn expr(&mut self, item: &Item) {
+		self.buf.push(' ');
+		self.item(item);
+	}
+
+	fn pattern(&mut self, pattern: &Pattern) {
+		self.buf.push(' ');
+		match pattern {
+			Pattern::Ident(name) => self.buf.push_str(name),
+			Pattern::Wildcard => self.buf.push('_'),
+			Pattern::Rest => self.buf.push_str(".."),
+			Pattern::Literal(item) => self.item(item),
+			Pattern::Or(alternatives) => {
+				self.open("or");
+				for alternative in alternatives {
+					self.pattern(alternative);
+				}
+				self.close();
+			}
+			Pattern::Variant { variant, value } => {
+				self.open(variant_tag(*variant));
+				if let Some(value) = value {
+					self.pattern(value);
+				}
+				self.close();
+			}
+			Pattern::Tuple(elements) | Pattern::Slice(elements) => {
+				self.open(if matches!(pattern, Pattern::Tuple(_)) { "tuple" } else { "slice" });
+				for element in elements {
+					self.pattern(element);
+				}
+				self.close();
+			}
+			Pattern::TupleStruct { path, elements } => {
+				self.open("tuple-struct");
+				self.sym(path);
+				for element in elements {
+					self.pattern(element);
+				}
+				self.close();
+			}
+			Pattern::Struct { path, fields } => {
+				self.open("struct");
+				self.sym(path);
+				for (name, pattern) in fields {
+					self.buf.push_str(" (");
+					self.buf.push_str(name);
+					self.pattern(pattern);
+					self.close();
+				}
+				self.close();
+			}
+		}
+	}
+
+	fn exprs(&mut self, items: &[Item]) {
+		for item in items {
+			self.expr(item);
+		}
+	}
+
+	fn stmts(&mut self, stmts: &[Item]) {
+		self.indent += 1;
+		for stmt in stmts {
+			self.newline();
+			self.item(stmt);
+		}
+		self.indent -= 1;
+	}
+
+	fn block(&mut self, tag: &str, stmts: &[Item]) {
+		self.indent += 1;
+		self.newline();
+		self.open(tag);
+		self.stmts(stmts);
+		self.close();
+		self.indent -= 1;
+	}
+
+	fn item(&mut self, item: &Item) {
+		match &item.kind {
+			ItemKind::FunctionDefinition { name, public, params, stmts } => {
+				self.open("fn");
+				if *public {
+					self.sym("pub");
+				}
+				self.sym(name);
+				self.syms(params);
+				self.stmts(stmts);
+				self.close();
+			}
+			ItemKind::While { condition, stmts } => {
+				self.open("while");
+				self.expr(condition);
+				self.stmts(stmts);
+				self.close();
+			}
+			ItemKind::ForIn { var, expr, stmts } => {
+				self.open("for-in");
+				self.sym(var);
+				self.expr(expr);
+				self.stmts(stmts);
+				self.close();
+			}
+			ItemKind::ForRange { var, min, max, jump, stmts } => {
+				self.open("for-range");
+				self.sym(var);
+				self.expr(min);
+				self.expr(max);
+				match jump {
+					Some(jump) => self.expr(jump),
+					None => self.sym("_"),
+				}
+				self.stmts(stmts);
+				self.close();
+			}
+			ItemKind::IfElif(IfElif { condition, stmts, elif, else_stmts }) => {
+				self.open("if");
+				self.expr(condition);
+				self.block("then", stmts);
+				for (cond, stmts) in elif {
+					self.indent += 1;
+					self.newline();
+					self.open("elif");
+					self.expr(cond);
+					self.stmts(stmts);
+					self.close();
+					self.indent -= 1;
+				}
+				if let Some(stmts) = else_stmts {
+					self.block("else", stmts);
+				}
+				self.close();
+			}
+			ItemKind::IfLet { pattern, expr, stmts, else_stmts } => {
+				self.open("if-let");
+				self.pattern(pattern);
+				self.expr(expr);
+				self.block("then", stmts);
+				if let Some(stmts) = else_stmts {
+					self.block("else", stmts);
+				}
+				self.close();
+			}
+			ItemKind::Block { stmts } => {
+				self.open("block");
+				self.stmts(stmts);
+				self.close();
+			}
+			ItemKind::VarSet { name, expr } => {
+				self.open("set");
+				self.sym(name);
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::Assign { place, op, expr } => {
+				match op {
+					Some(op) => self.open(&format!("{}=", op_symbol(op))),
+					None => self.open("set"),
+				}
+				self.expr(place);
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::VarDecl { name, expr } => {
+				self.open("let");
+				self.sym(name);
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::Let { pattern, expr, else_stmts } => {
+				self.open(if expr.is_some() { "let" } else { "declare" });
+				self.pattern(pattern);
+				if let Some(expr) = expr {
+					self.expr(expr);
+				}
+				if let Some(stmts) = else_stmts {
+					self.block("else", stmts);
+				}
+				self.close();
+			}
+			ItemKind::Break => self.buf.push_str("(break)"),
+			ItemKind::Continue => self.buf.push_str("(continue)"),
+			ItemKind::Return { expr } => {
+				self.open("return");
+				if let Some(expr) = expr {
+					self.expr(expr);
+				}
+				self.close();
+			}
+			ItemKind::Externs { functions } => {
+				self.open("externs");
+				for f in functions {
+					self.sym(f);
+				}
+				self.close();
+			}
+			ItemKind::Mod { name, items } => {
+				self.open("mod");
+				self.sym(name);
+				self.stmts(items);
+				self.close();
+			}
+			ItemKind::ExprCall { func, args } => {
+				self.open("call");
+				self.expr(func);
+				self.exprs(args);
+				self.close();
+			}
+			ItemKind::ExprBuiltinCall { builtin, args } => {
+				self.open("builtin");
+				self.sym(builtin.name());
+				self.exprs(args);
+				self.close();
+			}
+			ItemKind::ExprIdent(name) => self.buf.push_str(name),
+			ItemKind::ExprDecimal(value) => self.buf.push_str(&format!("{value:?}")),
+			ItemKind::ExprInteger(value, None) => self.buf.push_str(&value.to_string()),
+			ItemKind::ExprInteger(value, Some(ty)) if ty.is_signed() => self.buf.push_str(&format!("{value}{}", ty.name())),
+			ItemKind::ExprInteger(value, Some(ty)) => self.buf.push_str(&format!("{}{}", *value as u64, ty.name())),
+			ItemKind::ExprString(value) => self.str(value),
+			ItemKind::ExprBool(value) => self.buf.push_str(&value.to_string()),
+			ItemKind::ExprClosure { params, stmts } => {
+				self.open("closure");
+				self.syms(params);
+				self.stmts(stmts);
+				self.close();
+			}
+			ItemKind::ExprArray { elements } => {
+				self.open("array");
+				self.exprs(elements);
+				self.close();
+			}
+			ItemKind::ExprTuple { elements } => {
+				self.open("tuple");
+				self.exprs(elements);
+				self.close();
+			}
+			ItemKind::ExprStruct { path, fields } => {
+				self.open("struct");
+				self.sym(path);
+				for (name, value) in fields {
+					self.buf.push_str(" (");
+					self.buf.push_str(name);
+					self.expr(value);
+					self.close();
+				}
+				self.close();
+			}
+			ItemKind::ExprBinary { lhs, rhs, op } => {
+				self.open(op_symbol(op));
+				self.expr(lhs);
+				self.expr(rhs);
+				self.close();
+			}
+			ItemKind::ExprUnary { op, expr } => {
+				self.open(unary_tag(*op));
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::ExprCast { expr, ty } => {
+				self.open("as");
+				self.sym(ty.name());
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::ExprWrap { expr, ty } => {
+				self.open("wrap");
+				self.sym(ty.name());
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::ExprField { expr, field } => {
+				self.open("field");
+				self.expr(expr);
+				self.sym(field);
+				self.close();
+			}
+			ItemKind::ExprIndex { expr, index } => {
+				self.open("index");
+				self.expr(expr);
+				self.expr(index);
+				self.close();
+			}
+			ItemKind::ExprFString { strings, args, values } => {
+				self.open("fstring");
+				self.strs(strings);
+				self.strs(&args.iter().map(ToString::to_string).collect::<Vec<_>>());
+				self.exprs(values);
+				self.close();
+			}
+			ItemKind::ExprVariant { variant, value } => {
+				self.open(variant_tag(*variant));
+				if let Some(value) = value {
+					self.expr(value);
+				}
+				self.close();
+			}
+			ItemKind::ExprTry(expr) => {
+				self.open("try");
+				self.expr(expr);
+				self.close();
+			}
+			ItemKind::ExprMethodCall { receiver, method, args } => {
+				self.open("method");
+				self.expr(receiver);
+				self.sym(method);
+				self.exprs(args);
+				self.close();
+			}
+		}
+	}
+}
+
+enum Sexp {
+	List(Vec<Sexp>, usize),
+	Atom(String, usize),
+	Str(String, usize),
+}
+
+/// A format argument written as `"{0:>8}"`, with counts referring to values by index.
+fn format_arg(sexp: Sexp) -> Result<FormatArg, ParseError> {
+	let line = sexp.line();
+	let error = |message: String| ParseError { line, message };
+
+	let index = |arg: format::Argument<'_>| match arg {
+		format::Argument::Index(i) => Ok(i),
+		format::Argument::Name(name) => Err(format::FormatError(format!("expected an index, found `{name}`"))),
+	};
+
+	let (strings, mut args) = format::parse(&sexp.str()?, index).map_err(|e| error(e.to_string()))?;
+	match args.pop() {
+		Some(arg) if args.is_empty() && strings.iter().all(String::is_empty) => Ok(arg),
+		_ => Err(error("expected a single format argument".into())),
+	}
+}
+
+impl Sexp {
+	fn line(&self) -> usize {
+		match self {
+			Sexp::List(_, line) | Sexp::Atom(_, line) | Sexp::Str(_, line) => *line,
+		}
+	}
+
+	fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+		Err(ParseError {
+			line: self.line(),
+			message: message.into(),
+		})
+	}
+
+	fn sym(self) -> Result<String, ParseError> {
+		match self {
+			Sexp::Atom(sym, _) => Ok(sym),
+			other => other.error("expected a symbol"),
+		}
+	}
+
+	fn syms(self) -> Result<Vec<String>, ParseError> {
+		match self {
+			Sexp::List(list, _) => list.into_iter().map(Sexp::sym).collect(),
+			other => other.error("expected a list of symbols"),
+		}
+	}
+
+	fn str(self) -> Result<String, ParseError> {
+		match self {
+			Sexp::Str(s, _) => Ok(s),
+			other => other.error("expected a string"),
+		}
+	}
+
+	/// `(name value)`, as in struct fields.
+	fn field<T>(self, value: impl FnOnce(Sexp) -> Result<T, ParseError>) -> Result<(String, T), ParseError> {
+		match self {
+			Sexp::List(items, line) => {
+				let mut list = List { items: items.into_iter(), line };
+				let field = (list.next()?.sym()?, value(list.next()?)?);
+				match list.items.next() {
+					Some(extra) => extra.error("unexpected extra argument to field"),
+					None => Ok(field),
+				}
+			}
+			other => other.error("expected `(field value)`"),
+		}
+	}
+
+	fn pattern(self) -> Result<Pattern, ParseError> {
+		match self {
+			Sexp::Atom(sym, _) if sym == "_" => Ok(Pattern::Wildcard),
+			Sexp::Atom(sym, _) if sym == ".." => Ok(Pattern::Rest),
+			atom @ (Sexp::Atom(..) | Sexp::Str(..)) => match atom.item()?.kind {
+				ItemKind::ExprIdent(name) => Ok(Pattern::Ident(name)),
+				literal => Ok(Pattern::Literal(Box::new(literal.into()))),
+			},
+			Sexp::List(items, line) => {
+				let mut list = List { items: items.into_iter(), line };
+				let patterns = |list: List| list.items.map(Sexp::pattern).collect::<Result<Vec<_>, _>>();
+
+				match list.next()?.sym()?.as_str() {
+					"tuple" => Ok(Pattern::Tuple(patterns(list)?)),
+					"slice" => Ok(Pattern::Slice(patterns(list)?)),
+					"or" => Ok(Pattern::Or(patterns(list)?)),
+					tag if tag_variant(tag).is_some() => {
+						let variant = tag_variant(tag).unwrap();
+						let value = match variant.has_value() {
+							true => Some(Box::new(list.next()?.pattern()?)),
+							false => None,
+						};
+						match list.items.next() {
+							Some(extra) => extra.error(format!("unexpected extra argument to `{tag}`")),
+							None => Ok(Pattern::Variant { variant, value }),
+						}
+					}
+					"tuple-struct" => Ok(Pattern::TupleStruct {
+						path: list.next()?.sym()?,
+						elements: patterns(list)?,
+					}),
+					"struct" => Ok(Pattern::Struct {
+						path: list.next()?.sym()?,
+						fields: list.items.map(|field| field.field(Sexp::pattern)).collect::<Result<_, _>>()?,
+					}),
+					other => list.error(format!("unknown pattern `{other}`")),
+				}
+			}
+		}
+	}
+
+	fn item(self) -> Result<Item, ParseError> {
+		let line = self.line();
+
+		let kind = match self {
+			Sexp::Str(s, _) => ItemKind::ExprString(s),
+			Sexp::Atom(atom, _) => match atom.as_str() {
+				"true" => ItemKind::ExprBool(true),
+				"false" => ItemKind::ExprBool(false),
+				_ => {
+					if let Some(integer) = integer(&atom) {
+						integer
+					} else if atom.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+						match atom.parse::<f64>() {
+							Ok(f) => ItemKind::ExprDecimal(f),
+							Err(_) => return Sexp::Atom(atom, line).error("invalid number"),
+						}
+					} else {
+						ItemKind::ExprIdent(atom)
+					}
+				}
+			},
+			Sexp::List(list, _) => {
+				let mut list = List {
+					items: list.into_iter(),
+					line,
+				};
+
+				let tag = list.next()?.sym()?;
+				let kind = match tag.as_str() {
+					"fn" => {
+						let (public, name) = match list.next()?.sym()? {
+							vis if vis == "pub" => (true, list.next()?.sym()?),
+							name => (false, name),
+						};
+
+						ItemKind::FunctionDefinition {
+							name,
+							public,
+							params: list.next()?.syms()?,
+							stmts: list.rest()?,
+						}
+					}
+					"while" => ItemKind::While {
+						condition: list.expr()?,
+						stmts: list.rest()?,
+					},
+					"for-in" => ItemKind::ForIn {
+						var: list.next()?.sym()?,
+						expr: list.expr()?,
+						stmts: list.rest()?,
+					},
+					"for-range" => ItemKind::ForRange {
+						var: list.next()?.sym()?,
+						min: list.expr()?,
+						max: list.expr()?,
+						jump: list.optional()?,
+						stmts: list.rest()?,
+					},
+					"if" => {
+						let condition = list.expr()?;
+						let stmts = list.tagged("then")?;
+
+						let mut elif = vec![];
+						let mut else_stmts = None;
+						for branch in list.items {
+							let mut branch = match branch {
+								Sexp::List(items, line) => List { items: items.into_iter(), line },
+								other => return other.error("expected `elif` or `else`"),
+							};
+
+							match branch.next()?.sym()?.as_str() {
+								"elif" if else_stmts.is_none() => elif.push((*branch.expr()?, branch.rest()?)),
+								"else" if else_stmts.is_none() => else_stmts = Some(branch.rest()?),
+								other => return branch.error(format!("unexpected `{other}` in if")),
+							}
+						}
+
+						return Ok(ItemKind::IfElif(IfElif { condition, stmts, elif, else_stmts }).into());
+					}
+					"if-let" => {
+						let pattern = list.next()?.pattern()?;
+						let expr = list.expr()?;
+						let stmts = list.tagged("then")?;
+						let else_stmts = list.else_stmts()?;
+						ItemKind::IfLet { pattern, expr, stmts, else_stmts }
+					}
+					"block" => ItemKind::Block { stmts: list.rest()? },
+					"set" => match list.next()? {
+						Sexp::Atom(name, _) => ItemKind::VarSet { name, expr: list.expr()? },
+						place => ItemKind::Assign { place: Box::new(place.item()?), op: None, expr: list.expr()? },
+					},
+					"as" => ItemKind::ExprCast {
+						ty: list.num_type()?,
+						expr: list.expr()?,
+					},
+					"wrap" => ItemKind::ExprWrap {
+						ty: list.num_type()?,
+						expr: list.expr()?,
+					},
+					"field" => ItemKind::ExprField {
+						expr: list.expr()?,
+						field: list.next()?.sym()?,
+					},
+					"index" => ItemKind::ExprIndex {
+						expr: list.expr()?,
+						index: list.expr()?,
+					},
+					"let" => {
+						let pattern = list.next()?.pattern()?;
+						let expr = list.expr()?;
+						match (pattern, list.else_stmts()?) {
+							(Pattern::Ident(name), None) => ItemKind::VarDecl { name, expr },
+							(pattern, else_stmts) => ItemKind::Let { pattern, expr: Some(expr), else_stmts },
+						}
+					}
+					"declare" => ItemKind::Let {
+						pattern: list.next()?.pattern()?,
+						expr: None,
+						else_stmts: None,
+					},
+					"break" => ItemKind::Break,
+					"continue" => ItemKind::Continue,
+					"return" => ItemKind::Return {
+						expr: list.items.next().map(Sexp::item).transpose()?.map(Box::new),
+					},
+					"externs" => ItemKind::Externs {
+						functions: list.items.by_ref().map(Sexp::sym).collect::<Result<_, _>>()?,
+					},
+					"mod" => ItemKind::Mod {
+						name: list.next()?.sym()?,
+						items: list.rest()?,
+					},
+					"call" => ItemKind::ExprCall {
+						func: list.expr()?,
+						args: list.rest()?,
+					},
+					"builtin" => ItemKind::ExprBuiltinCall {
+						builtin: list.builtin()?,
+						args: list.rest()?,
+					},
+					"closure" => ItemKind::ExprClosure {
+						params: list.next()?.syms()?,
+						stmts: list.rest()?,
+					},
+					"array" => ItemKind::ExprArray { elements: list.rest()? },
+					"tuple" => ItemKind::ExprTuple { elements: list.rest()? },
+					"struct" => ItemKind::ExprStruct {
+						path: list.next()?.sym()?,
+						fields: list.items.by_ref().map(|field| field.field(Sexp::item)).collect::<Result<_, _>>()?,
+					},
+					"fstring" => ItemKind::ExprFString {
+						strings: match list.next()? {
+							Sexp::List(strings, _) => strings.into_iter().map(Sexp::str).collect::<Result<_, _>>()?,
+							other => return other.error("expected a list of strings"),
+						},
+						args: match list.next()? {
+							Sexp::List(args, _) => args.into_iter().map(format_arg).collect::<Result<_, _>>()?,
+							other => return other.error("expected a list of format arguments"),
+						},
+						values: list.rest()?,
+					},
+					"try" => ItemKind::ExprTry(list.expr()?),
+					"method" => ItemKind::ExprMethodCall {
+						receiver: list.expr()?,
+						method: list.next()?.sym()?,
+						args: list.rest()?,
+					},
+					tag if tag_unary(tag).is_some() => ItemKind::ExprUnary {
+						op: tag_unary(tag).unwrap(),
+						expr: list.expr()?,
+					},
+					tag if tag_variant(tag).is_some() => {
+						let variant = tag_variant(tag).unwrap();
+						ItemKind::ExprVariant {
+							variant,
+							value: if variant.has_value() { Some(list.expr()?) } else { None },
+						}
+					}
+					op => match (symbol_op(op), op.strip_suffix('=').and_then(symbol_op)) {
+						(Some(op), _) => ItemKind::ExprBinary {
+							lhs: list.expr()?,
+							rhs: list.expr()?,
+							op,
+						},
+						(None, Some(op)) => ItemKind::Assign {
+							place: list.expr()?,
+							op: Some(op),
+							expr: list.expr()?,
+						},
+						_ => return list.error(format!("unknown form `{op}`")),
+					},
+				};
+
+				if let Some(extra) = list.items.next() {
+					return extra.error(format!("unexpected extra argument to `{tag}`"));
+				}
+
+				kind
+			}
+		};
+
+		Ok(kind.into())
+	}
+}
+
+struct List {
+	items: std::vec::IntoIter<Sexp>,
+	line: usize,
+}
+
+impl List {
+	fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+		Err(ParseError {
+			line: self.line,
+			message: message.into(),
+		})
+	}
+
+	fn next(&mut self) -> Result<Sexp, ParseError> {
+		match self.items.next() {
+			Some(sexp) => Ok(sexp),
+			None => self.error("missing argument"),
+		}
+	}
+
+	fn num_type(&mut self) -> Result<NumType, ParseError> {
+		let name = self.next()?.sym()?;
+		match NumType::parse(&name) {
+			Some(ty) => Ok(ty),
+			None => self.error(format!("unknown number type `{name}`")),
+		}
+	}
+
+	fn builtin(&mut self) -> Result<Builtin, ParseError> {
+		let name = self.next()?.sym()?;
+		match Builtin::parse(&name) {
+			Some(builtin) => Ok(builtin),
+			None => self.error(format!("unknown builtin `{name}`")),
+		}
+	}
+
+	fn expr(&mut self) -> Result<Box<Item>, ParseError> {
+		Ok(Box::new(self.next()?.item()?))
+	}
+
+	fn optional(&mut self) -> Result<Option<Box<Item>>, ParseError> {
+		match self.next()? {
+			Sexp::Atom(sym, _) if sym == "_" => Ok(None),
+			sexp => Ok(Some(Box::new(sexp.item()?))),
+		}
+	}
+
+	/// Remaining items, which are consumed.
+	fn rest(&mut self) -> Result<Vec<Item>, ParseError> {
+		self.items.by_ref().map(Sexp::item).collect()
+	}
+
+	/// A trailing `(else stmts...)`, if there's anything left.
+	fn else_stmts(&mut self) -> Result<Option<Vec<Item>>, ParseError> {
+		match self.items.as_slice() {
+			[] => Ok(None),
+			_ => self.tagged("else").map(Some),
+		}
+	}
+
+	/// `({tag} stmts...)`
+	fn tagged(&mut self, tag: &str) -> Result<Vec<Item>, ParseError> {
+		match self.next()? {
+			Sexp::List(items, line) => {
+				let mut list = List { items: items.into_iter(), line };
+				match list.next()? {
+					Sexp::Atom(sym, _) if sym == tag => list.rest(),
+					other => other.error(format!("expected `{tag}`")),
+				}
+			}
+			other => other.error(format!("expected `({tag} ...)`")),
+		}
+	}
+}
+
+struct Lexer<'a> {
+	chars: std::iter::Peekable<std::str::Chars<'a>>,
+	line: usize,
+}
+
+impl Lexer<'_> {
+	fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+		Err(ParseError {
+			line: self.line,
+			message: message.into(),
+		})
+	}
+
+	fn skip_whitespace(&mut self) {
+		while let Some(&c) = self.chars.peek() {
+			match c {
+				'\n' => self.line += 1,
+				';' => {
+					// Comment until end of line
+					while self.chars.next_if(|&c| c != '\n').is_some() {}
+					continue;
+				}
+				c if c.is_whitespace() => (),
+				_ => break,
+			}
+			self.chars.next();
+		}
+	}
+
+	fn sexp(&mut self) -> Result<Option<Sexp>, ParseError> {
+		self.skip_whitespace();
+
+		let line = self.line;
+		let Some(c) = self.chars.next() else {
+			return Ok(None);
+		};
+
+		match c {
+			'(' => {
+				let mut list = vec![];
+				loop {
+					self.skip_whitespace();
+					match self.chars.peek() {
+						Some(')') => {
+							self.chars.next();
+							break;
+						}
+						Some(_) => list.push(self.sexp()?.unwrap()),
+						None => return self.error("unclosed `(`"),
+					}
+				}
+				Ok(Some(Sexp::List(list, line)))
+			}
+			')' => self.error("unexpected `)`"),
+			'"' => {
+				let mut s = String::new();
+				loop {
+					match self.chars.next() {
+						Some('"') => break,
+						Some('\\') => match self.chars.next() {
+							Some('n') => s.push('\n'),
+							Some('r') => s.push('\r'),
+							Some('t') => s.push('\t'),
+							Some('u') => {
+								let mut hex = String::new();
+								if self.chars.next() != Some('{') {
+									return self.error("expected `{` after `\\u`");
+								}
+								for c in self.chars.by_ref() {
+									if c == '}' {
+										break;
+									}
+									hex.push(c);
+								}
+								match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+									Some(c) => s.push(c),
+									None => return self.error(format!("invalid unicode escape `{hex}`")),
+								}
+							}
+							Some(c) => s.push(c),
+							None => return self.error("unterminated string"),
+						},
+						Some(c) => {
+							if c == '\n' {
+								self.line += 1;
+							}
+							s.push(c)
+						}
+						None => return self.error("unterminated string"),
+					}
+				}
+				Ok(Some(Sexp::Str(s, line)))
+			}
+			c => {
+				let mut atom = String::from(c);
+				while let Some(c) = self.chars.next_if(|&c| !c.is_whitespace() && !matches!(c, '(' | ')' | '"' | ';')) {
+					atom.push(c);
+				}
+				Ok(Some(Sexp::Atom(atom, line)))
+			}
+		}
+	}
+}
@@ -0,0 +1,83 @@
+/// Identifies a source file registered in a [SourceMap].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileId(pub u32);
+
+/// Line is 1-indexed, column is 0-indexed (same as proc_macro2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineColumn {
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+	pub file: FileId,
+
+	// Byte range inside of the file
+	pub start: usize,
+	pub end: usize,
+
+	pub lo: LineColumn,
+	pub hi: LineColumn,
+}
+
+impl Span {
+	/// Smallest span covering both `self` and `other`.
+	/// Spans from different files can't be joined, so `self` is returned as is.
+	pub fn join(self, other: Span) -> Span {
+		if self.file != other.file {
+			return self;
+		}
+
+		Span {
+			file: self.file,
+			start: self.start.min(other.start),
+			end: self.end.max(other.end),
+			lo: if self.start <= other.start { self.lo } else { other.lo },
+			hi: if self.end >= other.end { self.hi } else { other.hi },
+		}
+	}
+}
+
+#[derive(Debug)]
+struct SourceFile {
+	path: String,
+	contents: String,
+}
+
+/// Maps [FileId]s to the path and contents of every file given to a backend.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+	files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn add(&mut self, path: impl Into<String>, contents: impl Into<String>) -> FileId {
+		self.files.push(SourceFile {
+			path: path.into(),
+			contents: contents.into(),
+		});
+
+		FileId(self.files.len() as u32 - 1)
+	}
+
+	pub fn path(&self, file: FileId) -> Option<&str> {
+		self.files.get(file.0 as usize).map(|f| f.path.as_str())
+	}
+
+	pub fn contents(&self, file: FileId) -> Option<&str> {
+		self.files.get(file.0 as usize).map(|f| f.contents.as_str())
+	}
+
+	/// Source text a span covers.
+	pub fn snippet(&self, span: &Span) -> Option<&str> {
+		self.contents(span.file)?.get(span.start..span.end)
+	}
+}
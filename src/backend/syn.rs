@@ -1,4 +1,8 @@
+use syn::ext::IdentExt;
 use syn::parse::Parser;
+use syn::spanned::Spanned;
+
+use super::{Builtin, CharRepr, Item, ItemKind, NumType, Pattern, Variant, TEMPORARY};
 
 #[derive(Debug)]
 pub struct SynBackend {
@@ -6,385 +10,1366 @@ pub struct SynBackend {
 }
 
 impl super::Ast for SynBackend {
-	fn generate(code: impl AsRef<[u8]>) -> super::Result<Self> {
+	fn generate_with(code: impl AsRef<[u8]>, options: &super::Options) -> super::Result<Self> {
 		let code = code.as_ref();
 		let code = std::str::from_utf8(code).unwrap();
 
 		let syn_ast = syn::parse_file(code)?;
 
-		fn syn_item_to_item(item: syn::Item) -> super::Item {
-			match item {
-				syn::Item::Fn(syn::ItemFn { sig, block, .. }) => {
-					if let Some(syn::Abi { name: _, .. }) = sig.abi {
-						return super::Item::Externs {
-							functions: vec![sig.ident.to_string()],
-						};
-					}
+		let lower = Lower {
+			file: options.file,
+			macros: &options.macros,
+			strict: options.strict_integers,
+			chars: options.chars,
+			errors: Default::default(),
+			types: std::cell::RefCell::new(vec![Default::default()]),
+		};
+		let nodes: Vec<super::Item> = syn_ast
+			.items
+			.into_iter()
+			.filter_map(|item| lower.syn_item_to_item(item))
+			.collect();
 
-					let param_names: Vec<String> = sig
-						.inputs
-						.into_iter()
-						.map(|arg| match arg {
-							syn::FnArg::Receiver(_) => String::from("self"),
-							syn::FnArg::Typed(data) => match data.pat.as_ref() {
-								syn::Pat::Ident(name) => name.ident.to_string(),
-								_ => todo!(),
-							},
-						})
-						.collect();
+		let errors = lower.errors.into_inner().into_iter().reduce(|mut errors, error| {
+			errors.combine(error);
+			errors
+		});
+		if let Some(errors) = errors {
+			return Err(errors.into());
+		}
+
+		Ok(SynBackend { items: nodes })
+	}
+
+	fn from_items(items: Vec<super::Item>) -> Self {
+		SynBackend { items }
+	}
+
+	fn items(&self) -> &Vec<super::Item> {
+		&self.items
+	}
+}
+
+/// State shared while lowering a single file.
+struct Lower<'a> {
+	file: super::FileId,
+	macros: &'a super::Macros,
+	// See [super::Options::strict_integers].
+	strict: bool,
+	// See [super::Options::chars].
+	chars: CharRepr,
+	// Errors in the file that syn accepts, like invalid format strings.
+	errors: std::cell::RefCell<Vec<syn::Error>>,
+	// Types of the locals in the function being lowered, as far as they're known, with a frame for each scope.
+	// A local of unknown type is `None`, hiding any it shadows. See [Lower::ty].
+	types: std::cell::RefCell<Vec<std::collections::HashMap<String, Option<Ty>>>>,
+}
+
+/// The little that's known about types, enough to tell apart what Rust spells the same, like `!` of a bool
+/// and of an integer. Only primitives are tracked, from annotations, literals and casts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+	Bool,
+	// Numbers whose exact type is left for rustc to infer, like unsuffixed literals.
+	Int,
+	Float,
+	Num(NumType),
+}
+
+impl Ty {
+	fn of(ty: &syn::Type) -> Option<Ty> {
+		match ty {
+			syn::Type::Reference(syn::TypeReference { elem, .. }) | syn::Type::Paren(syn::TypeParen { elem, .. }) => Ty::of(elem),
+			syn::Type::Path(syn::TypePath { qself: None, path }) => match path.get_ident()?.to_string().as_str() {
+				"bool" => Some(Ty::Bool),
+				name => NumType::parse(name).map(Ty::Num),
+			},
+			_ => None,
+		}
+	}
+
+	fn is_int(self) -> bool {
+		match self {
+			Ty::Int => true,
+			Ty::Num(ty) => !ty.is_float(),
+			Ty::Bool | Ty::Float => false,
+		}
+	}
+
+	/// The type of an operation on both, which is the more precise one.
+	fn merge(a: Option<Ty>, b: Option<Ty>) -> Option<Ty> {
+		match (a, b) {
+			(Some(Ty::Num(ty)), _) | (_, Some(Ty::Num(ty))) => Some(Ty::Num(ty)),
+			(a, b) => a.or(b),
+		}
+	}
+
+	/// The unsigned type whose bits `{:x}`, `{:o}` and `{:b}` print a negative value of this type as, when it's
+	/// narrower than Lua's integers. Integers of no particular type are `i32`, like in Rust.
+	fn radix(ty: Option<Ty>) -> Option<NumType> {
+		match ty? {
+			Ty::Int | Ty::Num(NumType::I32) => Some(NumType::U32),
+			Ty::Num(NumType::I16) => Some(NumType::U16),
+			Ty::Num(NumType::I8) => Some(NumType::U8),
+			_ => None,
+		}
+	}
+
+	/// The type if it's one whose values above `i64::MAX` are stored as negative integers, so they need unsigned
+	/// division, comparisons and formatting. `usize` is left out, since it's in every comparison of indexes and
+	/// lengths and those never get that large.
+	fn unsigned(ty: Option<Ty>) -> Option<NumType> {
+		match ty? {
+			Ty::Num(ty @ (NumType::U64 | NumType::U128)) => Some(ty),
+			_ => None,
+		}
+	}
+
+	/// The integer type results of arithmetic are wrapped into with strict integers. 64-bit ones already wrap.
+	fn wrapping(ty: Option<Ty>) -> Option<NumType> {
+		match ty {
+			Some(Ty::Num(ty)) if !ty.is_float() && ty.bits() < 64 => Some(ty),
+			_ => None,
+		}
+	}
+}
+
+impl Lower<'_> {
+	fn span(&self, span: proc_macro2::Span) -> Option<super::Span> {
+		let (lo, hi) = (span.start(), span.end());
 
-					super::Item::FunctionDefinition {
-						name: sig.ident.to_string(),
-						params: param_names,
-						stmts: block.stmts.into_iter().map(stmt_to_item).collect(),
+		// Call site spans (eg. tokens that were never in the file) have no location.
+		if lo.line == 0 {
+			return None;
+		}
+
+		let range = span.byte_range();
+		Some(super::Span {
+			file: self.file,
+			start: range.start,
+			end: range.end,
+			lo: super::LineColumn {
+				line: lo.line,
+				column: lo.column,
+			},
+			hi: super::LineColumn {
+				line: hi.line,
+				column: hi.column,
+			},
+		})
+	}
+
+	/// Lowers an item, or returns None for ones that only exist at compile time, like type definitions.
+	fn syn_item_to_item(&self, item: syn::Item) -> Option<Item> {
+		let span = self.span(item.span());
+
+		let kind = match item {
+			syn::Item::Fn(syn::ItemFn { vis, sig, block, .. }) => {
+				if let Some(syn::Abi { name: _, .. }) = sig.abi {
+					return Some(Item::new(
+						ItemKind::Externs {
+							functions: vec![sig.ident.unraw().to_string()],
+						},
+						span,
+					));
+				}
+
+				let pats = sig.inputs.into_iter().map(|arg| match arg {
+					syn::FnArg::Receiver(receiver) => {
+						let ident = syn::Ident::new("self", receiver.self_token.span);
+						syn::Pat::Ident(syn::PatIdent { attrs: vec![], by_ref: None, mutability: None, ident, subpat: None })
 					}
+					syn::FnArg::Typed(data) => syn::Pat::Type(data),
+				});
+
+				// Locals of the enclosing function aren't visible in this one.
+				let outer = self.types.replace(vec![Default::default()]);
+				let (params, mut stmts) = self.params(pats);
+
+				stmts.extend(match sig.output {
+					syn::ReturnType::Default => self.stmts(block.stmts),
+					syn::ReturnType::Type(..) => self.body(block.stmts),
+				});
+				self.types.replace(outer);
+
+				ItemKind::FunctionDefinition {
+					name: sig.ident.unraw().to_string(),
+					public: !matches!(vis, syn::Visibility::Inherited),
+					params,
+					stmts,
 				}
+			}
 
-				syn::Item::ForeignMod(syn::ItemForeignMod { abi: _, items, .. }) => {
-					// TODO: Different behavior with "C" abi versus no abi.
-					// No abi / "lua" / "lang" abi just defines an extern _G function?
-
-					println!("foreignmod");
-
-					let mut funcs = vec![];
-					for item in items {
-						match item {
-							syn::ForeignItem::Fn(syn::ForeignItemFn { sig, attrs: _, .. }) => {
-								let name = sig.ident.to_string();
-								funcs.push(name);
-							},
-
-							syn::ForeignItem::Verbatim(data) => {
-								match syn::parse2::<syn::Item>(data) {
-									Ok(syn::Item::Fn(syn::ItemFn { sig, .. })) => {
-										funcs.push(sig.ident.to_string());
-									},
-									other => todo!("{:#?}", other)
-								}
-							},
+			// Tuple structs are constructed like a call, so they get a function returning the tuple.
+			syn::Item::Struct(syn::ItemStruct { vis, ident, fields: syn::Fields::Unnamed(fields), .. }) => {
+				let params: Vec<String> = (0..fields.unnamed.len()).map(|i| format!("_{i}")).collect();
+				let elements = params.iter().map(|param| Item::new(ItemKind::ExprIdent(param.clone()), span)).collect();
+				let tuple = Item::new(ItemKind::ExprTuple { elements }, span);
+
+				ItemKind::FunctionDefinition {
+					name: ident.unraw().to_string(),
+					public: !matches!(vis, syn::Visibility::Inherited),
+					params,
+					stmts: vec![Item::new(ItemKind::Return { expr: Some(Box::new(tuple)) }, span)],
+				}
+			}
+
+			syn::Item::Struct(_) | syn::Item::Enum(_) | syn::Item::Type(_) => return None,
 
-							other => {
-								dbg!(other);
-								todo!()
+			syn::Item::ForeignMod(syn::ItemForeignMod { abi: _, items, .. }) => {
+				// TODO: Different behavior with "C" abi versus no abi.
+				// No abi / "lua" / "lang" abi just defines an extern _G function?
+
+				let mut funcs = vec![];
+				for item in items {
+					match item {
+						syn::ForeignItem::Fn(syn::ForeignItemFn { sig, attrs: _, .. }) => {
+							let name = sig.ident.unraw().to_string();
+							funcs.push(name);
+						},
+
+						syn::ForeignItem::Verbatim(data) => {
+							match syn::parse2::<syn::Item>(data) {
+								Ok(syn::Item::Fn(syn::ItemFn { sig, .. })) => {
+									funcs.push(sig.ident.unraw().to_string());
+								},
+								other => todo!("{:#?}", other)
 							}
+						},
+
+						other => {
+							dbg!(other);
+							todo!()
 						}
 					}
+				}
+
+				ItemKind::Externs { functions: funcs }
+			}
+
+			syn::Item::Mod(syn::ItemMod {
+				ident,
+				content: Some((_, v)),
+				..
+			}) => ItemKind::Mod {
+				name: ident.unraw().to_string(),
+				items: v.into_iter().filter_map(|item| self.syn_item_to_item(item)).collect(),
+			},
+
+			_ => todo!(),
+		};
 
-					super::Item::Externs { functions: funcs }
+		Some(Item::new(kind, span))
+	}
+
+	/// Lowers a pattern. `mut` and `ref` make no difference once every binding is a local.
+	fn pattern(&self, pat: syn::Pat) -> Pattern {
+		let patterns = |pats: syn::punctuated::Punctuated<syn::Pat, syn::Token![,]>| {
+			pats.into_iter().map(|pat| self.pattern(pat)).collect()
+		};
+		let path = |path: &syn::Path| path.segments.iter().map(|x| x.ident.unraw().to_string()).collect::<Vec<_>>().join("::");
+
+		match pat {
+			syn::Pat::Ident(syn::PatIdent { ident, subpat: None, .. }) if ident == "None" => {
+				Pattern::Variant { variant: Variant::None, value: None }
+			}
+			syn::Pat::Path(syn::PatPath { path, .. }) if variant(&path) == Some(Variant::None) => {
+				Pattern::Variant { variant: Variant::None, value: None }
+			}
+			syn::Pat::Ident(syn::PatIdent { ident, subpat: None, .. }) => {
+				let name = ident.unraw().to_string();
+				self.declare(&name, None);
+				Pattern::Ident(name)
+			}
+			syn::Pat::Wild(_) => Pattern::Wildcard,
+			syn::Pat::Rest(_) => Pattern::Rest,
+			syn::Pat::Tuple(syn::PatTuple { elems, .. }) => Pattern::Tuple(patterns(elems)),
+			syn::Pat::TupleStruct(syn::PatTupleStruct { path: p, pat, .. }) if variant(&p).is_some_and(Variant::has_value) => {
+				let span = pat.span();
+				let mut elements = patterns(pat.elems);
+				let value = match elements.pop() {
+					Some(Pattern::Rest) if elements.is_empty() => Pattern::Wildcard,
+					Some(value) if elements.is_empty() => value,
+					_ => {
+						self.errors.borrow_mut().push(syn::Error::new(span, "expected a single pattern"));
+						Pattern::Wildcard
+					}
+				};
+				Pattern::Variant { variant: variant(&p).unwrap(), value: Some(Box::new(value)) }
+			}
+			syn::Pat::TupleStruct(syn::PatTupleStruct { path: p, pat, .. }) => Pattern::TupleStruct {
+				path: path(&p),
+				elements: patterns(pat.elems),
+			},
+			syn::Pat::Struct(syn::PatStruct { path: p, fields, .. }) => Pattern::Struct {
+				path: path(&p),
+				fields: fields
+					.into_iter()
+					.filter_map(|field| match field.member {
+						syn::Member::Named(name) => Some((name.unraw().to_string(), self.pattern(*field.pat))),
+						syn::Member::Unnamed(index) => {
+							self.errors.borrow_mut().push(syn::Error::new(index.span, "tuple struct fields can't be matched by index"));
+							None
+						}
+					})
+					.collect(),
+			},
+			syn::Pat::Slice(syn::PatSlice { elems, .. }) => Pattern::Slice(patterns(elems)),
+			syn::Pat::Lit(syn::PatLit { expr, .. }) => self.literal_pattern(*expr),
+			syn::Pat::Or(syn::PatOr { cases, .. }) => {
+				let span = cases.span();
+				let alternatives = cases.into_iter().map(|pat| self.pattern(pat)).collect::<Vec<_>>();
+				if alternatives.iter().any(|pattern| !pattern.bindings().is_empty()) {
+					self.errors.borrow_mut().push(syn::Error::new(span, "`|` patterns can't bind anything"));
 				}
+				Pattern::Or(alternatives)
+			}
 
-				syn::Item::Mod(syn::ItemMod {
-					ident,
-					content: Some((_, v)),
-					..
-				}) => super::Item::Mod {
-					name: ident.to_string(),
-					items: v.into_iter().map(syn_item_to_item).collect(),
-				},
+			// Types and references don't change what gets bound.
+			syn::Pat::Type(syn::PatType { pat, .. }) | syn::Pat::Reference(syn::PatReference { pat, .. }) => self.pattern(*pat),
 
-				_ => todo!(),
+			other => {
+				let message = match other {
+					syn::Pat::Ident(_) => "`@` bindings are not supported",
+					_ => "this pattern is not supported",
+				};
+				self.errors.borrow_mut().push(syn::Error::new(other.span(), message));
+				Pattern::Wildcard
 			}
 		}
+	}
 
-		fn stmt_to_item(stmt: syn::Stmt) -> super::Item {
-			match stmt {
-				syn::Stmt::Item(item) => syn_item_to_item(item),
-				syn::Stmt::Local(local) => match local.pat {
-					syn::Pat::Ident(name) => super::Item::VarDecl {
-						name: name.ident.to_string(),
-						expr: Box::new(expr_to_item(*local.init.unwrap().1)),
-					},
-					_ => todo!(),
-				},
-				syn::Stmt::Expr(expr) => expr_to_item(expr),
-				syn::Stmt::Semi(expr, _) => expr_to_item(expr),
+	/// A pattern that always matches, as in function parameters and plain `let`s.
+	fn irrefutable(&self, pat: syn::Pat) -> Pattern {
+		let span = pat.span();
+		let pattern = self.pattern(pat);
+		if pattern.is_refutable() {
+			let message = "refutable patterns are only supported in `if let`, `while let` and `let ... else`";
+			self.errors.borrow_mut().push(syn::Error::new(span, message));
+		}
+		pattern
+	}
+
+	/// A literal in a pattern, which may be negated.
+	fn literal_pattern(&self, expr: syn::Expr) -> Pattern {
+		let span = expr.span();
+		let (negated, expr) = match expr {
+			syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => (true, *expr),
+			expr => (false, expr),
+		};
+
+		let mut literal = match expr {
+			syn::Expr::Lit(_) => self.expr_to_item(expr),
+			_ => {
+				self.errors.borrow_mut().push(syn::Error::new(span, "only literals are supported in patterns"));
+				return Pattern::Wildcard;
+			}
+		};
+		if negated {
+			match &mut literal.kind {
+				ItemKind::ExprInteger(i, _) => *i = -*i,
+				ItemKind::ExprDecimal(f) => *f = -*f,
+				_ => self.errors.borrow_mut().push(syn::Error::new(span, "only numbers can be negated")),
+			}
+		}
+		literal.span = self.span(span);
+
+		match literal.kind {
+			// A byte string lowered to an array with [CharRepr::Code] matches like a slice of its bytes.
+			ItemKind::ExprArray { elements } => {
+				Pattern::Slice(elements.into_iter().map(|byte| Pattern::Literal(Box::new(byte))).collect())
+			}
+			_ => Pattern::Literal(Box::new(literal)),
+		}
+	}
+
+	/// The name a pattern is bound to, along with a `let` destructuring it if it's more than a name.
+	/// `name` is used for patterns that don't have one.
+	fn binding(&self, pat: syn::Pat, name: String) -> (String, Option<Item>) {
+		let span = self.span(pat.span());
+		let ty = match &pat {
+			syn::Pat::Type(syn::PatType { ty, .. }) => Ty::of(ty),
+			_ => None,
+		};
+
+		match self.irrefutable(pat) {
+			Pattern::Ident(name) => {
+				self.declare(&name, ty);
+				(name, None)
+			}
+			Pattern::Wildcard => ("_".into(), None),
+			pattern => {
+				let expr = Some(Box::new(Item::new(ItemKind::ExprIdent(name.clone()), span)));
+				(name, Some(Item::new(ItemKind::Let { pattern, expr, else_stmts: None }, span)))
+			}
+		}
+	}
+
+	/// Remembers the type of a variable in the innermost scope, hiding the one of any variable it shadows.
+	fn declare(&self, name: &str, ty: Option<Ty>) {
+		if let Some(scope) = self.types.borrow_mut().last_mut() {
+			scope.insert(name.to_owned(), ty);
+		}
+	}
+
+	/// Lowers something with a scope of its own, whose variables are forgotten after it.
+	fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+		self.types.borrow_mut().push(Default::default());
+		let result = f();
+		self.types.borrow_mut().pop();
+		result
+	}
+
+	/// What's known about the type of a variable.
+	fn local_ty(&self, name: &str) -> Option<Ty> {
+		self.types.borrow().iter().rev().find_map(|scope| scope.get(name).copied())?
+	}
+
+	/// What's known about the type of an expression.
+	fn ty(&self, expr: &syn::Expr) -> Option<Ty> {
+		match expr {
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Bool(_), .. }) => Some(Ty::Bool),
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(i), .. }) => Some(NumType::parse(i.suffix()).map_or(Ty::Int, Ty::Num)),
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Float(f), .. }) => Some(NumType::parse(f.suffix()).map_or(Ty::Float, Ty::Num)),
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Char(_), .. }) if self.chars == CharRepr::Code => Some(Ty::Num(NumType::U32)),
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Byte(_), .. }) if self.chars == CharRepr::Code => Some(Ty::Num(NumType::U8)),
+			syn::Expr::Path(syn::ExprPath { path, .. }) => self.local_ty(&path.get_ident()?.unraw().to_string()),
+			syn::Expr::Binary(syn::ExprBinary { left, op, right, .. }) => self.binary_ty(left, op, right),
+			syn::Expr::Unary(syn::ExprUnary { expr, .. })
+			| syn::Expr::Paren(syn::ExprParen { expr, .. })
+			| syn::Expr::Reference(syn::ExprReference { expr, .. }) => self.ty(expr),
+			syn::Expr::Cast(syn::ExprCast { ty, .. }) => Ty::of(ty),
+			syn::Expr::MethodCall(syn::ExprMethodCall { method, .. }) if method.to_string().starts_with("is_") => Some(Ty::Bool),
+			_ => None,
+		}
+	}
+
+	fn binary_ty(&self, left: &syn::Expr, op: &syn::BinOp, right: &syn::Expr) -> Option<Ty> {
+		use super::BinaryOp::*;
+
+		match super::BinaryOp::from(*op) {
+			And | Or | Eq | Ne | Lt | Le | Gt | Ge => Some(Ty::Bool),
+			// Shifts have the type of what's shifted, other operands are of the same type.
+			Bshl | Bshr => self.ty(left),
+			_ => Ty::merge(self.ty(left), self.ty(right)),
+		}
+	}
+
+	/// Wraps the result of integer arithmetic of type `ty` with strict integers.
+	fn wrap(&self, kind: ItemKind, ty: Option<Ty>, span: Option<super::Span>) -> ItemKind {
+		match Ty::wrapping(ty) {
+			Some(ty) if self.strict => ItemKind::ExprWrap { expr: Box::new(Item::new(kind, span)), ty },
+			_ => kind,
+		}
+	}
+
+	/// Parameter names, and the `let`s destructuring them at the start of the body.
+	fn params(&self, pats: impl IntoIterator<Item = syn::Pat>) -> (Vec<String>, Vec<Item>) {
+		let mut lets = vec![];
+		let params = pats
+			.into_iter()
+			.enumerate()
+			.map(|(i, pat)| {
+				let (name, destructure) = self.binding(pat, format!("{TEMPORARY}__arg{i}"));
+				lets.extend(destructure);
+				name
+			})
+			.collect();
+
+		(params, lets)
+	}
+
+	/// Lowers the arguments of `format!` and friends. Named arguments and variables captured by the format string
+	/// go after the positional ones, so every value is evaluated once, in order.
+	fn format(&self, args: syn::punctuated::Punctuated<syn::Expr, syn::Token![,]>, span: Option<super::Span>) -> Item {
+		use super::format::{self, Argument, FormatError};
+
+		let mut args = args.into_iter();
+		let literal = match args.next() {
+			Some(syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. })) => s,
+			other => {
+				let at = other.map_or_else(proc_macro2::Span::call_site, |other| other.span());
+				self.errors.borrow_mut().push(syn::Error::new(at, "format argument must be a string literal"));
+				return Item::new(ItemKind::ExprString(String::new()), span);
+			}
+		};
+
+		let mut positional = vec![];
+		let mut named = vec![];
+		for arg in args {
+			match arg {
+				syn::Expr::Assign(syn::ExprAssign { left, right, .. }) if matches!(&*left, syn::Expr::Path(p) if p.path.get_ident().is_some()) => {
+					let syn::Expr::Path(path) = *left else { unreachable!() };
+					named.push((path.path.get_ident().unwrap().unraw().to_string(), (self.ty(&right), self.expr_to_item(*right))));
+				}
+				arg => positional.push((self.ty(&arg), self.expr_to_item(arg))),
 			}
 		}
 
-		fn expr_to_item(expr: syn::Expr) -> super::Item {
-			match expr {
-				syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
-					syn::Lit::Bool(b) => super::Item::ExprBool(b.value()),
-					syn::Lit::Str(s) => super::Item::ExprString(s.value()),
-					syn::Lit::Int(i) => super::Item::ExprInteger(i.base10_parse::<i64>().unwrap()),
-					syn::Lit::Float(f) => {
-						super::Item::ExprDecimal(f.base10_parse::<f64>().unwrap())
+		let mut captures: Vec<String> = vec![];
+		let mut used = vec![false; positional.len() + named.len()];
+		let resolve = |arg: Argument<'_>| {
+			let index = match arg {
+				Argument::Index(i) if i < positional.len() => i,
+				Argument::Index(i) => return Err(FormatError(format!("invalid reference to positional argument {i}"))),
+				Argument::Name(name) => match named.iter().position(|(n, _)| n == name) {
+					Some(i) => positional.len() + i,
+					None => {
+						let i = captures.iter().position(|c| c == name).unwrap_or_else(|| {
+							captures.push(name.to_owned());
+							captures.len() - 1
+						});
+						return Ok(used.len() + i);
 					}
-	
-					_ => todo!(),
 				},
+			};
 
-				syn::Expr::Call(syn::ExprCall { func, args, .. }) => super::Item::ExprCall {
-					func: Box::new(expr_to_item(*func)),
-					args: args.into_iter().map(expr_to_item).collect(),
-				},
+			used[index] = true;
+			Ok(index)
+		};
 
-				syn::Expr::Path(syn::ExprPath { path, .. }) => {
-					let path_ident = path
-						.segments
-						.iter()
-						.map(|x| x.ident.to_string())
-						.collect::<Vec<String>>()
-						.join("__");
-					super::Item::ExprIdent(path_ident)
+		let parsed = format::parse(&literal.value(), resolve).and_then(|parsed| match used.iter().position(|used| !used) {
+			Some(_) => Err(FormatError("argument never used".into())),
+			None => Ok(parsed),
+		});
+
+		let (mut strings, mut args) = match parsed {
+			Ok(parsed) => parsed,
+			Err(e) => {
+				self.errors.borrow_mut().push(syn::Error::new(literal.span(), e));
+				return Item::new(ItemKind::ExprString(literal.value()), span);
+			}
+		};
+
+		if args.is_empty() {
+			return Item::new(ItemKind::ExprString(strings.remove(0)), span);
+		}
+
+		let captures = captures.into_iter().map(|name| {
+			(self.local_ty(&name), Item::new(ItemKind::ExprIdent(name), self.span(literal.span())))
+		});
+		let (tys, mut values): (Vec<_>, Vec<_>) =
+			positional.into_iter().chain(named.into_iter().map(|(_, value)| value)).chain(captures).unzip();
+
+		// A `u64` is marked by wrapping it into its type, which leaves its bits as they are, to be printed unsigned.
+		// A negative number is printed in hex, octal or binary as the bits of its type, not of Lua's 64-bit integers.
+		// A variable also printed some other way is read again to be wrapped.
+		let radix = |arg: &format::FormatArg| {
+			use format::FormatKind::*;
+			matches!(arg.spec.kind, LowerHex | UpperHex | Octal | Binary)
+		};
+		for (i, ty) in tys.into_iter().enumerate() {
+			if let Some(ty) = Ty::unsigned(ty) {
+				let span = values[i].span;
+				values[i] = Item::new(ItemKind::ExprWrap { expr: Box::new(values[i].clone()), ty }, span);
+			}
+
+			let Some(ty) = Ty::radix(ty).filter(|_| args.iter().any(|arg| arg.value == i && radix(arg))) else {
+				continue;
+			};
+			let wrap = |value: Item| {
+				let span = value.span;
+				Item::new(ItemKind::ExprWrap { expr: Box::new(value), ty }, span)
+			};
+
+			if args.iter().all(|arg| arg.value != i || radix(arg)) {
+				values[i] = wrap(values[i].clone());
+			} else if matches!(values[i].kind, ItemKind::ExprIdent(_)) {
+				values.push(wrap(values[i].clone()));
+				for arg in args.iter_mut().filter(|arg| arg.value == i && radix(arg)) {
+					arg.value = values.len() - 1;
 				}
+			}
+		}
 
-				syn::Expr::AssignOp(syn::ExprAssignOp {
-					left, right, op, ..
-				}) => match op {
-					syn::BinOp::AddEq(_) => {
-						let left = expr_to_item(*left);
-						if let super::Item::ExprIdent(ref name) = left {
-							super::Item::VarSet {
-								name: name.to_owned(),
-								expr: Box::new(super::Item::ExprBinary {
-									lhs: Box::new(left),
-									rhs: Box::new(expr_to_item(*right)),
-									op: super::BinaryOp::Add,
-								}),
-							}
-						} else {
-							todo!()
-						}
-					}
+		Item::new(ItemKind::ExprFString { strings, args, values }, span)
+	}
 
-					syn::BinOp::SubEq(_) => {
-						let left = expr_to_item(*left);
-						if let super::Item::ExprIdent(ref name) = left {
-							super::Item::VarSet {
-								name: name.to_owned(),
-								expr: Box::new(super::Item::ExprBinary {
-									lhs: Box::new(left),
-									rhs: Box::new(expr_to_item(*right)),
-									op: super::BinaryOp::Sub,
-								}),
-							}
-						} else {
-							todo!()
-						}
-					}
+	/// Lowers the std macros to calls of [Builtin]s.
+	fn macro_to_item(&self, mac: syn::Macro, span: Option<super::Span>) -> Item {
+		let path = mac.path.segments.iter().map(|x| x.ident.unraw().to_string()).collect::<Vec<String>>().join("::");
+		let call = |builtin: Builtin, args: Vec<Item>| Item::new(ItemKind::ExprBuiltinCall { builtin, args }, span);
 
-					syn::BinOp::MulEq(_) => {
-						let left = expr_to_item(*left);
-						if let super::Item::ExprIdent(ref name) = left {
-							super::Item::VarSet {
-								name: name.to_owned(),
-								expr: Box::new(super::Item::ExprBinary {
-									lhs: Box::new(left),
-									rhs: Box::new(expr_to_item(*right)),
-									op: super::BinaryOp::Mul,
-								}),
-							}
-						} else {
-							todo!()
-						}
-					}
+		// `vec![x; n]` isn't a list of expressions, so it has to be checked first.
+		let parsed = syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated.parse2(mac.tokens.clone());
+		let args = match parsed {
+			Ok(args) => args,
+			Err(_) if path == "vec" => {
+				self.errors.borrow_mut().push(syn::Error::new(mac.span(), "`vec![value; count]` is not supported"));
+				return Item::new(ItemKind::ExprArray { elements: vec![] }, span);
+			}
+			Err(e) => {
+				self.errors.borrow_mut().push(e);
+				return Item::new(ItemKind::ExprArray { elements: vec![] }, span);
+			}
+		};
 
-					syn::BinOp::DivEq(_) => {
-						let left = expr_to_item(*left);
-						if let super::Item::ExprIdent(ref name) = left {
-							super::Item::VarSet {
-								name: name.to_owned(),
-								expr: Box::new(super::Item::ExprBinary {
-									lhs: Box::new(left),
-									rhs: Box::new(expr_to_item(*right)),
-									op: super::BinaryOp::Div,
-								}),
-							}
-						} else {
-							todo!()
-						}
+		// The message of a panic, if the macro was given one.
+		let message = |args: syn::punctuated::Punctuated<syn::Expr, syn::Token![,]>| match args.is_empty() {
+			true => None,
+			false => Some(self.format(args, span)),
+		};
+		let string = |s: &str| Item::new(ItemKind::ExprString(s.into()), span);
+
+		if let Some(rule) = self.macros.get(&path) {
+			let func = Item::new(ItemKind::ExprIdent(rule.function()), self.span(mac.path.span()));
+			let args = match rule {
+				super::Macro::Call(_) => args.into_iter().map(|arg| self.expr_to_item(arg)).collect(),
+				super::Macro::Format(_) => message(args).into_iter().collect(),
+			};
+			return Item::new(ItemKind::ExprCall { func: Box::new(func), args }, span);
+		}
+
+		match path.as_str() {
+			// `println!(a, b)` without a format string passes everything to print, which separates them with tabs.
+			"println" if !has_format_string(&args) => call(Builtin::Print, args.into_iter().map(|arg| self.expr_to_item(arg)).collect()),
+			"println" => call(Builtin::Print, vec![self.format(args, span)]),
+
+			"format" => self.format(args, span),
+			"print" => call(Builtin::Write, vec![self.format(args, span)]),
+			"eprint" => call(Builtin::Ewrite, vec![self.format(args, span)]),
+			"eprintln" => call(Builtin::Ewrite, vec![line(message(args), span)]),
+
+			"write" | "writeln" if args.is_empty() => {
+				self.errors.borrow_mut().push(syn::Error::new(mac.span(), format!("`{path}!` needs a destination")));
+				call(Builtin::Fwrite, vec![])
+			}
+			"write" | "writeln" => {
+				let dst = self.expr_to_item(args.iter().next().unwrap().clone());
+				let rest = args.into_iter().skip(1).collect();
+
+				let s = match path.as_str() {
+					"write" => self.format(rest, span),
+					_ => line(message(rest), span),
+				};
+				call(Builtin::Fwrite, vec![dst, s])
+			}
+
+			"panic" => call(Builtin::Panic, vec![message(args).unwrap_or_else(|| string("explicit panic"))]),
+			"unreachable" => call(Builtin::Panic, vec![prefixed("internal error: entered unreachable code", message(args), span)]),
+			"todo" => call(Builtin::Panic, vec![prefixed("not yet implemented", message(args), span)]),
+			"unimplemented" => call(Builtin::Panic, vec![prefixed("not implemented", message(args), span)]),
+
+			"assert" | "debug_assert" if args.is_empty() => {
+				self.errors.borrow_mut().push(syn::Error::new(mac.span(), format!("`{path}!` needs a condition")));
+				call(Builtin::Assert, vec![])
+			}
+			"assert" | "debug_assert" => {
+				let cond = args.iter().next().unwrap();
+				let source = cond.span().source_text().unwrap_or_else(|| "condition".into());
+				let cond = self.expr_to_item(cond.clone());
+
+				let rest = args.into_iter().skip(1).collect();
+				let message = message(rest).unwrap_or_else(|| string(&format!("assertion failed: {source}")));
+				call(Builtin::Assert, vec![cond, message])
+			}
+
+			"assert_eq" | "assert_ne" | "debug_assert_eq" | "debug_assert_ne" if args.len() < 2 => {
+				self.errors.borrow_mut().push(syn::Error::new(mac.span(), format!("`{path}!` needs two values")));
+				call(Builtin::Assert, vec![])
+			}
+			"assert_eq" | "assert_ne" | "debug_assert_eq" | "debug_assert_ne" => {
+				let (left, right) = (args.iter().next().unwrap().clone(), args.iter().nth(1).unwrap().clone());
+				let rest = args.into_iter().skip(2).collect();
+				self.assert_cmp(path.ends_with("eq"), self.expr_to_item(left), self.expr_to_item(right), message(rest), span)
+			}
+
+			"vec" => Item::new(ItemKind::ExprArray { elements: args.into_iter().map(|e| self.expr_to_item(e)).collect() }, span),
+
+			_ => todo!("Unsupported macro: {path}"),
+		}
+	}
+
+	/// `assert_eq!` and `assert_ne!`, as a block binding both values so they're evaluated once and can be printed.
+	fn assert_cmp(&self, eq: bool, left: Item, right: Item, message: Option<Item>, span: Option<super::Span>) -> Item {
+		use super::format::{FormatArg, FormatKind, FormatSpec};
+
+		let ident = |name: &str| Item::new(ItemKind::ExprIdent(name.into()), span);
+		let decl = |name: &str, expr: Item| Item::new(ItemKind::VarDecl { name: name.into(), expr: Box::new(expr) }, span);
+
+		let [left_name, right_name] = ["__left", "__right"].map(|name| format!("{TEMPORARY}{name}"));
+		let op = if eq { "==" } else { "!=" };
+		let header = format!("assertion `left {op} right` failed");
+		let debug = |value| FormatArg { value, spec: FormatSpec { kind: FormatKind::Debug, ..Default::default() } };
+
+		let (strings, args, values) = match message {
+			Some(message) => (
+				vec![format!("{header}: "), "\n  left: ".into(), "\n right: ".into(), String::new()],
+				vec![FormatArg { value: 0, spec: FormatSpec::default() }, debug(1), debug(2)],
+				vec![message, ident(&left_name), ident(&right_name)],
+			),
+			None => (
+				vec![format!("{header}\n  left: "), "\n right: ".into(), String::new()],
+				vec![debug(0), debug(1)],
+				vec![ident(&left_name), ident(&right_name)],
+			),
+		};
+
+		let panic = Item::new(
+			ItemKind::ExprBuiltinCall {
+				builtin: Builtin::Panic,
+				args: vec![Item::new(ItemKind::ExprFString { strings, args, values }, span)],
+			},
+			span,
+		);
+		let failed = Item::new(
+			ItemKind::ExprBinary {
+				lhs: Box::new(ident(&left_name)),
+				rhs: Box::new(ident(&right_name)),
+				op: if eq { super::BinaryOp::Ne } else { super::BinaryOp::Eq },
+			},
+			span,
+		);
+		let check = Item::new(
+			ItemKind::IfElif(super::IfElif {
+				condition: Box::new(failed),
+				stmts: vec![panic],
+				elif: vec![],
+				else_stmts: None,
+			}),
+			span,
+		);
+
+		let stmts = vec![decl(&left_name, left), decl(&right_name, right), check];
+		Item::new(ItemKind::Block { stmts }, span)
+	}
+
+	fn stmts(&self, stmts: Vec<syn::Stmt>) -> Vec<Item> {
+		self.scope(|| stmts.into_iter().filter_map(|stmt| self.stmt_to_item(stmt)).collect())
+	}
+
+	/// Lowers the statements of a function body, returning its tail expression.
+	fn body(&self, stmts: Vec<syn::Stmt>) -> Vec<Item> {
+		let tail = matches!(stmts.last(), Some(syn::Stmt::Expr(_)));
+
+		let mut stmts = self.stmts(stmts);
+		if tail {
+			if let Some(last) = stmts.pop() {
+				stmts.push(return_tail(last));
+			}
+		}
+
+		stmts
+	}
+
+	fn stmt_to_item(&self, stmt: syn::Stmt) -> Option<Item> {
+		let span = self.span(stmt.span());
+
+		Some(match stmt {
+			syn::Stmt::Item(item) => return self.syn_item_to_item(item),
+			syn::Stmt::Local(local) => {
+				let ty = match (&local.pat, &local.init) {
+					(syn::Pat::Type(syn::PatType { ty, .. }), _) => Ty::of(ty),
+					(_, Some((_, expr))) => self.ty(expr),
+					(_, None) => None,
+				};
+				let expr = local.init.map(|(_, expr)| Box::new(self.expr_to_item(*expr)));
+
+				match (self.irrefutable(local.pat), expr) {
+					(Pattern::Ident(name), Some(expr)) => {
+						self.declare(&name, ty);
+						Item::new(ItemKind::VarDecl { name, expr }, span)
 					}
+					(pattern, expr) => Item::new(ItemKind::Let { pattern, expr, else_stmts: None }, span),
+				}
+			}
+			syn::Stmt::Expr(expr) => self.expr_to_item(expr),
+			// syn 1 doesn't know about `let ... else`, and leaves it as tokens.
+			syn::Stmt::Semi(syn::Expr::Verbatim(tokens), _) => return self.let_else(tokens, span),
+			syn::Stmt::Semi(expr, _) => self.expr_to_item(expr),
+		})
+	}
 
-					_ => todo!(),
-				},
+	/// Lowers `let PAT = EXPR else { ... }`, by parsing everything before the `else` as a regular `let`.
+	fn let_else(&self, tokens: proc_macro2::TokenStream, span: Option<super::Span>) -> Option<Item> {
+		use proc_macro2::{Delimiter, TokenTree};
 
-				syn::Expr::Closure(syn::ExprClosure { inputs, body, .. }) => {
-					use syn::Pat;
+		let error_span = tokens.span();
+		let mut tokens = tokens.into_iter().collect::<Vec<_>>();
+		let parsed = match tokens.as_slice() {
+			[.., TokenTree::Ident(kw), TokenTree::Group(group)] if kw == "else" && group.delimiter() == Delimiter::Brace => {
+				let block = syn::parse2::<syn::Block>(TokenTree::Group(group.clone()).into());
+				tokens.truncate(tokens.len() - 2);
+				let local = syn::parse2::<syn::Stmt>(quote_semi(tokens));
+				match (local, block) {
+					(Ok(syn::Stmt::Local(syn::Local { pat, init: Some((_, expr)), .. })), Ok(block)) => Some((pat, *expr, block)),
+					_ => None,
+				}
+			}
+			_ => None,
+		};
 
-					let params: Vec<String> = inputs
-						.into_iter()
-						.map(|arg| match arg {
-							Pat::Ident(id) => id.ident.to_string(),
-							_ => todo!(),
-						})
-						.collect();
+		let Some((pat, expr, block)) = parsed else {
+			self.errors.borrow_mut().push(syn::Error::new(error_span, "unsupported syntax"));
+			return None;
+		};
 
-					if let syn::Expr::Block(syn::ExprBlock { block, .. }) = *body {
-						super::Item::ExprClosure {
-							params,
-							stmts: block.stmts.into_iter().map(stmt_to_item).collect(),
-						}
-					} else {
-						todo!()
+		// The bindings are only in scope after the `else`.
+		let expr = Some(Box::new(self.expr_to_item(expr)));
+		let else_stmts = Some(self.stmts(block.stmts));
+		let kind = ItemKind::Let { pattern: self.pattern(pat), expr, else_stmts };
+		Some(Item::new(kind, span))
+	}
+
+	/// Lowers an `if`, where `else if` chains of plain conditions become a single [ItemKind::IfElif].
+	fn if_to_item(&self, expr: syn::ExprIf) -> ItemKind {
+		let syn::ExprIf { cond, then_branch, else_branch, .. } = expr;
+		let else_stmts = |branch: Option<(_, Box<syn::Expr>)>| {
+			branch.map(|(_, branch)| match *branch {
+				syn::Expr::Block(syn::ExprBlock { block, .. }) => self.stmts(block.stmts),
+				branch => vec![self.expr_to_item(branch)],
+			})
+		};
+
+		match *cond {
+			// The bindings are only in scope in the first branch.
+			syn::Expr::Let(syn::ExprLet { pat, expr, .. }) => {
+				let expr = Box::new(self.expr_to_item(*expr));
+				let (pattern, stmts) = self.scope(|| (self.pattern(pat), self.stmts(then_branch.stmts)));
+				ItemKind::IfLet { pattern, expr, stmts, else_stmts: else_stmts(else_branch) }
+			}
+			cond => {
+				let condition = Box::new(self.expr_to_item(cond));
+				let stmts = self.stmts(then_branch.stmts);
+				let else_stmts = else_stmts(else_branch);
+				match else_stmts {
+					Some(mut else_stmts) if matches!(else_stmts.as_slice(), [Item { kind: ItemKind::IfElif(_), .. }]) => {
+						let Some(ItemKind::IfElif(inner)) = else_stmts.pop().map(|item| item.kind) else { unreachable!() };
+						let mut elif = vec![(*inner.condition, inner.stmts)];
+						elif.extend(inner.elif);
+						ItemKind::IfElif(super::IfElif { condition, stmts, elif, else_stmts: inner.else_stmts })
 					}
+					else_stmts => ItemKind::IfElif(super::IfElif { condition, stmts, elif: vec![], else_stmts }),
 				}
+			}
+		}
+	}
 
-				syn::Expr::Binary(syn::ExprBinary {
-					left, right, op, ..
-				}) => super::Item::ExprBinary {
-					lhs: Box::new(expr_to_item(*left)),
-					rhs: Box::new(expr_to_item(*right)),
-					op: op.into(),
-				},
+	/// `place = expr`, or `place op= expr`. Variables are set directly, anything else is an [ItemKind::Assign].
+	fn assign(&self, place: syn::Expr, op: Option<super::BinaryOp>, expr: syn::Expr, span: Option<super::Span>) -> ItemKind {
+		let place_span = place.span();
+		let ty = self.ty(&place);
+		let mut target = &place;
+		while let syn::Expr::Paren(syn::ExprParen { expr, .. }) = target {
+			target = expr;
+		}
+		let deref = matches!(target, syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Deref(_), .. }));
+		let op = op.map(|op| typed(op, Ty::merge(ty, self.ty(&expr))));
+		let place = self.place(place);
+		let expr = self.expr_to_item(expr);
 
-				syn::Expr::Array(syn::ExprArray { elems, .. }) => super::Item::ExprArray {
-					elements: elems.into_iter().map(expr_to_item).collect(),
+		match place.kind {
+			ItemKind::ExprIdent(_) if deref => {
+				let message = "assigning through a reference to a variable is not supported";
+				self.errors.borrow_mut().push(syn::Error::new(place_span, message));
+				expr.kind
+			}
+			ItemKind::ExprIdent(name) => {
+				let expr = match op {
+					Some(op) => {
+						let wraps = overflows(&op);
+						let lhs = Box::new(Item::new(ItemKind::ExprIdent(name.clone()), place.span));
+						let kind = ItemKind::ExprBinary { lhs, rhs: Box::new(expr), op };
+						Item::new(if wraps { self.wrap(kind, ty, span) } else { kind }, span)
+					}
+					None => expr,
+				};
+				ItemKind::VarSet { name, expr: Box::new(expr) }
+			}
+			ItemKind::ExprField { .. } | ItemKind::ExprIndex { .. } => ItemKind::Assign {
+				place: Box::new(place),
+				op,
+				expr: Box::new(expr),
+			},
+			_ => {
+				self.errors.borrow_mut().push(syn::Error::new(place_span, "can't assign to this expression"));
+				expr.kind
+			}
+		}
+	}
+
+	/// Bytes lowered to a string with [CharRepr::String], which can only hold UTF-8.
+	fn bytes_to_string(&self, bytes: Vec<u8>, span: proc_macro2::Span) -> ItemKind {
+		match String::from_utf8(bytes) {
+			Ok(s) => ItemKind::ExprString(s),
+			Err(_) => {
+				let message = "bytes that aren't UTF-8 need chars lowered to their codes";
+				self.errors.borrow_mut().push(syn::Error::new(span, message));
+				ItemKind::ExprString(String::new())
+			}
+		}
+	}
+
+	/// The target of an assignment. Derefs are dropped, as what they point to is a table already.
+	fn place(&self, expr: syn::Expr) -> Item {
+		let span = self.span(expr.span());
+		match expr {
+			syn::Expr::Paren(syn::ExprParen { expr, .. }) => self.place(*expr),
+			syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Deref(_), expr, .. }) => self.place(*expr),
+			syn::Expr::Field(syn::ExprField { base, member: m, .. }) => Item::new(member(self.place(*base), m, span), span),
+			syn::Expr::Index(syn::ExprIndex { expr, index, .. }) => Item::new(
+				ItemKind::ExprIndex {
+					expr: Box::new(self.place(*expr)),
+					index: Box::new(self.expr_to_item(*index)),
 				},
+				span,
+			),
+			expr => self.expr_to_item(expr),
+		}
+	}
 
-				syn::Expr::Tuple(syn::ExprTuple { elems, .. }) => super::Item::ExprArray {
-					elements: elems.into_iter().map(expr_to_item).collect(),
+	fn expr_to_item(&self, expr: syn::Expr) -> Item {
+		let span = self.span(expr.span());
+
+		let kind = match expr {
+			syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+				syn::Lit::Bool(b) => ItemKind::ExprBool(b.value()),
+				syn::Lit::Str(s) => ItemKind::ExprString(s.value()),
+				// `1f32` is an integer to syn.
+				syn::Lit::Int(i) if NumType::parse(i.suffix()).is_some_and(NumType::is_float) => {
+					decimal(i.base10_parse::<f64>().unwrap(), i.suffix())
+				}
+				// Anything up to `u64::MAX`, which is stored as the `i64` with the same bits. Without a suffix, only
+				// a `u64` can hold more than `i64::MAX`.
+				syn::Lit::Int(i) => match i.base10_parse::<u64>() {
+					Ok(value) => {
+						let ty = NumType::parse(i.suffix()).or((value > i64::MAX as u64).then_some(NumType::U64));
+						ItemKind::ExprInteger(value as i64, ty)
+					}
+					Err(_) => {
+						self.errors.borrow_mut().push(syn::Error::new(i.span(), "integers larger than `u64::MAX` are not supported"));
+						ItemKind::ExprInteger(0, None)
+					}
 				},
+				syn::Lit::Float(f) => decimal(f.base10_parse::<f64>().unwrap(), f.suffix()),
 
-				syn::Expr::Loop(syn::ExprLoop { label: _, body, .. }) => super::Item::While {
-					condition: Box::new(super::Item::ExprBool(true)),
-					stmts: body.stmts.into_iter().map(stmt_to_item).collect(),
+				syn::Lit::Char(c) => match self.chars {
+					CharRepr::String => ItemKind::ExprString(c.value().into()),
+					CharRepr::Code => ItemKind::ExprInteger(u32::from(c.value()).into(), None),
+				},
+				syn::Lit::Byte(b) => match self.chars {
+					CharRepr::String => self.bytes_to_string(vec![b.value()], b.span()),
+					CharRepr::Code => ItemKind::ExprInteger(b.value().into(), Some(NumType::U8)),
 				},
+				syn::Lit::ByteStr(s) => match self.chars {
+					CharRepr::String => self.bytes_to_string(s.value(), s.span()),
+					CharRepr::Code => {
+						let bytes = s.value().into_iter().map(|b| Item::new(ItemKind::ExprInteger(b.into(), Some(NumType::U8)), span));
+						ItemKind::ExprArray { elements: bytes.collect() }
+					}
+				},
+				lit => {
+					self.errors.borrow_mut().push(syn::Error::new(lit.span(), "unsupported literal"));
+					ItemKind::ExprString(String::new())
+				}
+			},
 
-				syn::Expr::While(syn::ExprWhile { cond, body, .. }) => super::Item::While {
-					condition: Box::new(expr_to_item(*cond)),
-					stmts: body.stmts.into_iter().map(stmt_to_item).collect(),
+			syn::Expr::Call(syn::ExprCall { func, args, .. }) => match &*func {
+				syn::Expr::Path(syn::ExprPath { path, .. }) if variant(path).is_some_and(Variant::has_value) => {
+					let variant = variant(path).unwrap();
+					if args.len() != 1 {
+						let message = format!("`{}` takes a single value", variant.name());
+						self.errors.borrow_mut().push(syn::Error::new(args.span(), message));
+					}
+					let value = args.into_iter().next().map(|arg| Box::new(self.expr_to_item(arg)));
+					ItemKind::ExprVariant { variant, value }
+				}
+				_ => ItemKind::ExprCall {
+					func: Box::new(self.expr_to_item(*func)),
+					args: args.into_iter().map(|arg| self.expr_to_item(arg)).collect(),
 				},
+			},
 
-				syn::Expr::ForLoop(syn::ExprForLoop { pat, body, expr, .. }) => {
-					let ident = match pat {
-						syn::Pat::Ident(i) => i.ident.to_string(),
-						other => todo!("{other:?}")
-					};
-
-					match *expr {
-						syn::Expr::Range(syn::ExprRange { from, to, .. }) => {
-							super::Item::ForRange {
-								max: Box::new(to.map(|to| expr_to_item(*to)).unwrap_or(super::Item::ExprInteger(9999))),
-								min: Box::new(from.map(|from| expr_to_item(*from)).unwrap_or(super::Item::ExprInteger(9999))),
-								jump: None,
-								var: ident,
-								stmts: body.stmts.into_iter().map(stmt_to_item).collect()
+			// Variants holding a value are functions when they aren't called, as in `.map(Some)`.
+			syn::Expr::Path(syn::ExprPath { path, .. }) if variant(&path).is_some() => match variant(&path).unwrap() {
+				Variant::None => ItemKind::ExprVariant { variant: Variant::None, value: None },
+				variant => {
+					let value = Some(Box::new(Item::new(ItemKind::ExprIdent(format!("{TEMPORARY}__arg0")), span)));
+					let expr = Some(Box::new(Item::new(ItemKind::ExprVariant { variant, value }, span)));
+					ItemKind::ExprClosure {
+						params: vec![format!("{TEMPORARY}__arg0")],
+						stmts: vec![Item::new(ItemKind::Return { expr }, span)],
+					}
+				}
+			},
+
+			syn::Expr::Try(syn::ExprTry { expr, .. }) => ItemKind::ExprTry(Box::new(self.expr_to_item(*expr))),
+
+			// Turbofish types make no difference.
+			syn::Expr::MethodCall(syn::ExprMethodCall { receiver, method, args, .. }) => ItemKind::ExprMethodCall {
+				receiver: Box::new(self.expr_to_item(*receiver)),
+				method: method.unraw().to_string(),
+				args: args.into_iter().map(|arg| self.expr_to_item(arg)).collect(),
+			},
+
+			syn::Expr::Path(syn::ExprPath { path, .. }) => {
+				let path_ident = path
+					.segments
+					.iter()
+					.map(|x| x.ident.unraw().to_string())
+					.collect::<Vec<String>>()
+					.join("::");
+
+				// Like extern crates, paths starting with `::` are provided by the host.
+				match path.leading_colon {
+					Some(_) => ItemKind::ExprIdent(format!("::{path_ident}")),
+					None => ItemKind::ExprIdent(path_ident),
+				}
+			}
+
+			syn::Expr::Assign(syn::ExprAssign { left, right, .. }) => self.assign(*left, None, *right, span),
+			syn::Expr::AssignOp(syn::ExprAssignOp { left, op, right, .. }) => self.assign(*left, Some(op.into()), *right, span),
+
+			syn::Expr::Field(syn::ExprField { base, member: m, .. }) => member(self.expr_to_item(*base), m, span),
+			syn::Expr::Index(syn::ExprIndex { expr, index, .. }) => ItemKind::ExprIndex {
+				expr: Box::new(self.expr_to_item(*expr)),
+				index: Box::new(self.expr_to_item(*index)),
+			},
+
+			syn::Expr::Closure(syn::ExprClosure { inputs, body, .. }) => self.scope(|| {
+				let (params, mut stmts) = self.params(inputs);
+
+				if let syn::Expr::Block(syn::ExprBlock { block, .. }) = *body {
+					stmts.extend(self.body(block.stmts));
+				} else {
+					let expr = self.expr_to_item(*body);
+					stmts.push(Item::new(ItemKind::Return { expr: Some(Box::new(expr)) }, span));
+				}
+
+				ItemKind::ExprClosure { params, stmts }
+			}),
+
+			syn::Expr::Binary(syn::ExprBinary {
+				left, right, op, ..
+			}) => {
+				let ty = self.binary_ty(&left, &op, &right);
+				let op = typed(super::BinaryOp::from(op), Ty::merge(self.ty(&left), self.ty(&right)));
+				let wraps = overflows(&op);
+				let kind = ItemKind::ExprBinary {
+					lhs: Box::new(self.expr_to_item(*left)),
+					rhs: Box::new(self.expr_to_item(*right)),
+					op,
+				};
+
+				match wraps {
+					true => self.wrap(kind, ty, span),
+					false => kind,
+				}
+			}
+
+			// `!` flips the bits of integers, and is assumed to be on a bool unless the operand is known to be one.
+			syn::Expr::Unary(syn::ExprUnary { op, expr, .. }) => {
+				let ty = self.ty(&expr);
+				let expr = self.expr_to_item(*expr);
+				match (op, expr.kind) {
+					// What a reference points to is shared already if it's a table, so it's the same as the value.
+					(syn::UnOp::Deref(_), kind) => kind,
+					(syn::UnOp::Neg(_), ItemKind::ExprInteger(i, ty)) => ItemKind::ExprInteger(i.wrapping_neg(), ty),
+					(syn::UnOp::Neg(_), ItemKind::ExprDecimal(x)) => ItemKind::ExprDecimal(-x),
+					(op, kind) => {
+						let op = match op {
+							syn::UnOp::Not(_) if ty.is_some_and(Ty::is_int) => super::UnaryOp::BitNot,
+							syn::UnOp::Not(_) => super::UnaryOp::Not,
+							_ => super::UnaryOp::Neg,
+						};
+						let kind = ItemKind::ExprUnary { op, expr: Box::new(Item::new(kind, expr.span)) };
+
+						// Flipping the bits of an unsigned integer can't overflow, but it sets the ones above its type.
+						match Ty::wrapping(ty) {
+							Some(ty) if op == super::UnaryOp::BitNot && !ty.is_signed() => {
+								ItemKind::ExprWrap { expr: Box::new(Item::new(kind, span)), ty }
 							}
-						},
-						_ => super::Item::ForIn {
-							var: ident,
-							expr: Box::new(expr_to_item(*expr)),
-							stmts: body.stmts.into_iter().map(stmt_to_item).collect()
+							_ => self.wrap(kind, ty, span),
 						}
 					}
-				},
+				}
+			}
 
-				syn::Expr::Range(syn::ExprRange { from: _, to: _, .. }) => unimplemented!(),
-
-				syn::Expr::Break(_) => super::Item::Break,
-				syn::Expr::Continue(_) => super::Item::Continue,
-
-				syn::Expr::If(syn::ExprIf {
-					cond,
-					then_branch,
-					else_branch,
-					..
-				}) => {
-					if let Some((.., b)) = else_branch {
-						if let syn::Expr::Block(syn::ExprBlock { block, .. }) = *b {
-							// Block expression works enough as a statement.
-							super::Item::IfElif(super::IfElif {
-								condition: Box::new(expr_to_item(*cond)),
-								stmts: then_branch.stmts.into_iter().map(stmt_to_item).collect(),
-								elif: vec![],
-								else_stmts: Some(
-									block.stmts.into_iter().map(stmt_to_item).collect(),
-								),
-							})
-						} else if let syn::Expr::If(syn::ExprIf {
-							cond, then_branch, ..
-						}) = *b
-						{
-							println!("elif {cond:#?} {then_branch:#?}");
-							todo!()
-						} else {
-							// Expression directly ???
-							dbg!(b);
-							unreachable!()
-						}
-					// super::Item::IfElif(super::IfElif { condition: Box::new(expr_to_item(*cond)), stmts: then_branch.stmts.into_iter().map(stmt_to_item).collect(), elif: vec![] })
-					} else {
-						super::Item::IfElif(super::IfElif {
-							condition: Box::new(expr_to_item(*cond)),
-							stmts: then_branch.stmts.into_iter().map(stmt_to_item).collect(),
-							elif: vec![],
-							else_stmts: None,
+			// Casts between integers only wrap, and the ones to 64 bits do nothing as that's what integers are already.
+			syn::Expr::Cast(syn::ExprCast { expr, ty, .. }) => {
+				let from = self.ty(&expr);
+				let expr = self.expr_to_item(*expr);
+				match Ty::of(&ty) {
+					Some(Ty::Num(to)) if !to.is_float() && from.is_some_and(Ty::is_int) && to.bits() == 64 => expr.kind,
+					Some(Ty::Num(to)) if !to.is_float() && from.is_some_and(Ty::is_int) => {
+						ItemKind::ExprWrap { expr: Box::new(expr), ty: to }
+					}
+					Some(Ty::Num(to)) => ItemKind::ExprCast { expr: Box::new(expr), ty: to },
+					_ => {
+						self.errors.borrow_mut().push(syn::Error::new(ty.span(), "only casts to number types are supported"));
+						expr.kind
+					}
+				}
+			}
+			syn::Expr::Reference(syn::ExprReference { expr, .. }) => return self.expr_to_item(*expr),
+
+			syn::Expr::Array(syn::ExprArray { elems, .. }) => ItemKind::ExprArray {
+				elements: elems.into_iter().map(|e| self.expr_to_item(e)).collect(),
+			},
+
+			syn::Expr::Tuple(syn::ExprTuple { elems, .. }) => ItemKind::ExprTuple {
+				elements: elems.into_iter().map(|e| self.expr_to_item(e)).collect(),
+			},
+
+			syn::Expr::Struct(syn::ExprStruct { path, fields, rest, .. }) => {
+				if let Some(rest) = rest {
+					self.errors.borrow_mut().push(syn::Error::new(rest.span(), "struct update syntax is not supported"));
+				}
+
+				ItemKind::ExprStruct {
+					path: path.segments.iter().map(|x| x.ident.unraw().to_string()).collect::<Vec<_>>().join("::"),
+					fields: fields
+						.into_iter()
+						.filter_map(|field| match field.member {
+							syn::Member::Named(name) => Some((name.unraw().to_string(), self.expr_to_item(field.expr))),
+							syn::Member::Unnamed(index) => {
+								self.errors.borrow_mut().push(syn::Error::new(index.span, "tuple structs are constructed with a call"));
+								None
+							}
 						})
+						.collect(),
+				}
+			}
+
+			syn::Expr::Loop(syn::ExprLoop { label: _, body, .. }) => ItemKind::While {
+				condition: Box::new(Item::new(ItemKind::ExprBool(true), span)),
+				stmts: self.stmts(body.stmts),
+			},
+
+			// `while let` loops until the pattern stops matching.
+			syn::Expr::While(syn::ExprWhile { cond, body, .. }) => match *cond {
+				syn::Expr::Let(syn::ExprLet { pat, expr, .. }) => {
+					let expr = Box::new(self.expr_to_item(*expr));
+					let (pattern, stmts) = self.scope(|| (self.pattern(pat), self.stmts(body.stmts)));
+					let else_stmts = Some(vec![Item::new(ItemKind::Break, span)]);
+					let if_let = ItemKind::IfLet { pattern, expr, stmts, else_stmts };
+					ItemKind::While {
+						condition: Box::new(Item::new(ItemKind::ExprBool(true), span)),
+						stmts: vec![Item::new(if_let, span)],
 					}
+				}
+				cond => ItemKind::While {
+					condition: Box::new(self.expr_to_item(cond)),
+					stmts: self.stmts(body.stmts),
 				},
+			},
 
-				syn::Expr::Macro(syn::ExprMacro { attrs: _, mac }) => {
-					let path = mac.path
-						.segments
-						.iter()
-						.map(|x| x.ident.to_string())
-						.collect::<Vec<String>>()
-						.join("__");
-
-					if path == "println" {
-						// Arguments
-						// use syn::parse_quote::ParseQuote;
-						let args: Vec<super::Item> = syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated
-							.parse2(mac.tokens)
-							.expect("Only accepts items")
-							.into_iter()
-							.map(expr_to_item)
-							.collect();
-
-						super::Item::ExprCall { func: Box::new(super::Item::ExprIdent("print".into())), args }
-					} else if path == "format" {
-						let mut args = syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated
-							.parse2(mac.tokens)
-							.expect("Only accepts items")
-							.into_iter()
-							.map(expr_to_item);
-
-						let mut strings = vec![];
-						let mut replacements = vec![];
-						let mut values = vec![];
-
-						match args.next() {
-							Some(super::Item::ExprString(str)) => {
-								let split = str.split("{}");
-								for (k, s) in split.enumerate() {
-									strings.push(s.to_owned());
-									if k != 0 {
-										replacements.push(k as u16);
+			syn::Expr::ForLoop(syn::ExprForLoop { pat, body, expr, .. }) => {
+				let (ident, stmts) = self.scope(|| {
+					let (ident, destructure) = self.binding(pat, format!("{TEMPORARY}__item"));
+					if matches!(*expr, syn::Expr::Range(_)) {
+						self.declare(&ident, Some(Ty::Int));
+					}
+					(ident, destructure.into_iter().chain(self.stmts(body.stmts)).collect::<Vec<_>>())
+				});
+
+				match *expr {
+					syn::Expr::Range(syn::ExprRange { from, to, limits, .. }) => {
+						// ForRange is inclusive, so `a..b` needs to stop at b - 1.
+						let to = to.map(|to| match limits {
+							syn::RangeLimits::Closed(_) => self.expr_to_item(*to),
+							syn::RangeLimits::HalfOpen(_) => {
+								let to = self.expr_to_item(*to);
+								match to.kind {
+									ItemKind::ExprInteger(i, ty) if i.checked_sub(1).is_some() => {
+										Item::new(ItemKind::ExprInteger(i - 1, ty), to.span)
 									}
+									_ => Item::new(
+										ItemKind::ExprBinary {
+											lhs: Box::new(Item::new(to.kind, to.span)),
+											rhs: Box::new(Item::new(ItemKind::ExprInteger(1, None), to.span)),
+											op: super::BinaryOp::Sub,
+										},
+										to.span,
+									),
 								}
-							},
-							other => panic!("First arg should be a string: {other:?}")
-						}
-
-						for value in args {
-							values.push(value);
-						}
+							}
+						});
 
-						super::Item::ExprFString {
-							strings,
-							replacements,
-							values
+						ItemKind::ForRange {
+							max: Box::new(to.unwrap_or(ItemKind::ExprInteger(9999, None).into())),
+							min: Box::new(from.map(|from| self.expr_to_item(*from)).unwrap_or(ItemKind::ExprInteger(9999, None).into())),
+							jump: None,
+							var: ident,
+							stmts,
 						}
-					} else {
-						todo!("Unsupported macro: {path}")
+					},
+					_ => ItemKind::ForIn {
+						var: ident,
+						expr: Box::new(self.expr_to_item(*expr)),
+						stmts,
 					}
-				},
+				}
+			},
+
+			syn::Expr::Range(syn::ExprRange { from: _, to: _, .. }) => unimplemented!(),
 
-				unk => todo!("unknown expr: {unk:#?}")
+			syn::Expr::Block(syn::ExprBlock { block, .. }) | syn::Expr::Unsafe(syn::ExprUnsafe { block, .. }) => {
+				ItemKind::Block { stmts: self.stmts(block.stmts) }
 			}
-		}
 
-		let nodes: Vec<super::Item> = syn_ast
-			.items
-			.into_iter()
-			.map(syn_item_to_item)
-			.collect();
+			syn::Expr::Break(_) => ItemKind::Break,
+			syn::Expr::Continue(_) => ItemKind::Continue,
+			syn::Expr::Return(syn::ExprReturn { expr, .. }) => ItemKind::Return {
+				expr: expr.map(|expr| Box::new(self.expr_to_item(*expr))),
+			},
 
-		Ok(SynBackend { items: nodes })
+			syn::Expr::If(expr) => self.if_to_item(expr),
+
+			syn::Expr::Let(_) => {
+				let message = "`let` is only supported as the condition of `if` and `while`";
+				self.errors.borrow_mut().push(syn::Error::new(expr.span(), message));
+				ItemKind::ExprBool(false)
+			}
+
+			syn::Expr::Macro(syn::ExprMacro { attrs: _, mac }) => return self.macro_to_item(mac, span),
+
+			syn::Expr::Paren(syn::ExprParen { expr, .. }) => return self.expr_to_item(*expr),
+
+			unk => todo!("unknown expr: {unk:#?}")
+		};
+
+		Item::new(kind, span)
 	}
+}
 
-	fn items(&self) -> &Vec<super::Item> {
-		&self.items
+/// Whether the result of the operator can be out of the range of its operands' type.
+fn overflows(op: &super::BinaryOp) -> bool {
+	matches!(op, super::BinaryOp::Add | super::BinaryOp::Sub | super::BinaryOp::Mul | super::BinaryOp::Bshl)
+}
+
+/// A float literal, rounded to the nearest `f32` if it has that suffix so `0.1f32` isn't the same as `0.1`.
+fn decimal(value: f64, suffix: &str) -> ItemKind {
+	match NumType::parse(suffix) {
+		Some(NumType::F32) => ItemKind::ExprDecimal(value as f32 as f64),
+		_ => ItemKind::ExprDecimal(value),
+	}
+}
+
+/// The operator for operands of type `ty`, which is [super::BinaryOp::IntDiv] for `/` of integers, and one of the
+/// unsigned ones for `u64`s.
+fn typed(op: super::BinaryOp, ty: Option<Ty>) -> super::BinaryOp {
+	use super::BinaryOp::*;
+
+	match op {
+		Div if Ty::unsigned(ty).is_some() => UDiv,
+		Mod if Ty::unsigned(ty).is_some() => UMod,
+		Lt if Ty::unsigned(ty).is_some() => ULt,
+		Le if Ty::unsigned(ty).is_some() => ULe,
+		Ge if Ty::unsigned(ty).is_some() => UGe,
+		Gt if Ty::unsigned(ty).is_some() => UGt,
+		Div if ty.is_some_and(Ty::is_int) => IntDiv,
+		op => op,
+	}
+}
+
+/// A panic message starting with `prefix`, followed by the one the macro was given.
+fn prefixed(prefix: &str, message: Option<Item>, span: Option<super::Span>) -> Item {
+	use super::format::FormatArg;
+
+	match message {
+		None => Item::new(ItemKind::ExprString(prefix.into()), span),
+		Some(message) => Item::new(
+			ItemKind::ExprFString {
+				strings: vec![format!("{prefix}: "), String::new()],
+				args: vec![FormatArg { value: 0, spec: Default::default() }],
+				values: vec![message],
+			},
+			span,
+		),
+	}
+}
+
+/// The message of `eprintln!` and `writeln!`, followed by a newline.
+fn line(message: Option<Item>, span: Option<super::Span>) -> Item {
+	let mut message = message.unwrap_or_else(|| Item::new(ItemKind::ExprString(String::new()), span));
+	match &mut message.kind {
+		ItemKind::ExprString(s) => s.push('\n'),
+		ItemKind::ExprFString { strings, .. } => strings.last_mut().unwrap().push('\n'),
+		_ => unreachable!("format strings are lowered to strings"),
+	}
+	message
+}
+
+/// Whether the first argument of a macro is a format string, rather than only a value followed by others.
+fn has_format_string(args: &syn::punctuated::Punctuated<syn::Expr, syn::Token![,]>) -> bool {
+	match args.first() {
+		Some(syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. })) => args.len() == 1 || s.value().replace("{{", "").contains('{'),
+		_ => false,
+	}
+}
+
+/// The variant of `Option` or `Result` a path names, either by itself or through `std`.
+fn variant(path: &syn::Path) -> Option<Variant> {
+	let mut segments = path.segments.iter().rev();
+	let last = segments.next()?;
+	let std = ["std", "core", "option", "result", "Option", "Result"];
+	if !segments.all(|segment| std.iter().any(|name| segment.ident == name)) {
+		return None;
+	}
+	Variant::ALL.into_iter().find(|variant| last.ident == variant.name())
+}
+
+/// `base.member`, where tuple fields are indexes.
+fn member(base: Item, member: syn::Member, span: Option<super::Span>) -> ItemKind {
+	match member {
+		syn::Member::Named(name) => ItemKind::ExprField {
+			expr: Box::new(base),
+			field: name.unraw().to_string(),
+		},
+		syn::Member::Unnamed(index) => ItemKind::ExprIndex {
+			expr: Box::new(base),
+			index: Box::new(Item::new(ItemKind::ExprInteger(index.index as i64, None), span)),
+		},
+	}
+}
+
+/// The tokens of a statement, terminated by a `;`.
+fn quote_semi(mut tokens: Vec<proc_macro2::TokenTree>) -> proc_macro2::TokenStream {
+	tokens.push(proc_macro2::Punct::new(';', proc_macro2::Spacing::Alone).into());
+	tokens.into_iter().collect()
+}
+
+/// Wraps a tail expression in a return, descending into ifs so each branch returns its own value.
+fn return_tail(item: Item) -> Item {
+	let tail = |stmts: &mut Vec<Item>| {
+		if let Some(last) = stmts.pop() {
+			stmts.push(return_tail(last));
+		}
+	};
+
+	match item.kind {
+		ItemKind::IfElif(mut if_elif) => {
+			tail(&mut if_elif.stmts);
+			for (_, stmts) in &mut if_elif.elif {
+				tail(stmts);
+			}
+			if let Some(stmts) = &mut if_elif.else_stmts {
+				tail(stmts);
+			}
+			Item::new(ItemKind::IfElif(if_elif), item.span)
+		}
+
+		ItemKind::IfLet { pattern, expr, mut stmts, mut else_stmts } => {
+			tail(&mut stmts);
+			if let Some(stmts) = &mut else_stmts {
+				tail(stmts);
+			}
+			Item::new(ItemKind::IfLet { pattern, expr, stmts, else_stmts }, item.span)
+		}
+
+		ItemKind::Block { mut stmts } => {
+			tail(&mut stmts);
+			Item::new(ItemKind::Block { stmts }, item.span)
+		}
+
+		kind if kind.is_expr() => Item::new(
+			ItemKind::Return {
+				expr: Some(Box::new(Item::new(kind, item.span))),
+			},
+			item.span,
+		),
+
+		kind => Item::new(kind, item.span),
 	}
 }
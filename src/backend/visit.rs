@@ -0,0 +1,617 @@
+//! Traversal of the IR.
+//!
+//! [Visit] and [VisitMut] have a method for every [ItemKind] variant, named after it, which walks its children by default.
+//! Override the ones you care about and call [walk_item] / [walk_item_mut] from `visit_item` to keep descending.
+//!
+//! [Fold] takes ownership instead, and lets statements be dropped or expanded into several.
+
+use super::format::FormatArg;
+use super::{BinaryOp, Builtin, IfElif, Item, ItemKind, NumType, Pattern, UnaryOp, Variant};
+
+pub trait Visit {
+	fn visit_item(&mut self, item: &Item) {
+		walk_item(self, item)
+	}
+
+	fn visit_stmts(&mut self, stmts: &[Item]) {
+		for stmt in stmts {
+			self.visit_item(stmt);
+		}
+	}
+
+	fn visit_function_definition(&mut self, _name: &str, _public: bool, _params: &[String], stmts: &[Item]) {
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_while(&mut self, condition: &Item, stmts: &[Item]) {
+		self.visit_item(condition);
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_for_in(&mut self, _var: &str, expr: &Item, stmts: &[Item]) {
+		self.visit_item(expr);
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_for_range(&mut self, _var: &str, min: &Item, max: &Item, jump: Option<&Item>, stmts: &[Item]) {
+		self.visit_item(min);
+		self.visit_item(max);
+		if let Some(jump) = jump {
+			self.visit_item(jump);
+		}
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_if_elif(&mut self, if_elif: &IfElif) {
+		self.visit_item(&if_elif.condition);
+		self.visit_stmts(&if_elif.stmts);
+		for (cond, stmts) in &if_elif.elif {
+			self.visit_item(cond);
+			self.visit_stmts(stmts);
+		}
+		if let Some(stmts) = &if_elif.else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_if_let(&mut self, _pattern: &Pattern, expr: &Item, stmts: &[Item], else_stmts: Option<&[Item]>) {
+		self.visit_item(expr);
+		self.visit_stmts(stmts);
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_block(&mut self, stmts: &[Item]) {
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_var_set(&mut self, _name: &str, expr: &Item) {
+		self.visit_item(expr);
+	}
+
+	fn visit_assign(&mut self, place: &Item, _op: Option<&BinaryOp>, expr: &Item) {
+		self.visit_item(place);
+		self.visit_item(expr);
+	}
+
+	fn visit_var_decl(&mut self, _name: &str, expr: &Item) {
+		self.visit_item(expr);
+	}
+
+	fn visit_let(&mut self, _pattern: &Pattern, expr: Option<&Item>, else_stmts: Option<&[Item]>) {
+		if let Some(expr) = expr {
+			self.visit_item(expr);
+		}
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_break(&mut self) {}
+
+	fn visit_continue(&mut self) {}
+
+	fn visit_return(&mut self, expr: Option<&Item>) {
+		if let Some(expr) = expr {
+			self.visit_item(expr);
+		}
+	}
+
+	fn visit_externs(&mut self, _functions: &[String]) {}
+
+	fn visit_mod(&mut self, _name: &str, items: &[Item]) {
+		self.visit_stmts(items);
+	}
+
+	fn visit_expr_call(&mut self, func: &Item, args: &[Item]) {
+		self.visit_item(func);
+		self.visit_stmts(args);
+	}
+
+	fn visit_expr_builtin_call(&mut self, _builtin: Builtin, args: &[Item]) {
+		self.visit_stmts(args);
+	}
+
+	fn visit_expr_ident(&mut self, _name: &str) {}
+
+	fn visit_expr_decimal(&mut self, _value: f64) {}
+
+	fn visit_expr_integer(&mut self, _value: i64, _ty: Option<NumType>) {}
+
+	fn visit_expr_string(&mut self, _value: &str) {}
+
+	fn visit_expr_bool(&mut self, _value: bool) {}
+
+	fn visit_expr_closure(&mut self, _params: &[String], stmts: &[Item]) {
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_expr_array(&mut self, elements: &[Item]) {
+		self.visit_stmts(elements);
+	}
+
+	fn visit_expr_tuple(&mut self, elements: &[Item]) {
+		self.visit_stmts(elements);
+	}
+
+	fn visit_expr_struct(&mut self, _path: &str, fields: &[(String, Item)]) {
+		for (_, value) in fields {
+			self.visit_item(value);
+		}
+	}
+
+	fn visit_expr_binary(&mut self, lhs: &Item, rhs: &Item, _op: &BinaryOp) {
+		self.visit_item(lhs);
+		self.visit_item(rhs);
+	}
+
+	fn visit_expr_unary(&mut self, expr: &Item, _op: UnaryOp) {
+		self.visit_item(expr);
+	}
+
+	fn visit_expr_cast(&mut self, expr: &Item, _ty: NumType) {
+		self.visit_item(expr);
+	}
+
+	fn visit_expr_wrap(&mut self, expr: &Item, _ty: NumType) {
+		self.visit_item(expr);
+	}
+
+	fn visit_expr_field(&mut self, expr: &Item, _field: &str) {
+		self.visit_item(expr);
+	}
+
+	fn visit_expr_index(&mut self, expr: &Item, index: &Item) {
+		self.visit_item(expr);
+		self.visit_item(index);
+	}
+
+	fn visit_expr_fstring(&mut self, _strings: &[String], _args: &[FormatArg], values: &[Item]) {
+		self.visit_stmts(values);
+	}
+
+	fn visit_expr_variant(&mut self, _variant: Variant, value: Option<&Item>) {
+		if let Some(value) = value {
+			self.visit_item(value);
+		}
+	}
+
+	fn visit_expr_try(&mut self, expr: &Item) {
+		self.visit_item(expr);
+	}
+
+	fn visit_expr_method_call(&mut self, receiver: &Item, _method: &str, args: &[Item]) {
+		self.visit_item(receiver);
+		self.visit_stmts(args);
+	}
+}
+
+/// Dispatches to the [Visit] method for the item's variant.
+pub fn walk_item<V: Visit + ?Sized>(v: &mut V, item: &Item) {
+	match &item.kind {
+		ItemKind::FunctionDefinition { name, public, params, stmts } => {
+			v.visit_function_definition(name, *public, params, stmts)
+		}
+		ItemKind::While { condition, stmts } => v.visit_while(condition, stmts),
+		ItemKind::ForIn { var, expr, stmts } => v.visit_for_in(var, expr, stmts),
+		ItemKind::ForRange { var, min, max, jump, stmts } => v.visit_for_range(var, min, max, jump.as_deref(), stmts),
+		ItemKind::IfElif(if_elif) => v.visit_if_elif(if_elif),
+		ItemKind::IfLet { pattern, expr, stmts, else_stmts } => v.visit_if_let(pattern, expr, stmts, else_stmts.as_deref()),
+		ItemKind::Block { stmts } => v.visit_block(stmts),
+		ItemKind::VarSet { name, expr } => v.visit_var_set(name, expr),
+		ItemKind::Assign { place, op, expr } => v.visit_assign(place, op.as_ref(), expr),
+		ItemKind::VarDecl { name, expr } => v.visit_var_decl(name, expr),
+		ItemKind::Let { pattern, expr, else_stmts } => v.visit_let(pattern, expr.as_deref(), else_stmts.as_deref()),
+		ItemKind::Break => v.visit_break(),
+		ItemKind::Continue => v.visit_continue(),
+		ItemKind::Return { expr } => v.visit_return(expr.as_deref()),
+		ItemKind::Externs { functions } => v.visit_externs(functions),
+		ItemKind::Mod { name, items } => v.visit_mod(name, items),
+		ItemKind::ExprCall { func, args } => v.visit_expr_call(func, args),
+		ItemKind::ExprBuiltinCall { builtin, args } => v.visit_expr_builtin_call(*builtin, args),
+		ItemKind::ExprIdent(name) => v.visit_expr_ident(name),
+		ItemKind::ExprDecimal(value) => v.visit_expr_decimal(*value),
+		ItemKind::ExprInteger(value, ty) => v.visit_expr_integer(*value, *ty),
+		ItemKind::ExprString(value) => v.visit_expr_string(value),
+		ItemKind::ExprBool(value) => v.visit_expr_bool(*value),
+		ItemKind::ExprClosure { params, stmts } => v.visit_expr_closure(params, stmts),
+		ItemKind::ExprArray { elements } => v.visit_expr_array(elements),
+		ItemKind::ExprTuple { elements } => v.visit_expr_tuple(elements),
+		ItemKind::ExprStruct { path, fields } => v.visit_expr_struct(path, fields),
+		ItemKind::ExprBinary { lhs, rhs, op } => v.visit_expr_binary(lhs, rhs, op),
+		ItemKind::ExprUnary { op, expr } => v.visit_expr_unary(expr, *op),
+		ItemKind::ExprCast { expr, ty } => v.visit_expr_cast(expr, *ty),
+		ItemKind::ExprWrap { expr, ty } => v.visit_expr_wrap(expr, *ty),
+		ItemKind::ExprField { expr, field } => v.visit_expr_field(expr, field),
+		ItemKind::ExprIndex { expr, index } => v.visit_expr_index(expr, index),
+		ItemKind::ExprFString { strings, args, values } => v.visit_expr_fstring(strings, args, values),
+		ItemKind::ExprVariant { variant, value } => v.visit_expr_variant(*variant, value.as_deref()),
+		ItemKind::ExprTry(expr) => v.visit_expr_try(expr),
+		ItemKind::ExprMethodCall { receiver, method, args } => v.visit_expr_method_call(receiver, method, args),
+	}
+}
+
+pub trait VisitMut {
+	fn visit_item_mut(&mut self, item: &mut Item) {
+		walk_item_mut(self, item)
+	}
+
+	fn visit_stmts_mut(&mut self, stmts: &mut Vec<Item>) {
+		for stmt in stmts {
+			self.visit_item_mut(stmt);
+		}
+	}
+
+	fn visit_function_definition_mut(
+		&mut self,
+		_name: &mut String,
+		_public: &mut bool,
+		_params: &mut Vec<String>,
+		stmts: &mut Vec<Item>,
+	) {
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_while_mut(&mut self, condition: &mut Item, stmts: &mut Vec<Item>) {
+		self.visit_item_mut(condition);
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_for_in_mut(&mut self, _var: &mut String, expr: &mut Item, stmts: &mut Vec<Item>) {
+		self.visit_item_mut(expr);
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_for_range_mut(
+		&mut self,
+		_var: &mut String,
+		min: &mut Item,
+		max: &mut Item,
+		jump: Option<&mut Item>,
+		stmts: &mut Vec<Item>,
+	) {
+		self.visit_item_mut(min);
+		self.visit_item_mut(max);
+		if let Some(jump) = jump {
+			self.visit_item_mut(jump);
+		}
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_if_elif_mut(&mut self, if_elif: &mut IfElif) {
+		self.visit_item_mut(&mut if_elif.condition);
+		self.visit_stmts_mut(&mut if_elif.stmts);
+		for (cond, stmts) in &mut if_elif.elif {
+			self.visit_item_mut(cond);
+			self.visit_stmts_mut(stmts);
+		}
+		if let Some(stmts) = &mut if_elif.else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_if_let_mut(
+		&mut self,
+		_pattern: &mut Pattern,
+		expr: &mut Item,
+		stmts: &mut Vec<Item>,
+		else_stmts: Option<&mut Vec<Item>>,
+	) {
+		self.visit_item_mut(expr);
+		self.visit_stmts_mut(stmts);
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_block_mut(&mut self, stmts: &mut Vec<Item>) {
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_var_set_mut(&mut self, _name: &mut String, expr: &mut Item) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_assign_mut(&mut self, place: &mut Item, _op: Option<&mut BinaryOp>, expr: &mut Item) {
+		self.visit_item_mut(place);
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_var_decl_mut(&mut self, _name: &mut String, expr: &mut Item) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_let_mut(&mut self, _pattern: &mut Pattern, expr: Option<&mut Item>, else_stmts: Option<&mut Vec<Item>>) {
+		if let Some(expr) = expr {
+			self.visit_item_mut(expr);
+		}
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_break_mut(&mut self) {}
+
+	fn visit_continue_mut(&mut self) {}
+
+	fn visit_return_mut(&mut self, expr: Option<&mut Item>) {
+		if let Some(expr) = expr {
+			self.visit_item_mut(expr);
+		}
+	}
+
+	fn visit_externs_mut(&mut self, _functions: &mut Vec<String>) {}
+
+	fn visit_mod_mut(&mut self, _name: &mut String, items: &mut Vec<Item>) {
+		self.visit_stmts_mut(items);
+	}
+
+	fn visit_expr_call_mut(&mut self, func: &mut Item, args: &mut Vec<Item>) {
+		self.visit_item_mut(func);
+		self.visit_stmts_mut(args);
+	}
+
+	fn visit_expr_builtin_call_mut(&mut self, _builtin: &mut Builtin, args: &mut Vec<Item>) {
+		self.visit_stmts_mut(args);
+	}
+
+	fn visit_expr_ident_mut(&mut self, _name: &mut String) {}
+
+	fn visit_expr_decimal_mut(&mut self, _value: &mut f64) {}
+
+	fn visit_expr_integer_mut(&mut self, _value: &mut i64, _ty: &mut Option<NumType>) {}
+
+	fn visit_expr_string_mut(&mut self, _value: &mut String) {}
+
+	fn visit_expr_bool_mut(&mut self, _value: &mut bool) {}
+
+	fn visit_expr_closure_mut(&mut self, _params: &mut Vec<String>, stmts: &mut Vec<Item>) {
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_expr_array_mut(&mut self, elements: &mut Vec<Item>) {
+		self.visit_stmts_mut(elements);
+	}
+
+	fn visit_expr_tuple_mut(&mut self, elements: &mut Vec<Item>) {
+		self.visit_stmts_mut(elements);
+	}
+
+	fn visit_expr_struct_mut(&mut self, _path: &mut String, fields: &mut Vec<(String, Item)>) {
+		for (_, value) in fields {
+			self.visit_item_mut(value);
+		}
+	}
+
+	fn visit_expr_binary_mut(&mut self, lhs: &mut Item, rhs: &mut Item, _op: &mut BinaryOp) {
+		self.visit_item_mut(lhs);
+		self.visit_item_mut(rhs);
+	}
+
+	fn visit_expr_unary_mut(&mut self, expr: &mut Item, _op: &mut UnaryOp) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_expr_cast_mut(&mut self, expr: &mut Item, _ty: &mut NumType) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_expr_wrap_mut(&mut self, expr: &mut Item, _ty: &mut NumType) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_expr_field_mut(&mut self, expr: &mut Item, _field: &mut String) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_expr_index_mut(&mut self, expr: &mut Item, index: &mut Item) {
+		self.visit_item_mut(expr);
+		self.visit_item_mut(index);
+	}
+
+	fn visit_expr_fstring_mut(&mut self, _strings: &mut Vec<String>, _args: &mut Vec<FormatArg>, values: &mut Vec<Item>) {
+		self.visit_stmts_mut(values);
+	}
+
+	fn visit_expr_variant_mut(&mut self, _variant: &mut Variant, value: Option<&mut Item>) {
+		if let Some(value) = value {
+			self.visit_item_mut(value);
+		}
+	}
+
+	fn visit_expr_try_mut(&mut self, expr: &mut Item) {
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_expr_method_call_mut(&mut self, receiver: &mut Item, _method: &mut String, args: &mut Vec<Item>) {
+		self.visit_item_mut(receiver);
+		self.visit_stmts_mut(args);
+	}
+}
+
+/// Dispatches to the [VisitMut] method for the item's variant.
+pub fn walk_item_mut<V: VisitMut + ?Sized>(v: &mut V, item: &mut Item) {
+	match &mut item.kind {
+		ItemKind::FunctionDefinition { name, public, params, stmts } => {
+			v.visit_function_definition_mut(name, public, params, stmts)
+		}
+		ItemKind::While { condition, stmts } => v.visit_while_mut(condition, stmts),
+		ItemKind::ForIn { var, expr, stmts } => v.visit_for_in_mut(var, expr, stmts),
+		ItemKind::ForRange { var, min, max, jump, stmts } => v.visit_for_range_mut(var, min, max, jump.as_deref_mut(), stmts),
+		ItemKind::IfElif(if_elif) => v.visit_if_elif_mut(if_elif),
+		ItemKind::IfLet { pattern, expr, stmts, else_stmts } => v.visit_if_let_mut(pattern, expr, stmts, else_stmts.as_mut()),
+		ItemKind::Block { stmts } => v.visit_block_mut(stmts),
+		ItemKind::VarSet { name, expr } => v.visit_var_set_mut(name, expr),
+		ItemKind::Assign { place, op, expr } => v.visit_assign_mut(place, op.as_mut(), expr),
+		ItemKind::VarDecl { name, expr } => v.visit_var_decl_mut(name, expr),
+		ItemKind::Let { pattern, expr, else_stmts } => v.visit_let_mut(pattern, expr.as_deref_mut(), else_stmts.as_mut()),
+		ItemKind::Break => v.visit_break_mut(),
+		ItemKind::Continue => v.visit_continue_mut(),
+		ItemKind::Return { expr } => v.visit_return_mut(expr.as_deref_mut()),
+		ItemKind::Externs { functions } => v.visit_externs_mut(functions),
+		ItemKind::Mod { name, items } => v.visit_mod_mut(name, items),
+		ItemKind::ExprCall { func, args } => v.visit_expr_call_mut(func, args),
+		ItemKind::ExprBuiltinCall { builtin, args } => v.visit_expr_builtin_call_mut(builtin, args),
+		ItemKind::ExprIdent(name) => v.visit_expr_ident_mut(name),
+		ItemKind::ExprDecimal(value) => v.visit_expr_decimal_mut(value),
+		ItemKind::ExprInteger(value, ty) => v.visit_expr_integer_mut(value, ty),
+		ItemKind::ExprString(value) => v.visit_expr_string_mut(value),
+		ItemKind::ExprBool(value) => v.visit_expr_bool_mut(value),
+		ItemKind::ExprClosure { params, stmts } => v.visit_expr_closure_mut(params, stmts),
+		ItemKind::ExprArray { elements } => v.visit_expr_array_mut(elements),
+		ItemKind::ExprTuple { elements } => v.visit_expr_tuple_mut(elements),
+		ItemKind::ExprStruct { path, fields } => v.visit_expr_struct_mut(path, fields),
+		ItemKind::ExprBinary { lhs, rhs, op } => v.visit_expr_binary_mut(lhs, rhs, op),
+		ItemKind::ExprUnary { op, expr } => v.visit_expr_unary_mut(expr, op),
+		ItemKind::ExprCast { expr, ty } => v.visit_expr_cast_mut(expr, ty),
+		ItemKind::ExprWrap { expr, ty } => v.visit_expr_wrap_mut(expr, ty),
+		ItemKind::ExprField { expr, field } => v.visit_expr_field_mut(expr, field),
+		ItemKind::ExprIndex { expr, index } => v.visit_expr_index_mut(expr, index),
+		ItemKind::ExprFString { strings, args, values } => v.visit_expr_fstring_mut(strings, args, values),
+		ItemKind::ExprVariant { variant, value } => v.visit_expr_variant_mut(variant, value.as_deref_mut()),
+		ItemKind::ExprTry(expr) => v.visit_expr_try_mut(expr),
+		ItemKind::ExprMethodCall { receiver, method, args } => v.visit_expr_method_call_mut(receiver, method, args),
+	}
+}
+
+pub trait Fold {
+	fn fold_item(&mut self, item: Item) -> Item {
+		fold_children(self, item)
+	}
+
+	/// Statements are folded one by one through [Fold::fold_stmt], so they can be removed or expanded.
+	fn fold_stmts(&mut self, stmts: Vec<Item>) -> Vec<Item> {
+		stmts.into_iter().flat_map(|stmt| self.fold_stmt(stmt)).collect()
+	}
+
+	fn fold_stmt(&mut self, stmt: Item) -> Vec<Item> {
+		vec![self.fold_item(stmt)]
+	}
+}
+
+/// Rebuilds the item with every child folded, keeping its span.
+pub fn fold_children<F: Fold + ?Sized>(f: &mut F, item: Item) -> Item {
+	let boxed = |f: &mut F, e: Box<Item>| Box::new(f.fold_item(*e));
+
+	let kind = match item.kind {
+		ItemKind::FunctionDefinition { name, public, params, stmts } => ItemKind::FunctionDefinition {
+			name,
+			public,
+			params,
+			stmts: f.fold_stmts(stmts),
+		},
+		ItemKind::While { condition, stmts } => ItemKind::While {
+			condition: boxed(f, condition),
+			stmts: f.fold_stmts(stmts),
+		},
+		ItemKind::ForIn { var, expr, stmts } => ItemKind::ForIn {
+			var,
+			expr: boxed(f, expr),
+			stmts: f.fold_stmts(stmts),
+		},
+		ItemKind::ForRange { var, min, max, jump, stmts } => ItemKind::ForRange {
+			var,
+			min: boxed(f, min),
+			max: boxed(f, max),
+			jump: jump.map(|j| boxed(f, j)),
+			stmts: f.fold_stmts(stmts),
+		},
+		ItemKind::IfElif(IfElif { condition, stmts, elif, else_stmts }) => ItemKind::IfElif(IfElif {
+			condition: boxed(f, condition),
+			stmts: f.fold_stmts(stmts),
+			elif: elif
+				.into_iter()
+				.map(|(cond, stmts)| (f.fold_item(cond), f.fold_stmts(stmts)))
+				.collect(),
+			else_stmts: else_stmts.map(|stmts| f.fold_stmts(stmts)),
+		}),
+		ItemKind::IfLet { pattern, expr, stmts, else_stmts } => ItemKind::IfLet {
+			pattern,
+			expr: boxed(f, expr),
+			stmts: f.fold_stmts(stmts),
+			else_stmts: else_stmts.map(|stmts| f.fold_stmts(stmts)),
+		},
+		ItemKind::Block { stmts } => ItemKind::Block { stmts: f.fold_stmts(stmts) },
+		ItemKind::VarSet { name, expr } => ItemKind::VarSet { name, expr: boxed(f, expr) },
+		ItemKind::Assign { place, op, expr } => ItemKind::Assign {
+			place: boxed(f, place),
+			op,
+			expr: boxed(f, expr),
+		},
+		ItemKind::VarDecl { name, expr } => ItemKind::VarDecl { name, expr: boxed(f, expr) },
+		ItemKind::Let { pattern, expr, else_stmts } => ItemKind::Let {
+			pattern,
+			expr: expr.map(|e| boxed(f, e)),
+			else_stmts: else_stmts.map(|stmts| f.fold_stmts(stmts)),
+		},
+		ItemKind::Return { expr } => ItemKind::Return {
+			expr: expr.map(|e| boxed(f, e)),
+		},
+		ItemKind::Mod { name, items } => ItemKind::Mod {
+			name,
+			items: f.fold_stmts(items),
+		},
+		ItemKind::ExprCall { func, args } => ItemKind::ExprCall {
+			func: boxed(f, func),
+			args: args.into_iter().map(|arg| f.fold_item(arg)).collect(),
+		},
+		ItemKind::ExprBuiltinCall { builtin, args } => ItemKind::ExprBuiltinCall {
+			builtin,
+			args: args.into_iter().map(|arg| f.fold_item(arg)).collect(),
+		},
+		ItemKind::ExprClosure { params, stmts } => ItemKind::ExprClosure {
+			params,
+			stmts: f.fold_stmts(stmts),
+		},
+		ItemKind::ExprArray { elements } => ItemKind::ExprArray {
+			elements: elements.into_iter().map(|e| f.fold_item(e)).collect(),
+		},
+		ItemKind::ExprTuple { elements } => ItemKind::ExprTuple {
+			elements: elements.into_iter().map(|e| f.fold_item(e)).collect(),
+		},
+		ItemKind::ExprStruct { path, fields } => ItemKind::ExprStruct {
+			path,
+			fields: fields.into_iter().map(|(name, value)| (name, f.fold_item(value))).collect(),
+		},
+		ItemKind::ExprBinary { lhs, rhs, op } => ItemKind::ExprBinary {
+			lhs: boxed(f, lhs),
+			rhs: boxed(f, rhs),
+			op,
+		},
+		ItemKind::ExprUnary { op, expr } => ItemKind::ExprUnary { op, expr: boxed(f, expr) },
+		ItemKind::ExprCast { expr, ty } => ItemKind::ExprCast { expr: boxed(f, expr), ty },
+		ItemKind::ExprWrap { expr, ty } => ItemKind::ExprWrap { expr: boxed(f, expr), ty },
+		ItemKind::ExprField { expr, field } => ItemKind::ExprField { expr: boxed(f, expr), field },
+		ItemKind::ExprIndex { expr, index } => ItemKind::ExprIndex {
+			expr: boxed(f, expr),
+			index: boxed(f, index),
+		},
+		ItemKind::ExprFString { strings, args, values } => ItemKind::ExprFString {
+			strings,
+			args,
+			values: values.into_iter().map(|v| f.fold_item(v)).collect(),
+		},
+		ItemKind::ExprVariant { variant, value } => ItemKind::ExprVariant {
+			variant,
+			value: value.map(|v| boxed(f, v)),
+		},
+		ItemKind::ExprTry(expr) => ItemKind::ExprTry(boxed(f, expr)),
+		ItemKind::ExprMethodCall { receiver, method, args } => ItemKind::ExprMethodCall {
+			receiver: boxed(f, receiver),
+			method,
+			args: args.into_iter().map(|arg| f.fold_item(arg)).collect(),
+		},
+
+		leaf @ (ItemKind::Break
+		| ItemKind::Continue
+		| ItemKind::Externs { .. }
+		| ItemKind::ExprIdent(_)
+		| ItemKind::ExprDecimal(_)
+		| ItemKind::ExprInteger(..)
+		| ItemKind::ExprString(_)
+		| ItemKind::ExprBool(_)) => leaf,
+	};
+
+	Item::new(kind, item.span)
+}
@@ -1,18 +1,50 @@
+use crate::backend::Span;
+
 pub mod lua;
 
+mod mangle;
+pub use mangle::Mangler;
+
 #[derive(Debug, thiserror::Error)]
 pub enum CodegenError {
 	#[cfg(feature = "syn")]
 	#[error("Internal Syn Error: {0}")]
 	Syn(#[from] ::syn::Error),
 
-	#[cfg(feature = "venial")]
+	#[cfg(all(feature = "venial", not(feature = "syn")))]
 	#[error("Internal Venial Error: {0}")]
 	Venial(#[from] ::venial::Error),
+
+	/// Something the target can't express, such as bitwise operators on Lua 5.1.
+	#[error("{what} can't be expressed in {target}")]
+	Unsupported { what: &'static str, target: &'static str, span: Option<Span> },
+}
+
+impl CodegenError {
+	pub fn span(&self) -> Option<Span> {
+		match self {
+			Self::Unsupported { span, .. } => *span,
+			#[cfg(feature = "syn")]
+			Self::Syn(_) => None,
+			#[cfg(all(feature = "venial", not(feature = "syn")))]
+			Self::Venial(_) => None,
+		}
+	}
 }
 
 pub type Result<T> = std::result::Result<T, CodegenError>;
 
 pub trait CodeGenerator: Sized {
+	/// Words that can't be used as identifiers in the generated code, which [Mangler] renames.
+	const RESERVED: &'static [&'static str] = &[];
+	/// Prefixes of names the generated code numbers, like `__arg0` and `__arg1`. [Mangler] renames every name
+	/// starting with one.
+	const RESERVED_PREFIXES: &'static [&'static str] = &[];
+
 	fn generate(&self, ast: &impl crate::backend::Ast) -> Result<String>;
+
+	/// Whether the character can be part of an identifier, `first` being whether it starts it.
+	fn is_ident_char(c: char, first: bool) -> bool {
+		c == '_' || c.is_ascii_alphabetic() || (!first && c.is_ascii_digit())
+	}
 }
@@ -0,0 +1,239 @@
+//! Format strings, rendered with `string.format`.
+//!
+//! Specs that mean the same thing for every type of value map onto a `string.format` directive. The rest
+//! depend on whether the value is a number, so they go through `__fmt` from `fmt.lua`, which is only
+//! included when a file needs it. That includes `{}` of anything that could be a float, since `%s` prints
+//! `1.0` where Rust prints `1`, and `0.1 + 0.2` with 14 digits instead of the 17 Rust needs.
+
+use std::collections::HashSet;
+
+use crate::backend::format::{self, Align, Count, FormatArg, FormatKind, FormatSpec};
+use crate::backend::visit::{self, Visit};
+use crate::backend::{BinaryOp, Item, ItemKind};
+
+use super::{Emitter, LuaVersion};
+
+const PRELUDE: &str = include_str!("fmt.lua");
+
+/// Whether `__fmt` prints the value as a `u64`, which Lua's integers would print as negative above `i64::MAX`.
+fn unsigned(value: &Item, spec: &FormatSpec) -> bool {
+	matches!(spec.kind, FormatKind::Display | FormatKind::Debug) && format::unsigned(value)
+}
+
+/// Locals `%s` prints like `{}` does, which their declaration decides since a local's type never changes. Names
+/// are unique within a function after [crate::shadow::unshadow], so each function starts with none.
+#[derive(Debug, Default)]
+pub(super) struct Plain(HashSet<String>);
+
+impl Plain {
+	/// Whether `%s` prints `value` like `{}` does, which it's known to for integers, strings and bools.
+	fn displays_as_is(&self, value: &Item) -> bool {
+		match &value.kind {
+			ItemKind::ExprInteger(..) | ItemKind::ExprString(_) | ItemKind::ExprBool(_) | ItemKind::ExprFString { .. } => {
+				true
+			}
+			ItemKind::ExprIdent(name) => self.0.contains(name),
+			ItemKind::ExprCast { ty, .. } | ItemKind::ExprWrap { ty, .. } => !ty.is_float(),
+			ItemKind::ExprBinary { op, .. } => {
+				use BinaryOp::*;
+				matches!(op, IntDiv | And | Or | Eq | Ne | Lt | Le | Gt | Ge | UDiv | UMod | ULt | ULe | UGe | UGt)
+			}
+			_ => false,
+		}
+	}
+
+	/// Remembers the locals a statement declares, if they're plain.
+	pub(super) fn declare(&mut self, item: &Item) {
+		match &item.kind {
+			ItemKind::VarDecl { name, expr } if self.displays_as_is(expr) => {
+				self.0.insert(name.clone());
+			}
+			// Only ranges of integers can be iterated.
+			ItemKind::ForRange { var, .. } => {
+				self.0.insert(var.clone());
+			}
+			_ => (),
+		}
+	}
+}
+
+/// The `string.format` directive for specs that don't depend on the value's type, or for `{}` of a value that
+/// prints the same either way.
+fn directive(spec: &FormatSpec, value: &Item, plain: &Plain) -> Option<String> {
+	use FormatKind::*;
+
+	if spec.fill != ' ' || spec.sign || spec.precision.is_some() || matches!(spec.width, Some(Count::Arg(_))) {
+		return None;
+	}
+
+	let width = match spec.width {
+		Some(Count::Is(n)) => n.to_string(),
+		_ => String::new(),
+	};
+
+	match spec.kind {
+		Display if !plain.displays_as_is(value) || format::unsigned(value) => None,
+		Display if spec.is_default() => Some("%s".into()),
+		// Strings and numbers are aligned differently by default.
+		Display if !spec.alternate && !spec.zero => match spec.align {
+			Some(Align::Left) => Some(format!("%-{width}s")),
+			Some(Align::Right) => Some(format!("%{width}s")),
+			_ => None,
+		},
+
+		// Only numbers can be formatted as hex, so the alignment is known. Zeroes ignore it, like in Rust.
+		// `%#x` leaves out the prefix of 0, so `#` is left to the helper.
+		LowerHex | UpperHex | Octal if spec.align != Some(Align::Center) && !spec.alternate => {
+			let left = if spec.align == Some(Align::Left) && !spec.zero { "-" } else { "" };
+			let zero = if spec.zero { "0" } else { "" };
+			Some(format!("%{left}{zero}{width}{}", spec.kind.as_str()))
+		}
+
+		_ => None,
+	}
+}
+
+/// Whether any format string in `items` needs `__fmt`.
+fn needs_prelude(items: &[Item]) -> bool {
+	#[derive(Default)]
+	struct Finder(bool, Plain);
+
+	impl Visit for Finder {
+		fn visit_item(&mut self, item: &Item) {
+			self.1.declare(item);
+			visit::walk_item(self, item);
+		}
+
+		fn visit_function_definition(&mut self, _name: &str, _public: bool, _params: &[String], stmts: &[Item]) {
+			let outer = std::mem::take(&mut self.1);
+			self.visit_stmts(stmts);
+			self.1 = outer;
+		}
+
+		fn visit_expr_fstring(&mut self, _strings: &[String], args: &[FormatArg], values: &[Item]) {
+			self.0 |= args.iter().any(|arg| directive(&arg.spec, &values[arg.value], &self.1).is_none());
+			for value in values {
+				self.visit_item(value);
+			}
+		}
+	}
+
+	let mut finder = Finder::default();
+	finder.visit_stmts(items);
+	finder.0
+}
+
+impl Emitter<'_> {
+	/// Defines `__fmt` when the items need it.
+	pub(super) fn push_format_prelude(&mut self, items: &[Item]) {
+		if needs_prelude(items) {
+			let base = format!("local base = {};", self.codegen.index_base.offset());
+			let prelude = PRELUDE.replace("--[[base]]", &base).replace("--[[unsigned]]", self.codegen.lua_version.unsigned());
+			self.buf.push_str(&super::inline_lua(&prelude));
+		}
+	}
+
+	pub(super) fn push_format(&mut self, strings: &[String], args: &[FormatArg], values: &[Item]) {
+		// Values are evaluated once each and in order, so anything else binds them to parameters first.
+		let mut counts = args.iter().flat_map(|arg| [arg.spec.width, arg.spec.precision]);
+		let in_order = args.iter().map(|arg| arg.value).eq(0..values.len())
+			&& !counts.any(|count| matches!(count, Some(Count::Arg(_))));
+
+		let params = (0..values.len()).map(|i| format!("_{}", i + 1)).collect::<Vec<_>>();
+		if !in_order {
+			self.buf.push_str(&format!("(function({}) return ", params.join(",")));
+		}
+
+		self.buf.push_str("string.format(");
+		let mut format = String::new();
+		for (i, s) in strings.iter().enumerate() {
+			format.push_str(&s.replace('%', "%%"));
+			if let Some(arg) = args.get(i) {
+				format.push_str(&directive(&arg.spec, &values[arg.value], &self.plain).unwrap_or_else(|| "%s".into()));
+			}
+		}
+		self.buf.push_str(&super::string(&format));
+
+		for arg in args {
+			self.buf.push(',');
+
+			let directive = directive(&arg.spec, &values[arg.value], &self.plain);
+			let helper = directive.is_none();
+			// `%s` only takes strings and numbers before 5.2.
+			let tostring = self.codegen.lua_version == LuaVersion::Lua51
+				&& directive.is_some_and(|d| d.ends_with('s'))
+				&& !matches!(values[arg.value].kind, ItemKind::ExprString(_));
+			if helper {
+				self.buf.push_str("__fmt(");
+			} else if tostring {
+				self.buf.push_str("tostring(");
+			}
+
+			match in_order {
+				true => self.push_item(&values[arg.value]),
+				false => self.buf.push_str(&params[arg.value]),
+			}
+
+			if helper {
+				self.buf.push(',');
+				self.push_spec(&arg.spec, &params, unsigned(&values[arg.value], &arg.spec));
+				self.buf.push(')');
+			} else if tostring {
+				self.buf.push(')');
+			}
+		}
+		self.buf.push(')');
+
+		if !in_order {
+			self.buf.push_str(" end)(");
+			for (i, value) in values.iter().enumerate() {
+				if i != 0 {
+					self.buf.push(',');
+				}
+				self.push_item(value);
+			}
+			self.buf.push(')');
+		}
+	}
+
+	/// The spec as a table for `__fmt`, leaving out the defaults.
+	fn push_spec(&mut self, spec: &FormatSpec, params: &[String], unsigned: bool) {
+		let count = |count: Count| match count {
+			Count::Is(n) => n.to_string(),
+			Count::Arg(i) => params[i].clone(),
+		};
+
+		let mut fields = vec![format!("kind=\"{}\"", spec.kind.as_str())];
+		if spec.fill != ' ' {
+			fields.push(format!("fill={}", super::string(&spec.fill.to_string())));
+		}
+		if let Some(align) = spec.align {
+			let align = match align {
+				Align::Left => "<",
+				Align::Center => "^",
+				Align::Right => ">",
+			};
+			fields.push(format!("align=\"{align}\""));
+		}
+		if spec.sign {
+			fields.push("sign=true".into());
+		}
+		if spec.alternate {
+			fields.push("alternate=true".into());
+		}
+		if spec.zero {
+			fields.push("zero=true".into());
+		}
+		if let Some(width) = spec.width {
+			fields.push(format!("width={}", count(width)));
+		}
+		if let Some(precision) = spec.precision {
+			fields.push(format!("precision={}", count(precision)));
+		}
+		if unsigned {
+			fields.push("unsigned=true".into());
+		}
+
+		self.buf.push_str(&format!("{{{}}}", fields.join(",")));
+	}
+}
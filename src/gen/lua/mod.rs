@@ -1,220 +1,710 @@
-use crate::backend::Item;
-
-#[derive(Debug)]
-pub struct LuaCodegen {}
+use crate::backend::{BinaryOp, Builtin, Item, ItemKind};
+
+use super::Mangler;
+
+mod format;
+mod number;
+mod option;
+mod pattern;
+mod place;
+mod sourcemap;
+mod version;
+pub use option::OptionRepr;
+pub use place::IndexBase;
+pub use sourcemap::{LineMap, Mapping};
+pub use version::LuaVersion;
+
+#[derive(Debug, Default)]
+pub struct LuaCodegen {
+	source_map: bool,
+	option_repr: OptionRepr,
+	index_base: IndexBase,
+	lua_version: LuaVersion,
+}
 
 impl LuaCodegen {
 	pub fn new() -> Self {
-		Self {}
+		Self::default()
+	}
+
+	/// Puts every statement on its own line so runtime errors point somewhere useful,
+	/// and records where each one came from. See [LuaCodegen::generate_with_map].
+	pub fn with_source_map(mut self, enabled: bool) -> Self {
+		self.source_map = enabled;
+		self
+	}
+
+	/// How `Option` values look in the generated code, and to the host. Defaults to [OptionRepr::Nil].
+	pub fn with_option_repr(mut self, repr: OptionRepr) -> Self {
+		self.option_repr = repr;
+		self
+	}
+
+	/// Where arrays and tuples start. Defaults to [IndexBase::One].
+	pub fn with_index_base(mut self, base: IndexBase) -> Self {
+		self.index_base = base;
+		self
+	}
+
+	/// The Lua the generated code has to run on. Defaults to [LuaVersion::Lua53].
+	pub fn with_lua_version(mut self, version: LuaVersion) -> Self {
+		self.lua_version = version;
+		self
+	}
+
+	/// Same as [super::CodeGenerator::generate], also returning the positions of the generated code.
+	/// The map is empty unless [LuaCodegen::with_source_map] is enabled.
+	pub fn generate_with_map(&self, ast: &impl crate::backend::Ast) -> super::Result<(String, LineMap)> {
+		let mut emitter = Emitter {
+			codegen: self,
+			mangler: Mangler::new::<Self>(),
+			buf: String::new(),
+			indent: 0,
+			line: 0,
+			line_start: 0,
+			map: LineMap::default(),
+			value: false,
+			plain: Default::default(),
+		};
+
+		// Lua has a single namespace, so a local could hide a module's table.
+		let items = crate::shadow::unshadow(ast.items().clone());
+		self.lua_version.check(&items)?;
+
+		emitter.push_format_prelude(&items);
+		emitter.push_option_prelude(&items);
+		emitter.push_number_prelude(&items);
+		emitter.push_tables(&items, None);
+		for item in &items {
+			emitter.push_stmt(item);
+		}
+
+		Ok((emitter.buf, emitter.map))
 	}
 }
 
 impl super::CodeGenerator for LuaCodegen {
+	// Globals the generated code calls, the preludes' functions and the temporaries it declares, so a user's local
+	// can't hide them.
+	const RESERVED: &'static [&'static str] = &[
+		"and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in", "local", "nil", "not",
+		"or", "repeat", "return", "then", "true", "until", "while", "_ENV", "_G", "string", "math", "pcall", "print",
+		"io", "error", "assert", "tostring", "type", "utf8", "__fmt", "__is_some", "__is_none", "__is_ok", "__is_err",
+		"__map", "__and_then", "__unwrap", "__unwrap_or", "__ok_or", "__ok", "__branch", "__try", "__catch", "__int",
+		"__idiv", "__let", "__place", "__key", "__left", "__right", "__item", "__residual", "__value",
+		"__array", "__index",
+	];
+	// Parameters that are destructured, numbered by their position.
+	const RESERVED_PREFIXES: &'static [&'static str] = &["__arg"];
+
 	fn generate(&self, ast: &impl crate::backend::Ast) -> super::Result<String> {
-		let items = ast.items();
-		let mut buf = String::new();
+		self.generate_with_map(ast).map(|(code, _)| code)
+	}
+}
 
-		let mut indent = 0;
+struct Emitter<'a> {
+	codegen: &'a LuaCodegen,
+	mangler: Mangler,
+	buf: String,
+	indent: u8,
 
-		fn push_stmts(buf: &mut String, indent: &mut u8, stmts: &Vec<Item>) {
-			*indent += 1;
-			for item in stmts {
-				push_item(buf, indent, item);
-			}
-			*indent -= 1;
-		}
+	// Current line and the byte offset it started at, only tracked for source maps.
+	line: usize,
+	line_start: usize,
+	map: LineMap,
 
-		fn push_item(buf: &mut String, indent: &mut u8, item: &Item) {
-			match item {
-				Item::FunctionDefinition {
-					name,
-					params,
-					stmts,
-				} => {
-					buf.push_str(&format!("function {name}("));
-					buf.push_str(&params.join(","));
-					buf.push(')');
+	// Whether statements are in a block used as a value, which is a function of its own.
+	value: bool,
+	plain: format::Plain,
+}
 
-					push_stmts(buf, indent, stmts);
-					buf.push_str("end;")
-				}
+impl Emitter<'_> {
+	fn newline(&mut self) {
+		if !self.codegen.source_map || self.buf.is_empty() {
+			return;
+		}
 
-				Item::VarDecl { name, expr } => {
-					buf.push_str(&format!("local {name} = "));
-					push_item(buf, indent, expr);
-					buf.push(';');
-				}
+		self.buf.push('\n');
+		self.line += 1;
+		self.line_start = self.buf.len();
 
-				Item::VarSet { name, expr } => {
-					buf.push_str(&format!("{name} = "));
-					push_item(buf, indent, expr);
-					buf.push(';');
-				}
+		for _ in 0..self.indent {
+			self.buf.push('\t');
+		}
+	}
 
-				Item::ExprCall { func, args } => {
-					push_item(buf, indent, func); // todo: push_item_inline
-					buf.push('(');
-					for (i, arg) in args.iter().enumerate() {
-						push_item(buf, indent, arg);
+	fn mark(&mut self, item: &Item) {
+		if !self.codegen.source_map {
+			return;
+		}
 
-						if i != args.len() - 1 {
-							buf.push(',');
-						}
-					}
-					buf.push(')');
-				}
+		if let Some(span) = item.span {
+			let (line, column) = (self.line, self.buf.len() - self.line_start);
 
-				Item::ExprIdent(ident) => buf.push_str(ident),
-
-				Item::ExprInteger(val) => buf.push_str(&val.to_string()),
-				Item::ExprBool(val) => buf.push_str(&val.to_string()),
-				Item::ExprString(val) => buf.push_str(&format!("\"{}\"", val.escape_default())),
-				Item::ExprFString { strings, replacements, values } => {
-					println!("{strings:?}, {replacements:?}, {values:?}");
-					let mut replacements = replacements.iter();
-					buf.push_str("string.format(\"");
-					for s in strings {
-						buf.push_str(s);
-
-						if replacements.next().is_some() {
-							buf.push_str("%s");
-						}
+			// Nested items starting at the same spot are less useful than the outermost one.
+			if !matches!(self.map.mappings.last(), Some(m) if m.line == line && m.column == column) {
+				self.map.mappings.push(Mapping { line, column, span });
+			}
+		}
+	}
+
+	/// Declares a table for every module up front, since functions can refer to modules declared after them.
+	fn push_tables(&mut self, items: &[Item], parent: Option<&str>) {
+		for item in items {
+			if let ItemKind::Mod { name, items } = &item.kind {
+				let name = self.mangler.ident(name.rsplit("::").next().unwrap_or(name));
+
+				self.newline();
+				self.mark(item);
+				let table = match parent {
+					Some(parent) => {
+						let table = format!("{parent}.{name}");
+						self.buf.push_str(&format!("{table} = {{}};"));
+						table
 					}
-					buf.push('"');
-					for v in values {
-						buf.push(',');
-						push_item(buf, indent, v);
+					None => {
+						self.buf.push_str(&format!("local {name} = {{}};"));
+						name.into_owned()
 					}
-					buf.push(')');
-				},
+				};
 
-				Item::ExprClosure { params, stmts } => {
-					buf.push_str("function(");
-					buf.push_str(&params.join(","));
-					buf.push(')');
+				self.push_tables(items, Some(&table));
+			}
+		}
+	}
 
-					push_stmts(buf, indent, stmts);
-					buf.push_str("end;")
-				}
+	/// Resolved paths are fields of their module's table, and host paths are globals.
+	fn path(&self, name: &str) -> String {
+		self.mangler.path(name.strip_prefix("::").unwrap_or(name), ".")
+	}
 
-				Item::ExprBinary { lhs, rhs, op } => {
-					use crate::backend::BinaryOp;
+	fn push_params(&mut self, params: &[String]) {
+		let params = params.iter().map(|param| self.mangler.ident(param)).collect::<Vec<_>>();
+		self.buf.push_str(&params.join(","));
+	}
 
-					push_item(buf, indent, lhs);
-					match op {
-						BinaryOp::Add => buf.push('+'),
-						BinaryOp::Sub => buf.push('-'),
-						BinaryOp::Mul => buf.push('*'),
-						BinaryOp::Div => buf.push('/'),
-						BinaryOp::Mod => buf.push('%'),
+	fn push_args(&mut self, args: &[Item]) {
+		self.buf.push('(');
+		for (i, arg) in args.iter().enumerate() {
+			self.push_item(arg);
 
-						_ => buf.push_str("test"),
-					}
+			if i != args.len() - 1 {
+				self.buf.push(',');
+			}
+		}
+		self.buf.push(')');
+	}
 
-					push_item(buf, indent, rhs);
-				}
+	/// Operands that are operations themselves are grouped with parentheses, since the IR has no precedence.
+	/// So are negative numbers and unary operators, as a `-` before another one would start a comment.
+	fn push_operand(&mut self, item: &Item) {
+		let group = match &item.kind {
+			// Division and remainder are calls.
+			ItemKind::ExprBinary { op: BinaryOp::IntDiv | BinaryOp::Mod | BinaryOp::UDiv | BinaryOp::UMod, .. } => false,
+			ItemKind::ExprBinary { .. } | ItemKind::ExprUnary { .. } | ItemKind::ExprCast { .. } | ItemKind::ExprWrap { .. } => true,
+			ItemKind::ExprInteger(val, _) => *val < 0,
+			ItemKind::ExprDecimal(val) => val.is_sign_negative(),
+			_ => false,
+		};
+
+		if group {
+			self.buf.push('(');
+		}
+		self.push_item(item);
+		if group {
+			self.buf.push(')');
+		}
+	}
 
-				Item::ExprArray { elements } => {
-					buf.push('{');
-					for (i, arg) in elements.iter().enumerate() {
-						push_item(buf, indent, arg);
+	/// Ends a loop's body, with the label `continue` jumps to. Lua 5.1 has no `goto`, and no `continue` either.
+	fn push_loop_end(&mut self) {
+		self.buf.push_str(match self.codegen.lua_version.goto() {
+			true => " ::__continue__:: end;",
+			false => " end;",
+		})
+	}
 
-						if i != elements.len() - 1 {
-							buf.push(',');
-						}
-					}
-					buf.push('}');
-				}
+	fn push_stmt(&mut self, item: &Item) {
+		self.newline();
+		if matches!(item.kind, ItemKind::FunctionDefinition { .. }) {
+			let outer = std::mem::take(&mut self.plain);
+			self.push_stmt_inline(item);
+			self.plain = outer;
+			return;
+		}
 
-				Item::IfElif(crate::backend::IfElif {
-					condition,
-					stmts,
-					elif,
-					else_stmts,
-				}) => {
-					buf.push_str("if ");
-					push_item(buf, indent, condition);
-					buf.push_str(" then ");
-					push_stmts(buf, indent, stmts);
-
-					if !elif.is_empty() {
-						for (cond, stmts) in elif {
-							buf.push_str(";elseif ");
-							push_item(buf, indent, cond);
-							buf.push_str(" then ");
-							push_stmts(buf, indent, stmts);
-						}
-					}
+		self.plain.declare(item);
+		self.push_stmt_inline(item);
+	}
+
+	/// Statements that need more than [Emitter::push_item], since Lua has neither block expressions
+	/// nor expression statements other than calls.
+	fn push_stmt_inline(&mut self, item: &Item) {
+		if self.push_try_stmt(item) {
+			return;
+		}
+
+		match &item.kind {
+			ItemKind::Block { stmts } => {
+				self.mark(item);
+				self.push_block(stmts, Tail::Discard);
+			}
+
+			ItemKind::IfElif(_) | ItemKind::IfLet { .. } => self.push_if(item),
+
+			ItemKind::VarDecl { name, expr } if is_compound(expr) => {
+				self.mark(item);
+				self.buf.push_str(&format!("local {};", self.mangler.ident(name)));
+				self.push_tail(expr, Tail::Set(name));
+			}
+
+			ItemKind::VarSet { name, expr } if is_compound(expr) => {
+				self.mark(item);
+				self.push_tail(expr, Tail::Set(name));
+			}
+
+			ItemKind::Return { expr: Some(expr) } if is_compound(expr) => {
+				self.mark(item);
+				self.push_tail(expr, Tail::Return);
+			}
+
+			kind if kind.is_expr() && !matches!(kind, ItemKind::ExprCall { .. } | ItemKind::ExprBuiltinCall { .. }) => {
+				self.buf.push_str("local _ = ");
+				self.push_item(item);
+				self.buf.push(';');
+			}
+
+			_ => self.push_item(item),
+		}
+	}
+
+	/// A `do ... end` block, doing `tail` with the value of its last statement.
+	fn push_block(&mut self, stmts: &[Item], tail: Tail) {
+		self.buf.push_str("do ");
+		self.push_stmts(&with_tail(stmts, tail));
+		self.buf.push_str("end;");
+	}
+
+	/// A block, `if` or `if let` used as a value, doing `tail` with the value of whichever branch runs.
+	fn push_tail(&mut self, expr: &Item, tail: Tail) {
+		match &expr.kind {
+			ItemKind::Block { stmts } => self.push_block(stmts, tail),
+			ItemKind::IfElif(branches) => {
+				let branches = crate::backend::IfElif {
+					condition: branches.condition.clone(),
+					stmts: with_tail(&branches.stmts, tail),
+					elif: branches.elif.iter().map(|(cond, stmts)| (cond.clone(), with_tail(stmts, tail))).collect(),
+					else_stmts: branches.else_stmts.as_ref().map(|stmts| with_tail(stmts, tail)),
+				};
+				self.push_if(&Item::new(ItemKind::IfElif(branches), expr.span));
+			}
+			ItemKind::IfLet { pattern, expr: value, stmts, else_stmts } => {
+				let if_let = ItemKind::IfLet {
+					pattern: pattern.clone(),
+					expr: value.clone(),
+					stmts: with_tail(stmts, tail),
+					else_stmts: else_stmts.as_ref().map(|stmts| with_tail(stmts, tail)),
+				};
+				self.push_if(&Item::new(if_let, expr.span));
+			}
+			other => unreachable!("{other:?} has an expression in Lua"),
+		}
+	}
+
+	/// An `if` or `if let` statement. Used as a value, [Emitter::push_item] wraps it in a function instead.
+	fn push_if(&mut self, item: &Item) {
+		self.mark(item);
 
-					if let Some(els) = else_stmts {
-						buf.push_str(" else ");
-						push_stmts(buf, indent, els);
+		match &item.kind {
+			ItemKind::IfLet { pattern, expr, stmts, else_stmts } => {
+				self.push_if_let(item, pattern, expr, stmts, else_stmts.as_deref())
+			}
+			ItemKind::IfElif(crate::backend::IfElif {
+				condition,
+				stmts,
+				elif,
+				else_stmts,
+			}) => {
+				self.buf.push_str("if ");
+				self.push_item(condition);
+				self.buf.push_str(" then ");
+				self.push_stmts(stmts);
+
+				if !elif.is_empty() {
+					for (cond, stmts) in elif {
+						self.buf.push_str(" elseif ");
+						self.push_item(cond);
+						self.buf.push_str(" then ");
+						self.push_stmts(stmts);
 					}
+				}
 
-					buf.push_str(" end;")
+				if let Some(els) = else_stmts {
+					self.buf.push_str(" else ");
+					self.push_stmts(els);
 				}
 
-				Item::While { condition, stmts } => {
-					buf.push_str("while ");
-					push_item(buf, indent, condition);
-					buf.push_str(" do ");
-					push_stmts(buf, indent, stmts);
-					buf.push_str(" ::__continue__:: end;")
+				self.buf.push_str(" end;")
+			}
+			other => unreachable!("{other:?} isn't an if"),
+		}
+	}
+
+	fn push_stmts(&mut self, stmts: &[Item]) {
+		self.indent += 1;
+
+		// Functions are visible in their whole block, so they're declared as locals and defined before anything else.
+		// They can't capture locals, so moving them up doesn't change what they refer to.
+		let (functions, stmts): (Vec<&Item>, Vec<&Item>) =
+			stmts.iter().partition(|item| matches!(item.kind, ItemKind::FunctionDefinition { .. }));
+
+		if !functions.is_empty() {
+			let names = functions
+				.iter()
+				.filter_map(|item| match &item.kind {
+					ItemKind::FunctionDefinition { name, .. } => Some(self.mangler.ident(name)),
+					_ => None,
+				})
+				.collect::<Vec<_>>();
+
+			self.newline();
+			self.buf.push_str(&format!("local {};", names.join(",")));
+			for function in functions {
+				self.push_stmt(function);
+			}
+		}
+
+		for (i, item) in stmts.iter().enumerate() {
+			// Lua only allows return as the last statement of a block.
+			if matches!(item.kind, ItemKind::Return { .. }) && i != stmts.len() - 1 {
+				self.newline();
+				self.buf.push_str("do ");
+				self.push_stmt_inline(item);
+				self.buf.push_str(" end;");
+				continue;
+			}
+
+			self.push_stmt(item);
+		}
+		self.indent -= 1;
+		self.newline();
+	}
+
+	fn push_item(&mut self, item: &Item) {
+		self.mark(item);
+
+		match &item.kind {
+			ItemKind::FunctionDefinition {
+				name,
+				params,
+				stmts,
+				..
+			} => {
+				self.buf.push_str(&format!("function {}(", self.path(name)));
+				self.push_params(params);
+				self.buf.push(')');
+
+				self.push_body(stmts);
+				self.buf.push_str("end;")
+			}
+
+			ItemKind::VarDecl { name, expr } => {
+				self.buf.push_str(&format!("local {} = ", self.mangler.ident(name)));
+				self.push_item(expr);
+				self.buf.push(';');
+			}
+
+			ItemKind::Let { pattern, expr, else_stmts } => self.push_let(item, pattern, expr.as_deref(), else_stmts.as_deref()),
+
+			ItemKind::VarSet { name, expr } => {
+				self.buf.push_str(&format!("{} = ", self.path(name)));
+				self.push_item(expr);
+				self.buf.push(';');
+			}
+
+			ItemKind::Assign { place, op, expr } => self.push_assign(item, place, op.as_ref(), expr),
+
+			ItemKind::ExprCall { func, args } => {
+				self.push_item(func); // todo: push_item_inline
+				self.push_args(args);
+			}
+
+			// The destination is a file, or anything else with a write method.
+			ItemKind::ExprBuiltinCall { builtin: Builtin::Fwrite, args } if !args.is_empty() => {
+				self.push_prefix(&args[0]);
+				self.buf.push_str(":write");
+				self.push_args(&args[1..]);
+			}
+			ItemKind::ExprBuiltinCall { builtin, args } => {
+				self.buf.push_str(match builtin {
+					Builtin::Print => "print",
+					// `fwrite` without a destination is an error in the backend.
+					Builtin::Write | Builtin::Fwrite => "io.write",
+					Builtin::Ewrite => "io.stderr:write",
+					Builtin::Panic => "error",
+					Builtin::Assert => "assert",
+				});
+				self.push_args(args);
+			}
+
+			ItemKind::ExprIdent(ident) => self.buf.push_str(&self.path(ident)),
+
+			ItemKind::ExprInteger(val, _) => self.buf.push_str(&integer(*val, self.codegen.lua_version)),
+			// Debug keeps the `.0` of whole numbers, so they stay floats in Lua too.
+			ItemKind::ExprDecimal(val) => self.buf.push_str(&format!("{val:?}")),
+			ItemKind::ExprBool(val) => self.buf.push_str(&val.to_string()),
+			ItemKind::ExprString(val) => self.buf.push_str(&string(val)),
+			ItemKind::ExprFString { strings, args, values } => self.push_format(strings, args, values),
+			ItemKind::ExprVariant { variant, value } => self.push_variant(*variant, value.as_deref()),
+			ItemKind::ExprMethodCall { receiver, method, args } => self.push_method_call(receiver, method, args),
+			ItemKind::ExprTry(expr) => {
+				self.buf.push_str("__try(");
+				self.push_item(expr);
+				self.buf.push(')');
+			}
+
+			// Statement blocks are handled by push_stmt_inline, this one is used as a value.
+			ItemKind::Block { stmts } => {
+				let value = std::mem::replace(&mut self.value, true);
+				self.buf.push_str("(function()");
+				self.push_stmts(&with_tail(stmts, Tail::Return));
+				self.buf.push_str("end)()");
+				self.value = value;
+			}
+			ItemKind::IfElif(_) | ItemKind::IfLet { .. } => {
+				let value = std::mem::replace(&mut self.value, true);
+				self.buf.push_str("(function()");
+				self.push_stmts(&[Item::new(ItemKind::Return { expr: Some(Box::new(item.clone())) }, item.span)]);
+				self.buf.push_str("end)()");
+				self.value = value;
+			}
+
+			// An expression, so it can be passed as an argument without a `;` after it.
+			ItemKind::ExprClosure { params, stmts } => {
+				self.buf.push_str("function(");
+				self.push_params(params);
+				self.buf.push(')');
+
+				self.push_body(stmts);
+				self.buf.push_str("end")
+			}
+
+			ItemKind::ExprBinary { lhs, rhs, op: op @ (BinaryOp::IntDiv | BinaryOp::Mod | BinaryOp::UDiv | BinaryOp::UMod) } => {
+				self.push_division(lhs, rhs, op)
+			}
+			ItemKind::ExprBinary { lhs, rhs, op: op @ (BinaryOp::ULt | BinaryOp::ULe | BinaryOp::UGe | BinaryOp::UGt) } => {
+				self.push_unsigned_comparison(lhs, rhs, op)
+			}
+			ItemKind::ExprBinary { lhs, rhs, op } => {
+				let op = match op {
+					BinaryOp::Add => "+",
+					BinaryOp::Sub => "-",
+					BinaryOp::Mul => "*",
+					BinaryOp::Div => "/",
+					BinaryOp::IntDiv | BinaryOp::Mod | BinaryOp::UDiv | BinaryOp::UMod => unreachable!(),
+					BinaryOp::And => " and ",
+					BinaryOp::Or => " or ",
+
+					BinaryOp::Bxor => "~",
+					BinaryOp::Band => "&",
+					BinaryOp::Bor => "|",
+					BinaryOp::Bshl => "<<",
+					BinaryOp::Bshr => ">>",
+
+					BinaryOp::Eq => "==",
+					BinaryOp::Lt => "<",
+					BinaryOp::Le => "<=",
+					BinaryOp::Ne => "~=",
+					BinaryOp::Ge => ">=",
+					BinaryOp::Gt => ">",
+					BinaryOp::ULt | BinaryOp::ULe | BinaryOp::UGe | BinaryOp::UGt => unreachable!(),
+
+				};
+
+				self.push_operand(lhs);
+				self.buf.push_str(op);
+				self.push_operand(rhs);
+			}
+
+			ItemKind::ExprUnary { op, expr } => {
+				use crate::backend::UnaryOp;
+
+				self.buf.push_str(match op {
+					UnaryOp::Neg => "-",
+					UnaryOp::Not => "not ",
+					UnaryOp::BitNot => "~",
+				});
+				self.push_operand(expr);
+			}
+
+			ItemKind::ExprCast { expr, ty } => self.push_cast(expr, *ty),
+			ItemKind::ExprWrap { expr, ty } => self.push_wrap(expr, *ty),
+
+			ItemKind::ExprField { expr, field } => self.push_field(expr, field),
+			ItemKind::ExprIndex { expr, index } => self.push_index(expr, index),
+
+			// Tuples are arrays, as their length is known anyway.
+			ItemKind::ExprArray { elements } | ItemKind::ExprTuple { elements } => {
+				self.buf.push('{');
+				if self.codegen.index_base == IndexBase::Zero && !elements.is_empty() {
+					self.buf.push_str("[0]=");
 				}
+				for (i, arg) in elements.iter().enumerate() {
+					self.push_item(arg);
 
-				Item::ForRange { var, min, max, jump: _, stmts } => {
-					buf.push_str("for ");
-					buf.push_str(var);
-					buf.push_str(" = ");
-					push_item(buf, indent, min);
-					buf.push(',');
-					push_item(buf, indent, max);
-					buf.push_str(" do ");
-					//buf.push_str(",");
-					//push_item(buf, indent, jump);
-
-					push_stmts(buf, indent, stmts);
-					buf.push_str(" ::__continue__:: end;")
+					if i != elements.len() - 1 {
+						self.buf.push(',');
+					}
 				}
+				self.buf.push('}');
+			}
 
-				Item::ForIn { var, expr, stmts } => {
-					buf.push_str("for ");
-					buf.push_str(var);
-					buf.push_str(" in ");
-					push_item(buf, indent, expr);
-					buf.push_str(" do ");
-					push_stmts(buf, indent, stmts);
-					buf.push_str(" ::__continue__:: end;")
+			ItemKind::ExprStruct { fields, .. } => {
+				self.buf.push('{');
+				for (i, (name, value)) in fields.iter().enumerate() {
+					if i != 0 {
+						self.buf.push(',');
+					}
+					self.buf.push_str(&format!("{}=", self.mangler.ident(name)));
+					self.push_item(value);
 				}
+				self.buf.push('}');
+			}
 
-				Item::Break => buf.push_str("break;"),
+			ItemKind::While { condition, stmts } => {
+				self.buf.push_str("while ");
+				self.push_item(condition);
+				self.buf.push_str(" do ");
+				self.push_stmts(stmts);
+				self.push_loop_end()
+			}
 
-				Item::Continue => buf.push_str("goto __continue__;"),
+			ItemKind::ForRange { var, min, max, jump: _, stmts } => {
+				self.buf.push_str("for ");
+				self.buf.push_str(&self.mangler.ident(var));
+				self.buf.push_str(" = ");
+				self.push_item(min);
+				self.buf.push(',');
+				self.push_item(max);
+				self.buf.push_str(" do ");
+				//self.buf.push_str(",");
+				//self.push_item(jump);
+
+				self.push_stmts(stmts);
+				self.push_loop_end()
+			}
 
-				Item::Externs { functions } => {
-					for name in functions {
-						buf.push_str(&format!(";local {name} = _G.{name};"));
-					}
+			// Arrays are iterated by index, since `ipairs` stops at the first `nil` and only counts from 1.
+			ItemKind::ForIn { var, expr, stmts } => {
+				self.buf.push_str("do local __array = ");
+				self.push_item(expr);
+				let (first, last) = match self.codegen.index_base {
+					IndexBase::One => (1, self.len("__array")),
+					IndexBase::Zero => (0, format!("{}-1", self.len("__array"))),
+				};
+				self.buf.push_str(&format!("; for __index = {first},{last} do local {} = __array[__index];", self.mangler.ident(var)));
+				self.push_stmts(stmts);
+				self.push_loop_end();
+				self.buf.push_str(" end;")
+			}
+
+			ItemKind::Break => self.buf.push_str("break;"),
+
+			ItemKind::Continue => self.buf.push_str("goto __continue__;"),
+
+			ItemKind::Return { expr } => {
+				self.buf.push_str("return");
+				if let Some(expr) = expr {
+					self.buf.push(' ');
+					self.push_item(expr);
 				}
+				self.buf.push(';');
+			}
 
-				Item::Mod { name, items } => {
-					buf.push_str(&format!("local {name} = {{}};"));
-					for item in items {
-						push_item(buf, indent, item);
+			ItemKind::Externs { functions } => {
+				for name in functions {
+					let local = self.mangler.ident(name);
+					if local == name.as_str() {
+						self.buf.push_str(&format!(";local {local} = _G.{name};"));
+					} else {
+						// The host's name isn't a valid identifier, so it has to be indexed with a string.
+						self.buf.push_str(&format!(";local {local} = _G[{}];", string(name)));
 					}
 				}
+			}
 
-				x => {
-					dbg!(x);
-					todo!()
+			// Its table was declared by push_tables.
+			ItemKind::Mod { items, .. } => {
+				for item in items {
+					self.push_stmt(item);
 				}
 			}
 		}
+	}
+}
 
-		for item in items {
-			push_item(&mut buf, &mut indent, item);
+/// Lua kept on one line, so it doesn't shift the lines of the source map. It can't have `--` comments.
+fn inline_lua(code: &str) -> String {
+	let lines = code.lines().map(str::trim).filter(|line| !line.is_empty());
+	lines.collect::<Vec<_>>().join(" ") + ";"
+}
+
+/// An integer literal. The smallest one is out of range without its `-`, which would make it a float, and before
+/// 5.3 every number is one anyway.
+fn integer(i: i64, version: LuaVersion) -> String {
+	match (i, version.integers()) {
+		(i64::MIN, false) => "-2^63".into(),
+		(i64::MIN, true) => "math.mininteger".into(),
+		(i, _) => i.to_string(),
+	}
+}
+
+/// A quoted Lua string literal. Anything but printable ASCII is escaped byte by byte as `\ddd`, the only escape
+/// for arbitrary bytes every version of Lua has, with three digits so a digit after it isn't taken as part of it.
+fn string(s: &str) -> String {
+	let mut out = String::with_capacity(s.len() + 2);
+	out.push('"');
+	for b in s.bytes() {
+		match b {
+			b'"' => out.push_str("\\\""),
+			b'\\' => out.push_str("\\\\"),
+			b'\n' => out.push_str("\\n"),
+			b'\r' => out.push_str("\\r"),
+			b'\t' => out.push_str("\\t"),
+			b' '..=b'~' => out.push(b.into()),
+			b => out.push_str(&format!("\\{b:03}")),
 		}
+	}
+	out.push('"');
+	out
+}
 
-		Ok(buf)
+/// What a block does with its value.
+#[derive(Clone, Copy)]
+enum Tail<'a> {
+	Discard,
+	Set(&'a str),
+	Return,
+}
+
+/// Whether a value has to be lowered with [Emitter::push_tail], having no expression in Lua.
+fn is_compound(item: &Item) -> bool {
+	matches!(item.kind, ItemKind::Block { .. } | ItemKind::IfElif(_) | ItemKind::IfLet { .. })
+}
+
+/// Statements of a block, with the last one replaced by doing `tail` with its value.
+fn with_tail(stmts: &[Item], tail: Tail) -> Vec<Item> {
+	let mut stmts = stmts.to_vec();
+	if let Some(last) = stmts.pop() {
+		let span = last.span;
+		let value = last.kind.is_expr() || is_compound(&last);
+
+		stmts.push(match tail {
+			Tail::Set(name) if value => Item::new(ItemKind::VarSet { name: name.to_owned(), expr: Box::new(last) }, span),
+			Tail::Return if value => Item::new(ItemKind::Return { expr: Some(Box::new(last)) }, span),
+			_ => last,
+		});
 	}
+
+	stmts
 }
@@ -0,0 +1,21 @@
+local __int
+do
+	function __int(v, bits, signed)
+		if type(v) == "boolean" then return v and 1 or 0 end
+		if type(v) == "string" then v = utf8.codepoint(v) end
+		if math.type(v) == "float" then
+			if v ~= v then return 0 end
+			local lo = signed and -2.0 ^ (bits - 1) or 0.0
+			local hi = signed and 2.0 ^ (bits - 1) or 2.0 ^ bits
+			if v <= lo then return math.tointeger(lo) end
+			if v >= hi then return signed and (1 << (bits - 1)) - 1 or (1 << bits) - 1 end
+			v = v >= 0 and math.floor(v) or math.ceil(v)
+			if v >= 2.0 ^ 63 then v = v - 2.0 ^ 64 end
+			return math.tointeger(v)
+		end
+		if bits == 64 then return v end
+		v = v & ((1 << bits) - 1)
+		if signed and v >= 1 << (bits - 1) then v = v - (1 << bits) end
+		return v
+	end
+end
@@ -0,0 +1,178 @@
+//! Casts and integers narrower than Lua's.
+//!
+//! Lua only has 64-bit integers and doubles, so integers are wrapped into narrower types by masking them, with the
+//! sign extended by hand. Casts to integers from anything that might not be one call `__int` from `number.lua`,
+//! which wraps integers the same way, turns bools into 0 or 1 and chars lowered to strings into their code, and
+//! rounds floats towards zero, saturating at the bounds of the type like `as` does. Lua 5.1 has neither integers nor bitwise operators, so there `%` wraps them.
+//!
+//! Integer division rounds towards zero in Rust, while Lua's `//` rounds down, so it calls `__idiv`, which is
+//! defined for the [LuaVersion] the code runs on. `%` is `math.fmod`, whose result has the sign of the dividend
+//! like Rust's, where Lua's `%` has the sign of the divisor.
+//!
+//! `u64`s above `i64::MAX` are stored as negative integers, so they're divided with `__udiv` and `__umod` and
+//! compared with their sign bits flipped, which orders them like signed integers.
+
+use crate::backend::visit::Visit;
+use crate::backend::{BinaryOp, Item, NumType};
+
+use super::Emitter;
+
+const PRELUDE: &str = include_str!("number.lua");
+/// `__int` without integers or bitwise operators, wrapping whole numbers with `%`. Floats are only told apart by
+/// having a fraction, so `300.0 as u8` wraps to 44 where Rust saturates it to 255.
+const PRELUDE_51: &str = include_str!("number51.lua");
+
+/// Whether anything in `items` casts to an integer, divides integers, and divides `u64`s.
+fn needs_prelude(items: &[Item]) -> (bool, bool, bool) {
+	#[derive(Default)]
+	struct Finder {
+		casts: bool,
+		divides: bool,
+		unsigned: bool,
+	}
+
+	impl Visit for Finder {
+		fn visit_expr_cast(&mut self, expr: &Item, ty: NumType) {
+			self.casts |= !ty.is_float();
+			self.visit_item(expr);
+		}
+
+		fn visit_expr_binary(&mut self, lhs: &Item, rhs: &Item, op: &BinaryOp) {
+			self.divides |= matches!(op, BinaryOp::IntDiv);
+			self.unsigned |= matches!(op, BinaryOp::UDiv | BinaryOp::UMod);
+			self.visit_item(lhs);
+			self.visit_item(rhs);
+		}
+
+		fn visit_assign(&mut self, place: &Item, op: Option<&BinaryOp>, expr: &Item) {
+			self.divides |= matches!(op, Some(BinaryOp::IntDiv));
+			self.unsigned |= matches!(op, Some(BinaryOp::UDiv | BinaryOp::UMod));
+			self.visit_item(place);
+			self.visit_item(expr);
+		}
+	}
+
+	let mut finder = Finder::default();
+	finder.visit_stmts(items);
+	(finder.casts, finder.divides, finder.unsigned)
+}
+
+impl Emitter<'_> {
+	/// Defines `__int`, `__idiv`, `__udiv` and `__umod` when the items need them.
+	pub(super) fn push_number_prelude(&mut self, items: &[Item]) {
+		let (casts, divides, unsigned) = needs_prelude(items);
+		if casts {
+			self.buf.push_str(&super::inline_lua(match self.codegen.lua_version.integers() {
+				true => PRELUDE,
+				false => PRELUDE_51,
+			}));
+		}
+		if divides {
+			self.buf.push_str(self.codegen.lua_version.idiv());
+		}
+		if unsigned {
+			self.buf.push_str(self.codegen.lua_version.udiv());
+		}
+	}
+
+	/// `/` of integers and `%`, which Lua rounds differently.
+	pub(super) fn push_division(&mut self, lhs: &Item, rhs: &Item, op: &BinaryOp) {
+		self.buf.push_str(match op {
+			BinaryOp::IntDiv => "__idiv(",
+			BinaryOp::UDiv => "__udiv(",
+			BinaryOp::UMod => "__umod(",
+			_ => "math.fmod(",
+		});
+		self.push_item(lhs);
+		self.buf.push(',');
+		self.push_item(rhs);
+		self.buf.push(')');
+	}
+
+	/// `<`, `<=`, `>=` and `>` of `u64`s. Before 5.3 they're floats, which compare as they are.
+	pub(super) fn push_unsigned_comparison(&mut self, lhs: &Item, rhs: &Item, op: &BinaryOp) {
+		let op = match op {
+			BinaryOp::ULt => "<",
+			BinaryOp::ULe => "<=",
+			BinaryOp::UGe => ">=",
+			_ => ">",
+		};
+
+		for (i, operand) in [lhs, rhs].into_iter().enumerate() {
+			if i == 1 {
+				self.buf.push_str(op);
+			}
+
+			match self.codegen.lua_version.integers() {
+				false => self.push_operand(operand),
+				true => {
+					self.buf.push('(');
+					self.push_operand(operand);
+					self.buf.push_str("~math.mininteger)");
+				}
+			}
+		}
+	}
+
+	pub(super) fn push_cast(&mut self, expr: &Item, ty: NumType) {
+		match ty {
+			NumType::F64 => {
+				self.push_operand(expr);
+				self.buf.push_str("+0.0");
+			}
+			// Rounded to the nearest `f32` by packing it into one, which returns the position after it too.
+			NumType::F32 => {
+				self.buf.push_str("(string.unpack(\"f\",string.pack(\"f\",");
+				self.push_item(expr);
+				self.buf.push_str(")))");
+			}
+			_ => {
+				self.buf.push_str("__int(");
+				self.push_item(expr);
+				self.buf.push_str(&format!(",{},{})", ty.bits(), ty.is_signed()));
+			}
+		}
+	}
+
+	pub(super) fn push_wrap(&mut self, expr: &Item, ty: NumType) {
+		let bits = ty.bits();
+		if bits == 64 {
+			return self.push_item(expr);
+		}
+
+		if !self.codegen.lua_version.integers() {
+			return self.push_wrap_mod(expr, ty);
+		}
+
+		let mask = (1_i64 << bits) - 1;
+		match ty.is_signed() {
+			false => {
+				self.push_operand(expr);
+				self.buf.push_str(&format!("&{mask}"));
+			}
+			// Shifted up so the smallest value is 0, masked, and shifted back down.
+			true => {
+				let half = 1_i64 << (bits - 1);
+				self.buf.push_str("((");
+				self.push_operand(expr);
+				self.buf.push_str(&format!("+{half})&{mask})-{half}"));
+			}
+		}
+	}
+	/// [Emitter::push_wrap] with `%`, whose result is never negative when the divisor isn't.
+	fn push_wrap_mod(&mut self, expr: &Item, ty: NumType) {
+		let modulus = 1_i64 << ty.bits();
+		match ty.is_signed() {
+			false => {
+				self.push_operand(expr);
+				self.buf.push_str(&format!("%{modulus}"));
+			}
+			true => {
+				let half = modulus / 2;
+				self.buf.push_str("((");
+				self.push_operand(expr);
+				self.buf.push_str(&format!("+{half})%{modulus})-{half}"));
+			}
+		}
+	}
+}
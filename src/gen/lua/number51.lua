@@ -0,0 +1,27 @@
+local __int
+do
+	local function codepoint(s)
+		local b = s:byte(1)
+		if b < 128 then return b end
+		local n = b >= 240 and 3 or b >= 224 and 2 or 1
+		local c = b % 2 ^ (6 - n)
+		for i = 2, n + 1 do c = c * 64 + s:byte(i) % 64 end
+		return c
+	end
+
+	function __int(v, bits, signed)
+		if type(v) == "boolean" then return v and 1 or 0 end
+		if type(v) == "string" then v = codepoint(v) end
+		if v ~= v then return 0 end
+		local lo = signed and -2 ^ (bits - 1) or 0
+		local hi = signed and 2 ^ (bits - 1) or 2 ^ bits
+		if v ~= math.floor(v) or v == math.huge or v == -math.huge then
+			if v <= lo then return lo end
+			if v >= hi then return hi - 1 end
+			return v >= 0 and math.floor(v) or math.ceil(v)
+		end
+		v = v % 2 ^ bits
+		if signed and v >= hi then v = v - 2 ^ bits end
+		return v
+	end
+end
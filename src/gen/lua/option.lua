@@ -0,0 +1,83 @@
+local __is_some, __is_none, __is_ok, __is_err, __map, __and_then, __unwrap, __unwrap_or, __ok_or, __ok, __branch, __try, __catch
+do
+	--[[repr]]
+
+	local function result(v)
+		return type(v) == "table" and (v.tag == "Ok" or v.tag == "Err")
+	end
+
+	function __is_some(v) return is_some(v) end
+	function __is_none(v) return not is_some(v) end
+	function __is_ok(v) return v.tag == "Ok" end
+	function __is_err(v) return v.tag == "Err" end
+
+	function __map(v, f)
+		if result(v) then
+			if v.tag == "Ok" then return {tag = "Ok", f(v[1])} end
+			return v
+		end
+		if is_some(v) then return some(f(get(v))) end
+		return none
+	end
+
+	function __and_then(v, f)
+		if result(v) then
+			if v.tag == "Ok" then return f(v[1]) end
+			return v
+		end
+		if is_some(v) then return f(get(v)) end
+		return none
+	end
+
+	function __unwrap(v)
+		if result(v) then
+			if v.tag == "Ok" then return v[1] end
+			error("called `Result::unwrap()` on an `Err` value: " .. tostring(v[1]), 2)
+		end
+		if is_some(v) then return get(v) end
+		error("called `Option::unwrap()` on a `None` value", 2)
+	end
+
+	function __unwrap_or(v, default)
+		if result(v) then
+			if v.tag == "Ok" then return v[1] end
+			return default
+		end
+		if is_some(v) then return get(v) end
+		return default
+	end
+
+	function __ok_or(v, err)
+		if is_some(v) then return {tag = "Ok", get(v)} end
+		return {tag = "Err", err}
+	end
+
+	function __ok(v)
+		if v.tag == "Ok" then return some(v[1]) end
+		return none
+	end
+
+	local residual = {}
+
+	function __branch(v)
+		if result(v) then
+			if v.tag == "Ok" then return false, v[1] end
+		elseif is_some(v) then
+			return false, get(v)
+		end
+		return true, v
+	end
+
+	function __try(v)
+		local early, value = __branch(v)
+		if early then error({[residual] = true, value = value}, 0) end
+		return value
+	end
+
+	function __catch(ok, ...)
+		if ok then return ... end
+		local e = ...
+		if type(e) == "table" and e[residual] then return e.value end
+		error(e, 0)
+	end
+end
@@ -0,0 +1,256 @@
+//! `Option` and `Result`, along with the prelude implementing their methods and `?`.
+//!
+//! Results are tables tagged like `{tag = "Ok", value}`, options depend on [OptionRepr]. Variants are built and
+//! matched inline, methods and `?` call functions from `option.lua`, which is only included when a file needs
+//! it. Those can't know the type of their argument, so they tell results apart by their tag, which mistakes an
+//! option holding a result for the result when options are `nil`. Tables are compared by reference, so `==`
+//! only works on options represented as `nil`.
+//!
+//! A `?` starting a statement, as in `let x = f()?;`, `f()?;` or `return f()?;`, is checked with `__branch` and
+//! returns right there. Anywhere else it raises the value to return with `error`, and functions doing that catch
+//! it around their body.
+
+use crate::backend::visit::{self, Visit};
+use crate::backend::format::FormatArg;
+use crate::backend::{Builtin, Item, ItemKind, Variant};
+
+use super::Emitter;
+
+const PRELUDE: &str = include_str!("option.lua");
+
+/// Methods the prelude defines as `__{method}(receiver, args...)`. Others are called on the receiver with `:`.
+const METHODS: &[&str] = &["is_some", "is_none", "is_ok", "is_err", "map", "and_then", "unwrap", "unwrap_or", "ok_or", "ok"];
+
+/// How `Option` values look in Lua.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OptionRepr {
+	/// `None` is `nil` and `Some(x)` is `x` itself, so `Some(None)` is `None` and options can't be stored in arrays.
+	#[default]
+	Nil,
+	/// Tagged like results, as `{tag = "Some", x}` and `{tag = "None"}`.
+	Tagged,
+}
+
+impl OptionRepr {
+	/// What the prelude builds and checks options with.
+	fn prelude(self) -> &'static str {
+		match self {
+			OptionRepr::Nil => {
+				"local none = nil; local function some(x) return x end; local function is_some(v) return v ~= nil end; \
+				 local function get(v) return v end;"
+			}
+			OptionRepr::Tagged => {
+				"local none = {tag = \"None\"}; local function some(x) return {tag = \"Some\", x} end; \
+				 local function is_some(v) return v.tag == \"Some\" end; local function get(v) return v[1] end;"
+			}
+		}
+	}
+}
+
+/// Whether anything in `items` calls into the prelude.
+fn needs_prelude(items: &[Item]) -> bool {
+	#[derive(Default)]
+	struct Finder(bool);
+
+	impl Visit for Finder {
+		fn visit_expr_try(&mut self, _expr: &Item) {
+			self.0 = true;
+		}
+
+		fn visit_expr_method_call(&mut self, receiver: &Item, method: &str, args: &[Item]) {
+			self.0 |= METHODS.contains(&method);
+			self.visit_item(receiver);
+			self.visit_stmts(args);
+		}
+	}
+
+	let mut finder = Finder::default();
+	finder.visit_stmts(items);
+	finder.0
+}
+
+/// The `?` a statement starts with, which returns early inline rather than with `error`.
+fn try_stmt(item: &Item) -> Option<&Item> {
+	let expr = match &item.kind {
+		ItemKind::VarDecl { expr, .. } | ItemKind::Return { expr: Some(expr) } => expr,
+		_ => item,
+	};
+	match &expr.kind {
+		ItemKind::ExprTry(expr) => Some(expr),
+		_ => None,
+	}
+}
+
+/// Whether a function's own body raises `?` with `error`, outside of the closures it defines.
+fn uses_try(stmts: &[Item]) -> bool {
+	#[derive(Default)]
+	struct Finder {
+		found: bool,
+		// Whether the item being visited is a statement, and whether it's inside a block used as a value, whose
+		// statements are in a function of their own.
+		stmt: bool,
+		value: bool,
+	}
+
+	impl Finder {
+		fn visit_exprs(&mut self, exprs: &[Item]) {
+			for expr in exprs {
+				self.visit_item(expr);
+			}
+		}
+	}
+
+	impl Visit for Finder {
+		fn visit_item(&mut self, item: &Item) {
+			let stmt = std::mem::take(&mut self.stmt);
+			match (try_stmt(item), &item.kind) {
+				(Some(expr), _) if stmt && !self.value => self.visit_item(expr),
+				(_, ItemKind::Block { .. }) if !stmt => {
+					let value = std::mem::replace(&mut self.value, true);
+					visit::walk_item(self, item);
+					self.value = value;
+				}
+				_ => visit::walk_item(self, item),
+			}
+		}
+
+		fn visit_stmts(&mut self, stmts: &[Item]) {
+			for stmt in stmts {
+				self.stmt = true;
+				self.visit_item(stmt);
+			}
+		}
+
+		fn visit_expr_try(&mut self, _expr: &Item) {
+			self.found = true;
+		}
+
+		fn visit_function_definition(&mut self, _name: &str, _public: bool, _params: &[String], _stmts: &[Item]) {}
+
+		fn visit_expr_closure(&mut self, _params: &[String], _stmts: &[Item]) {}
+
+		// The rest visit lists of expressions with `visit_stmts`, which would take them for statements.
+		fn visit_expr_call(&mut self, func: &Item, args: &[Item]) {
+			self.visit_item(func);
+			self.visit_exprs(args);
+		}
+
+		fn visit_expr_builtin_call(&mut self, _builtin: Builtin, args: &[Item]) {
+			self.visit_exprs(args);
+		}
+
+		fn visit_expr_array(&mut self, elements: &[Item]) {
+			self.visit_exprs(elements);
+		}
+
+		fn visit_expr_tuple(&mut self, elements: &[Item]) {
+			self.visit_exprs(elements);
+		}
+
+		fn visit_expr_fstring(&mut self, _strings: &[String], _args: &[FormatArg], values: &[Item]) {
+			self.visit_exprs(values);
+		}
+
+		fn visit_expr_method_call(&mut self, receiver: &Item, _method: &str, args: &[Item]) {
+			self.visit_item(receiver);
+			self.visit_exprs(args);
+		}
+	}
+
+	let mut finder = Finder::default();
+	finder.visit_stmts(stmts);
+	finder.found
+}
+
+impl Emitter<'_> {
+	/// Defines the prelude's functions when the items need them.
+	pub(super) fn push_option_prelude(&mut self, items: &[Item]) {
+		if needs_prelude(items) {
+			let prelude = PRELUDE.replace("--[[repr]]", self.codegen.option_repr.prelude());
+			self.buf.push_str(&super::inline_lua(&prelude));
+		}
+	}
+
+	/// The statements of a function or closure, caught by `__catch` if they use `?`.
+	pub(super) fn push_body(&mut self, stmts: &[Item]) {
+		let value = std::mem::replace(&mut self.value, false);
+		if uses_try(stmts) {
+			self.buf.push_str(" return __catch(pcall(function()");
+			self.push_stmts(stmts);
+			self.buf.push_str("end)) ");
+		} else {
+			self.push_stmts(stmts);
+		}
+		self.value = value;
+	}
+
+	/// A statement starting with `?`, returning the residual from the function right away. Returns `false` for
+	/// other statements, and inside blocks used as values, which are functions of their own.
+	pub(super) fn push_try_stmt(&mut self, item: &Item) -> bool {
+		let Some(expr) = try_stmt(item).filter(|_| !self.value) else {
+			return false;
+		};
+
+		self.mark(item);
+		match &item.kind {
+			ItemKind::VarDecl { name, .. } => {
+				let name = self.mangler.ident(name);
+				self.buf.push_str(&format!("local __residual, {name} = __branch("));
+				self.push_item(expr);
+				self.buf.push_str(&format!("); if __residual then return {name} end;"));
+			}
+			ItemKind::Return { .. } => {
+				self.buf.push_str("do local _, __value = __branch(");
+				self.push_item(expr);
+				self.buf.push_str("); return __value end;");
+			}
+			_ => {
+				self.buf.push_str("do local __residual, __value = __branch(");
+				self.push_item(expr);
+				self.buf.push_str("); if __residual then return __value end end;");
+			}
+		}
+		true
+	}
+
+	pub(super) fn push_variant(&mut self, variant: Variant, value: Option<&Item>) {
+		match (variant, value) {
+			(Variant::Some, Some(value)) if self.codegen.option_repr == OptionRepr::Nil => self.push_item(value),
+			(Variant::None, _) if self.codegen.option_repr == OptionRepr::Nil => self.buf.push_str("nil"),
+			(variant, value) => {
+				self.buf.push_str(&format!("{{tag={}", super::string(variant.name())));
+				if let Some(value) = value {
+					self.buf.push(',');
+					self.push_item(value);
+				}
+				self.buf.push('}');
+			}
+		}
+	}
+
+	pub(super) fn push_method_call(&mut self, receiver: &Item, method: &str, args: &[Item]) {
+		if METHODS.contains(&method) {
+			self.buf.push_str(&format!("__{method}("));
+			self.push_item(receiver);
+			for arg in args {
+				self.buf.push(',');
+				self.push_item(arg);
+			}
+			self.buf.push(')');
+			return;
+		}
+
+		self.push_prefix(receiver);
+		self.buf.push_str(&format!(":{}", self.mangler.ident(method)));
+		self.push_args(args);
+	}
+
+	/// What has to be true of `access` to hold the variant, and where its value is.
+	pub(super) fn variant_access(&self, variant: Variant, access: &str) -> (String, String) {
+		match (variant, self.codegen.option_repr) {
+			(Variant::Some, OptionRepr::Nil) => (format!("{access} ~= nil"), access.to_owned()),
+			(Variant::None, OptionRepr::Nil) => (format!("{access} == nil"), access.to_owned()),
+			(variant, _) => (format!("{access}.tag == {}", super::string(variant.name())), format!("{access}[1]")),
+		}
+	}
+}
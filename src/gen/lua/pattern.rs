@@ -0,0 +1,231 @@
+//! Destructuring `let`s, as one `local` statement binding every name, and the conditions of `if let` and
+//! `let ... else`.
+//!
+//! A tuple or array literal lining up with the pattern is assigned element by element. Anything else is stored
+//! in `__let` first, unless it's already a variable, and each binding indexes into it. Refutable patterns check
+//! the length of slices and compare literals with `==`, as every value has the shape its type says. Variants
+//! are checked as [OptionRepr](super::OptionRepr) says.
+
+use std::borrow::Cow;
+
+use crate::backend::{Item, ItemKind, Pattern, TEMPORARY};
+
+use super::{Emitter, LuaVersion};
+
+impl Emitter<'_> {
+	pub(super) fn push_let(&mut self, item: &Item, pattern: &Pattern, expr: Option<&Item>, else_stmts: Option<&[Item]>) {
+		let Some(expr) = expr else {
+			let names = pattern.bindings().into_iter().map(|name| self.mangler.ident(name)).collect::<Vec<_>>();
+			if !names.is_empty() {
+				self.buf.push_str(&format!("local {};", names.join(",")));
+			}
+			return;
+		};
+
+		if let Some(else_stmts) = else_stmts {
+			let base = self.push_base(item, expr);
+			if let Some(condition) = self.condition(pattern, &base) {
+				self.buf.push_str(&format!("if not ({condition}) then "));
+				self.push_stmts(else_stmts);
+				self.buf.push_str("end;");
+			}
+			return self.push_bindings(pattern, base);
+		}
+
+		if let (
+			Pattern::Tuple(patterns) | Pattern::TupleStruct { elements: patterns, .. } | Pattern::Slice(patterns),
+			ItemKind::ExprTuple { elements } | ItemKind::ExprArray { elements },
+		) = (pattern, &expr.kind)
+		{
+			let flat = patterns.iter().all(|pattern| matches!(pattern, Pattern::Ident(_) | Pattern::Wildcard));
+			if flat && !elements.is_empty() && patterns.len() == elements.len() {
+				let names = patterns
+					.iter()
+					.map(|pattern| match pattern {
+						Pattern::Ident(name) => self.mangler.ident(name),
+						_ => Cow::Borrowed("_"),
+					})
+					.collect::<Vec<_>>();
+
+				self.buf.push_str(&format!("local {} = ", names.join(",")));
+				for (i, element) in elements.iter().enumerate() {
+					if i != 0 {
+						self.buf.push(',');
+					}
+					self.push_item(element);
+				}
+				self.buf.push(';');
+				return;
+			}
+		}
+
+		// Still evaluated for its side effects.
+		if pattern.bindings().is_empty() {
+			let decl = ItemKind::VarDecl { name: "_".into(), expr: Box::new(expr.clone()) };
+			return self.push_stmt_inline(&Item::new(decl, item.span));
+		}
+
+		let base = self.push_base(item, expr);
+		self.push_bindings(pattern, base);
+	}
+
+	/// `if let`, in its own `do` block when the value has to be stored in `__let`.
+	pub(super) fn push_if_let(
+		&mut self,
+		item: &Item,
+		pattern: &Pattern,
+		expr: &Item,
+		stmts: &[Item],
+		else_stmts: Option<&[Item]>,
+	) {
+		let scoped = !matches!(expr.kind, ItemKind::ExprIdent(_));
+		if scoped {
+			self.buf.push_str("do ");
+		}
+
+		let base = self.push_base(item, expr);
+		let condition = self.condition(pattern, &base).unwrap_or_else(|| "true".into());
+		self.buf.push_str(&format!("if {condition} then "));
+		if !pattern.bindings().is_empty() {
+			self.indent += 1;
+			self.newline();
+			self.push_bindings(pattern, base);
+			self.indent -= 1;
+		}
+		self.push_stmts(stmts);
+
+		if let Some(stmts) = else_stmts {
+			self.buf.push_str(" else ");
+			self.push_stmts(stmts);
+		}
+		self.buf.push_str(" end;");
+
+		if scoped {
+			self.buf.push_str(" end;");
+		}
+	}
+
+	/// Where the value a pattern is matched against can be found, storing it in `__let` unless it's a variable.
+	fn push_base(&mut self, item: &Item, expr: &Item) -> String {
+		match &expr.kind {
+			ItemKind::ExprIdent(name) => self.path(name),
+			_ => {
+				let decl = ItemKind::VarDecl { name: format!("{}__let", TEMPORARY), expr: Box::new(expr.clone()) };
+				self.push_stmt_inline(&Item::new(decl, item.span));
+				"__let".into()
+			}
+		}
+	}
+
+	fn push_bindings(&mut self, pattern: &Pattern, base: String) {
+		let mut bindings = vec![];
+		self.bindings(pattern, base, &mut bindings);
+		if !bindings.is_empty() {
+			let (names, values): (Vec<_>, Vec<_>) = bindings.into_iter().unzip();
+			self.buf.push_str(&format!("local {} = {};", names.join(","), values.join(",")));
+		}
+	}
+
+	/// Every name bound by the pattern, with the expression indexing `access` to get its value.
+	fn bindings(&self, pattern: &Pattern, access: String, out: &mut Vec<(String, String)>) {
+		match pattern {
+			Pattern::Ident(name) => out.push((self.mangler.ident(name).into_owned(), access)),
+			Pattern::Wildcard | Pattern::Rest | Pattern::Literal(_) | Pattern::Or(_) => {}
+			Pattern::Tuple(elements) | Pattern::TupleStruct { elements, .. } | Pattern::Slice(elements) => {
+				for (element, access) in self.element_accesses(elements, &access) {
+					self.bindings(element, access, out);
+				}
+			}
+			Pattern::Struct { fields, .. } => {
+				for (name, pattern) in fields {
+					self.bindings(pattern, format!("{access}.{}", self.mangler.ident(name)), out);
+				}
+			}
+			Pattern::Variant { variant, value } => {
+				if let Some(value) = value {
+					self.bindings(value, self.variant_access(*variant, &access).1, out);
+				}
+			}
+		}
+	}
+
+	/// What has to be true of `access` for the pattern to match, or None if it always does.
+	fn condition(&self, pattern: &Pattern, access: &str) -> Option<String> {
+		let mut checks = vec![];
+		self.checks(pattern, access.to_owned(), &mut checks);
+		(!checks.is_empty()).then(|| checks.join(" and "))
+	}
+
+	fn checks(&self, pattern: &Pattern, access: String, out: &mut Vec<String>) {
+		match pattern {
+			Pattern::Ident(_) | Pattern::Wildcard | Pattern::Rest => {}
+			Pattern::Literal(item) => out.push(format!("{access} == {}", literal(item, self.codegen.lua_version))),
+			Pattern::Or(alternatives) => {
+				// An alternative that always matches makes the whole pattern always match.
+				let conditions = alternatives
+					.iter()
+					.map(|alternative| self.condition(alternative, &access))
+					.collect::<Option<Vec<_>>>();
+				if let Some(conditions) = conditions {
+					out.push(format!("({})", conditions.join(" or ")));
+				}
+			}
+			Pattern::Tuple(elements) | Pattern::TupleStruct { elements, .. } | Pattern::Slice(elements) => {
+				// Only slices can have a different length than their pattern.
+				if matches!(pattern, Pattern::Slice(_)) {
+					let len = self.len(&access);
+					match elements.iter().filter(|element| !matches!(element, Pattern::Rest)).count() {
+						n if n < elements.len() => out.push(format!("{len} >= {n}")),
+						n => out.push(format!("{len} == {n}")),
+					}
+				}
+				for (element, access) in self.element_accesses(elements, &access) {
+					self.checks(element, access, out);
+				}
+			}
+			Pattern::Struct { fields, .. } => {
+				for (name, pattern) in fields {
+					self.checks(pattern, format!("{access}.{}", self.mangler.ident(name)), out);
+				}
+			}
+			Pattern::Variant { variant, value } => {
+				let (check, access) = self.variant_access(*variant, &access);
+				out.push(check);
+				if let Some(value) = value {
+					self.checks(value, access, out);
+				}
+			}
+		}
+	}
+
+	/// Each element of a tuple or slice pattern, with the expression indexing `access` to get it. Elements after a
+	/// `..` count back from the end, the last one being at `#access` from either index base.
+	fn element_accesses<'a>(&self, elements: &'a [Pattern], access: &str) -> Vec<(&'a Pattern, String)> {
+		let rest = elements.iter().position(|element| matches!(element, Pattern::Rest));
+		elements
+			.iter()
+			.enumerate()
+			.map(|(i, element)| {
+				let access = match rest {
+					Some(rest) if i > rest => match elements.len() - 1 - i {
+						0 => format!("{access}[#{access}]"),
+						back => format!("{access}[#{access}-{back}]"),
+					},
+					_ => format!("{access}[{}]", i as i64 + self.codegen.index_base.offset()),
+				};
+				(element, access)
+			})
+			.collect()
+	}
+}
+
+/// A literal in a pattern. It isn't pushed as an item, which would mark its span in the middle of a condition.
+fn literal(item: &Item, version: LuaVersion) -> String {
+	match &item.kind {
+		ItemKind::ExprInteger(val, _) => super::integer(*val, version),
+		ItemKind::ExprDecimal(val) => format!("{val:?}"),
+		ItemKind::ExprBool(val) => val.to_string(),
+		ItemKind::ExprString(val) => super::string(val),
+		other => unreachable!("{other:?} isn't a literal"),
+	}
+}
@@ -0,0 +1,160 @@
+//! Fields, indexes and assignments to them.
+//!
+//! Arrays and tuples are tables starting at 1 unless [IndexBase::Zero] is used, so indexes are shifted by one.
+//! A compound assignment reads its place back, so unless the place is only made of variables and literals, the table and key are stored in
+//! `__place` and `__key` first to evaluate them once.
+//! Rust evaluates the value of an assignment before its place while Lua evaluates the table and key first, so a value that could tell
+//! the difference is stored in `__value` before either of them unless the place is a variable.
+
+use crate::backend::{BinaryOp, Item, ItemKind, TEMPORARY};
+
+use super::Emitter;
+
+/// Where arrays and tuples start in the generated code. The IR counts from 0, like Rust.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IndexBase {
+	/// Lua sequences, as `#`, `ipairs` and the `table` library expect. Every index is shifted by one.
+	#[default]
+	One,
+	/// The first element is at `[0]`, like arrays of hosts that count from 0. Indexes are left as they are.
+	Zero,
+}
+
+impl IndexBase {
+	/// What's added to an index of the IR.
+	pub(super) fn offset(self) -> i64 {
+		match self {
+			IndexBase::One => 1,
+			IndexBase::Zero => 0,
+		}
+	}
+}
+
+impl Emitter<'_> {
+	pub(super) fn push_assign(&mut self, item: &Item, place: &Item, op: Option<&BinaryOp>, expr: &Item) {
+		let stored = |name: &str| Box::new(Item::new(ItemKind::ExprIdent(format!("{}{name}", TEMPORARY)), place.span));
+		if !matches!(place.kind, ItemKind::ExprIdent(_)) && !inert(expr) {
+			self.buf.push_str("do local __value = ");
+			self.push_item(expr);
+			self.buf.push(';');
+			self.push_assign(item, place, op, &stored("__value"));
+			self.buf.push_str(" end;");
+			return;
+		}
+
+		let Some(op) = op else {
+			self.push_item(place);
+			self.buf.push_str(" = ");
+			self.push_item(expr);
+			self.buf.push(';');
+			return;
+		};
+
+		if simple(place) {
+			let value = ItemKind::ExprBinary { lhs: Box::new(place.clone()), rhs: Box::new(expr.clone()), op: op.clone() };
+			self.push_item(place);
+			self.buf.push_str(" = ");
+			self.push_item(&Item::new(value, item.span));
+			self.buf.push(';');
+			return;
+		}
+
+		self.buf.push_str("do local __place = ");
+		let stored = match &place.kind {
+			ItemKind::ExprField { expr, field } => {
+				self.push_item(expr);
+				ItemKind::ExprField { expr: stored("__place"), field: field.clone() }
+			}
+			ItemKind::ExprIndex { expr, index } => {
+				self.push_item(expr);
+				self.buf.push_str("; local __key = ");
+				self.push_item(index);
+				ItemKind::ExprIndex { expr: stored("__place"), index: stored("__key") }
+			}
+			other => unreachable!("{other:?} isn't a place"),
+		};
+		self.buf.push(';');
+
+		self.push_assign(item, &Item::new(stored, place.span), Some(op), expr);
+		self.buf.push_str(" end;");
+	}
+
+	pub(super) fn push_field(&mut self, expr: &Item, field: &str) {
+		self.push_prefix(expr);
+		self.buf.push('.');
+		self.buf.push_str(&self.mangler.ident(field));
+	}
+
+	pub(super) fn push_index(&mut self, expr: &Item, index: &Item) {
+		self.push_prefix(expr);
+		self.buf.push('[');
+		match (&index.kind, self.codegen.index_base) {
+			(ItemKind::ExprInteger(i, _), base) => self.buf.push_str(&(i + base.offset()).to_string()),
+			(_, IndexBase::Zero) => self.push_item(index),
+			(_, IndexBase::One) => {
+				self.push_operand(index);
+				self.buf.push_str("+1");
+			}
+		}
+		self.buf.push(']');
+	}
+
+	/// What's indexed or called, which has to be in parentheses unless it's a name, call or index itself.
+	pub(super) fn push_prefix(&mut self, item: &Item) {
+		let group = !matches!(
+			item.kind,
+			ItemKind::ExprIdent(_)
+				| ItemKind::ExprCall { .. }
+				| ItemKind::ExprBuiltinCall { .. }
+				| ItemKind::ExprField { .. }
+				| ItemKind::ExprIndex { .. }
+				| ItemKind::ExprMethodCall { .. }
+		);
+
+		if group {
+			self.buf.push('(');
+		}
+		self.push_item(item);
+		if group {
+			self.buf.push(')');
+		}
+	}
+
+	/// The length of the array in `access`, which `#` only counts from 1.
+	pub(super) fn len(&self, access: &str) -> String {
+		match self.codegen.index_base {
+			IndexBase::One => format!("#{access}"),
+			IndexBase::Zero => format!("({access}[0] == nil and 0 or #{access}+1)"),
+		}
+	}
+}
+
+/// Whether evaluating the place again gives the same result, as it's only made of variables and literals.
+fn simple(place: &Item) -> bool {
+	match &place.kind {
+		ItemKind::ExprIdent(_) | ItemKind::ExprInteger(..) | ItemKind::ExprString(_) | ItemKind::ExprBool(_) => true,
+		ItemKind::ExprField { expr, .. } => simple(expr),
+		ItemKind::ExprIndex { expr, index } => simple(expr) && simple(index),
+		_ => false,
+	}
+}
+
+/// Whether evaluating the value before or after the place gives the same results, as it can neither call, assign nor fail.
+fn inert(expr: &Item) -> bool {
+	match &expr.kind {
+		ItemKind::ExprIdent(_) | ItemKind::ExprDecimal(_) | ItemKind::ExprInteger(..) | ItemKind::ExprString(_) | ItemKind::ExprBool(_) => true,
+		ItemKind::ExprArray { elements } | ItemKind::ExprTuple { elements } => elements.iter().all(inert),
+		ItemKind::ExprStruct { fields, .. } => fields.iter().all(|(_, value)| inert(value)),
+		ItemKind::ExprVariant { value, .. } => value.as_deref().is_none_or(inert),
+		ItemKind::ExprField { expr, .. } | ItemKind::ExprUnary { expr, .. } | ItemKind::ExprCast { expr, .. } | ItemKind::ExprWrap { expr, .. } => {
+			inert(expr)
+		}
+		// Lua reads a missing index as nil instead of failing.
+		ItemKind::ExprIndex { expr, index } => inert(expr) && inert(index),
+		// Integer division and remainder fail on zero.
+		ItemKind::ExprBinary { lhs, rhs, op } => {
+			!matches!(op, BinaryOp::IntDiv | BinaryOp::Mod | BinaryOp::UDiv | BinaryOp::UMod) && inert(lhs) && inert(rhs)
+		}
+		_ => false,
+	}
+}
@@ -0,0 +1,154 @@
+use crate::backend::{SourceMap, Span};
+
+/// A position in the generated Lua (both 0-indexed) and the Rust it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+	pub line: usize,
+	pub column: usize,
+	pub span: Span,
+}
+
+/// Relates generated Lua positions back to the original Rust spans.
+/// Produced by [super::LuaCodegen::generate_with_map].
+#[derive(Debug, Default)]
+pub struct LineMap {
+	pub mappings: Vec<Mapping>,
+}
+
+impl LineMap {
+	/// Rust span for a 1-indexed Lua line, like the ones in error messages.
+	/// Lines without code of their own (eg. `end`) resolve to the closest mapping before them.
+	pub fn lookup(&self, lua_line: usize) -> Option<&Span> {
+		let line = lua_line.checked_sub(1)?;
+
+		self.mappings
+			.iter()
+			.find(|m| m.line == line)
+			.or_else(|| self.mappings.iter().rev().find(|m| m.line < line))
+			.map(|m| &m.span)
+	}
+
+	/// Replaces every `{chunk}:{line}:` location in a Lua error or traceback with the
+	/// Rust `path:line:column` it was generated from.
+	pub fn rewrite_traceback(&self, traceback: &str, chunk: &str, sources: &SourceMap) -> String {
+		if chunk.is_empty() {
+			return traceback.to_owned();
+		}
+
+		let mut out = String::with_capacity(traceback.len());
+		let mut rest = traceback;
+
+		while let Some(pos) = rest.find(chunk) {
+			out.push_str(&rest[..pos]);
+			let after = &rest[pos + chunk.len()..];
+
+			let location = after.strip_prefix(':').and_then(|s| {
+				let digits = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+				let line = s[..digits].parse::<usize>().ok()?;
+				Some((self.lookup(line)?, &s[digits..]))
+			});
+
+			match location {
+				Some((span, tail)) => {
+					let path = sources.path(span.file).unwrap_or("<unknown>");
+					out.push_str(&format!("{path}:{}:{}", span.lo.line, span.lo.column + 1));
+					rest = tail;
+				}
+				None => {
+					out.push_str(chunk);
+					rest = after;
+				}
+			}
+		}
+
+		out.push_str(rest);
+		out
+	}
+
+	/// Serializes to a [Source Map v3](https://sourcemaps.info/spec.html) JSON document.
+	pub fn to_json(&self, file: &str, sources: &SourceMap) -> String {
+		let mut files: Vec<crate::backend::FileId> = vec![];
+		for m in &self.mappings {
+			if !files.contains(&m.span.file) {
+				files.push(m.span.file);
+			}
+		}
+
+		let mut mappings = String::new();
+		let (mut prev_source, mut prev_line, mut prev_col) = (0i64, 0i64, 0i64);
+		let mut line = 0;
+		let mut first_on_line = true;
+		let mut prev_gen_col = 0i64;
+
+		for m in &self.mappings {
+			while line < m.line {
+				mappings.push(';');
+				line += 1;
+				first_on_line = true;
+				prev_gen_col = 0;
+			}
+
+			if !first_on_line {
+				mappings.push(',');
+			}
+			first_on_line = false;
+
+			let source = files.iter().position(|f| *f == m.span.file).unwrap() as i64;
+			let src_line = m.span.lo.line as i64 - 1;
+			let src_col = m.span.lo.column as i64;
+
+			vlq(&mut mappings, m.column as i64 - prev_gen_col);
+			vlq(&mut mappings, source - prev_source);
+			vlq(&mut mappings, src_line - prev_line);
+			vlq(&mut mappings, src_col - prev_col);
+
+			prev_gen_col = m.column as i64;
+			(prev_source, prev_line, prev_col) = (source, src_line, src_col);
+		}
+
+		let strings = |f: &dyn Fn(crate::backend::FileId) -> String| {
+			files.iter().map(|id| f(*id)).collect::<Vec<_>>().join(",")
+		};
+
+		format!(
+			"{{\"version\":3,\"file\":{},\"sources\":[{}],\"sourcesContent\":[{}],\"names\":[],\"mappings\":\"{mappings}\"}}",
+			json_string(file),
+			strings(&|id| json_string(sources.path(id).unwrap_or("<unknown>"))),
+			strings(&|id| sources.contents(id).map(json_string).unwrap_or_else(|| "null".into())),
+		)
+	}
+}
+
+fn vlq(buf: &mut String, value: i64) {
+	const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	let mut v = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+	loop {
+		let mut digit = v & 0b11111;
+		v >>= 5;
+		if v > 0 {
+			digit |= 0b100000;
+		}
+		buf.push(BASE64[digit as usize] as char);
+		if v == 0 {
+			break;
+		}
+	}
+}
+
+fn json_string(s: &str) -> String {
+	let mut out = String::from("\"");
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
@@ -0,0 +1,113 @@
+//! The versions of Lua the generated code can run on.
+//!
+//! Lua 5.3 added integers, `//` and bitwise operators, and 5.2 added `goto`, which `continue` jumps with. Code for
+//! [LuaVersion::Lua51] and [LuaVersion::Lua52] does without what they lack, and what can't be written without it is an error.
+
+use crate::backend::visit::{self, Visit};
+use crate::backend::{BinaryOp, Item, ItemKind, NumType, Span, UnaryOp};
+use crate::gen::{CodegenError, Result};
+
+/// The Lua the generated code runs on, which changes how numbers are handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LuaVersion {
+	/// Lua 5.1 and LuaJIT, where every number is a float. Integer division is rounded with `math.floor` and
+	/// `math.ceil`, integers are wrapped with `%`, and `{:?}` prints whole floats without their `.0`. Bitwise
+	/// operators, casts to `f32` and `continue` are errors.
+	Lua51,
+	/// Lua 5.2, which has floats like 5.1 but also `goto`, so `continue` works.
+	Lua52,
+	/// Lua 5.3 and later, with 64-bit integers, `//` and bitwise operators.
+	#[default]
+	Lua53,
+}
+
+impl LuaVersion {
+	/// Whether numbers can be integers, which comes with `//` and bitwise operators.
+	pub(super) fn integers(self) -> bool {
+		self == LuaVersion::Lua53
+	}
+
+	/// Whether `goto` and labels exist.
+	pub(super) fn goto(self) -> bool {
+		self != LuaVersion::Lua51
+	}
+
+	/// `__idiv`, dividing integers and rounding towards zero.
+	pub(super) fn idiv(self) -> &'static str {
+		match self {
+			LuaVersion::Lua51 | LuaVersion::Lua52 => {
+				"local function __idiv(a, b) local q = a / b; if q >= 0 then return math.floor(q) end; return math.ceil(q) end;"
+			}
+			// `//` rounds down, which is one less than Rust when the quotient is negative and not exact.
+			LuaVersion::Lua53 => {
+				"local function __idiv(a, b) local q = a // b; if q < 0 and q * b ~= a then q = q + 1 end; return q end;"
+			}
+		}
+	}
+
+	/// `__udiv` and `__umod`, dividing `u64`s. Before 5.3 they're floats, which are never negative.
+	pub(super) fn udiv(self) -> &'static str {
+		match self {
+			LuaVersion::Lua51 | LuaVersion::Lua52 => "local function __udiv(a, b) return math.floor(a / b) end; local __umod = math.fmod;",
+			// Halved to be positive, divided, and doubled back, which is at most one less than the quotient.
+			LuaVersion::Lua53 => {
+				"local function __udiv(a, b) if b < 0 then return math.ult(a, b) and 0 or 1 end; if a >= 0 then return a // b end; \
+				 local q = ((a >> 1) // b) << 1; if not math.ult(a - q * b, b) then q = q + 1 end; return q end; \
+				 local function __umod(a, b) return a - __udiv(a, b) * b end;"
+			}
+		}
+	}
+
+	/// `unsigned` of `fmt.lua`, the digits of a `u64`.
+	pub(super) fn unsigned(self) -> &'static str {
+		match self {
+			LuaVersion::Lua51 | LuaVersion::Lua52 => "local function unsigned(v) return string.format(\"%.0f\", v) end",
+			// Halved to be positive, then the last digit is what dividing by 10 leaves.
+			LuaVersion::Lua53 => {
+				"local function unsigned(v) if v >= 0 then return string.format(\"%d\", v) end local q = (v >> 1) // 5 return q .. v - q * 10 end"
+			}
+		}
+	}
+
+	/// The name errors call the version by.
+	fn name(self) -> &'static str {
+		match self {
+			LuaVersion::Lua51 => "Lua 5.1",
+			LuaVersion::Lua52 => "Lua 5.2",
+			LuaVersion::Lua53 => "Lua 5.3",
+		}
+	}
+
+	/// Fails on the first thing in `items` this version can't express.
+	pub(super) fn check(self, items: &[Item]) -> Result<()> {
+		if self.integers() {
+			return Ok(());
+		}
+
+		let mut finder = Unsupported(self, None);
+		finder.visit_stmts(items);
+		match finder.1 {
+			Some((what, span)) => Err(CodegenError::Unsupported { what, target: self.name(), span }),
+			None => Ok(()),
+		}
+	}
+}
+
+struct Unsupported(LuaVersion, Option<(&'static str, Option<Span>)>);
+
+impl Visit for Unsupported {
+	fn visit_item(&mut self, item: &Item) {
+		let bitwise = |op: &BinaryOp| matches!(op, BinaryOp::Bxor | BinaryOp::Band | BinaryOp::Bor | BinaryOp::Bshl | BinaryOp::Bshr);
+		let what = match &item.kind {
+			ItemKind::ExprBinary { op, .. } | ItemKind::Assign { op: Some(op), .. } if bitwise(op) => "bitwise operators",
+			ItemKind::ExprUnary { op: UnaryOp::BitNot, .. } => "bitwise operators",
+			// Rounding to the nearest `f32` takes `string.pack`.
+			ItemKind::ExprCast { ty: NumType::F32, .. } => "casts to `f32`",
+			ItemKind::Continue if !self.0.goto() => "`continue`",
+			// Every number is a float, so there's no negative integer to store them as.
+			ItemKind::ExprInteger(i, Some(ty)) if *i < 0 && !ty.is_signed() => "`u64`s above `i64::MAX`",
+			_ => return visit::walk_item(self, item),
+		};
+		self.1.get_or_insert((what, item.span));
+	}
+}
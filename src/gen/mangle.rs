@@ -0,0 +1,63 @@
+use std::borrow::Cow;
+
+use super::CodeGenerator;
+use crate::backend::TEMPORARY;
+
+/// Renames identifiers that aren't valid in a target language.
+///
+/// Mangling is deterministic, and only depends on the name itself:
+/// * `r#` is dropped, since `r#foo` and `foo` are the same identifier.
+/// * Characters the target doesn't allow are written as their code point, `café` becomes `caf_ue9_`.
+/// * Reserved words get an underscore appended, and so does any name that's a reserved word followed by
+///   underscores, so `end` becomes `end_` without colliding with an `end_` which becomes `end__`. So does any
+///   name starting with a reserved prefix.
+/// * Names starting with [TEMPORARY] are temporaries of the lowering or the generator itself. They're written
+///   without the prefix and aren't mangled, so their names can be reserved while they still refer to them.
+#[derive(Debug, Clone, Copy)]
+pub struct Mangler {
+	reserved: &'static [&'static str],
+	reserved_prefixes: &'static [&'static str],
+	is_ident_char: fn(char, bool) -> bool,
+}
+
+impl Mangler {
+	pub fn new<G: CodeGenerator>() -> Self {
+		Self {
+			reserved: G::RESERVED,
+			reserved_prefixes: G::RESERVED_PREFIXES,
+			is_ident_char: G::is_ident_char,
+		}
+	}
+
+	pub fn ident<'a>(&self, name: &'a str) -> Cow<'a, str> {
+		if let Some(name) = name.strip_prefix(TEMPORARY) {
+			return Cow::Borrowed(name);
+		}
+		let name = name.strip_prefix("r#").unwrap_or(name);
+
+		let mut out = Cow::Borrowed(name);
+		if name.char_indices().any(|(i, c)| !(self.is_ident_char)(c, i == 0)) {
+			let mut escaped = String::new();
+			for (i, c) in name.char_indices() {
+				if (self.is_ident_char)(c, i == 0) {
+					escaped.push(c);
+				} else {
+					escaped.push_str(&format!("_u{:x}_", c as u32));
+				}
+			}
+			out = Cow::Owned(escaped);
+		}
+
+		let reserved = self.reserved.contains(&out.trim_end_matches('_'));
+		if reserved || self.reserved_prefixes.iter().any(|prefix| out.starts_with(prefix)) {
+			out.to_mut().push('_');
+		}
+
+		out
+	}
+
+	/// Mangles every segment of a `::` separated path, joining them with `separator`.
+	pub fn path(&self, name: &str, separator: &str) -> String {
+		name.split("::").map(|segment| self.ident(segment)).collect::<Vec<_>>().join(separator)
+	}
+}
@@ -0,0 +1,1023 @@
+//! Reference interpreter for the IR.
+//!
+//! Evaluates [Item]s directly with Rust semantics, so results can be compared against running the generated code.
+//!
+//! ```
+//! use smyth::backend::Ast;
+//! use smyth::interp::{Interpreter, Value};
+//!
+//! let ast = smyth::Backend::generate("fn main() -> i32 { add(1, 2) }").unwrap();
+//!
+//! let mut interp = Interpreter::new();
+//! interp.register("add", |args| match args {
+//!     [Value::Int(a), Value::Int(b)] => Ok(Value::Int(a + b)),
+//!     _ => Err(smyth::interp::InterpError::Host("bad args".into())),
+//! });
+//!
+//! assert_eq!(interp.run(ast.items()).unwrap(), Value::Int(3));
+//! ```
+
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::backend::{BinaryOp, IfElif, Item, ItemKind, NumType, Pattern, UnaryOp, Variant};
+
+mod format;
+mod prelude;
+pub(crate) use format::format;
+
+#[derive(Debug, thiserror::Error)]
+pub enum InterpError {
+	#[error("Undefined variable: {0}")]
+	Undefined(String),
+
+	#[error("Type error: {0}")]
+	Type(String),
+
+	#[error("Expected {expected} arguments, got {got}")]
+	Arity { expected: usize, got: usize },
+
+	#[error("Arithmetic overflow")]
+	Overflow,
+
+	#[error("Division by zero")]
+	DivisionByZero,
+
+	#[error("{0} outside of a loop")]
+	OutsideLoop(&'static str),
+
+	#[error("Unsupported: {0}")]
+	Unsupported(String),
+
+	#[error("Host function failed: {0}")]
+	Host(String),
+
+	#[error("Panicked: {0}")]
+	Panic(String),
+
+	/// A `None` or `Err` returned by `?`, unwinding up to the function it's returned from.
+	#[error("`?` outside of a function")]
+	Propagate(Value),
+}
+
+pub type Result<T> = std::result::Result<T, InterpError>;
+
+pub type HostFn = dyn Fn(&[Value]) -> Result<Value>;
+
+#[derive(Clone)]
+pub enum Value {
+	Unit,
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+	Str(Rc<str>),
+	Array(Rc<RefCell<Vec<Value>>>),
+	Tuple(Rc<RefCell<Vec<Value>>>),
+	/// A struct's path and its fields, in the order they were written.
+	Struct(Rc<str>, Rc<RefCell<Vec<(String, Value)>>>),
+	Variant(Variant, Option<Rc<Value>>),
+	Function(Rc<Function>),
+	Host(Rc<HostFn>),
+	Module(Rc<Scope>),
+}
+
+pub struct Function {
+	params: Vec<String>,
+	stmts: Vec<Item>,
+	env: Rc<Scope>,
+}
+
+impl Value {
+	fn type_name(&self) -> &'static str {
+		match self {
+			Value::Unit => "()",
+			Value::Bool(_) => "bool",
+			Value::Int(_) => "integer",
+			Value::Float(_) => "float",
+			Value::Str(_) => "string",
+			Value::Array(_) => "array",
+			Value::Tuple(_) => "tuple",
+			Value::Struct(..) => "struct",
+			Value::Variant(Variant::Some | Variant::None, _) => "option",
+			Value::Variant(Variant::Ok | Variant::Err, _) => "result",
+			Value::Function(_) | Value::Host(_) => "function",
+			Value::Module(_) => "module",
+		}
+	}
+}
+
+impl PartialEq for Value {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Value::Unit, Value::Unit) => true,
+			(Value::Bool(a), Value::Bool(b)) => a == b,
+			(Value::Int(a), Value::Int(b)) => a == b,
+			(Value::Float(a), Value::Float(b)) => a == b,
+			(Value::Str(a), Value::Str(b)) => a == b,
+			(Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => *a.borrow() == *b.borrow(),
+			(Value::Struct(a, a_fields), Value::Struct(b, b_fields)) => {
+				let (a_fields, b_fields) = (a_fields.borrow(), b_fields.borrow());
+				a == b
+					&& a_fields.len() == b_fields.len()
+					&& a_fields.iter().all(|(name, value)| b_fields.iter().any(|(n, v)| n == name && v == value))
+			}
+			(Value::Variant(a, a_value), Value::Variant(b, b_value)) => a == b && a_value == b_value,
+			(Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+			(Value::Host(a), Value::Host(b)) => Rc::ptr_eq(a, b),
+			(Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+			_ => false,
+		}
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Unit => write!(f, "()"),
+			Value::Bool(b) => write!(f, "{b}"),
+			Value::Int(i) => write!(f, "{i}"),
+			Value::Float(x) => write!(f, "{x}"),
+			Value::Str(s) => write!(f, "{s}"),
+			Value::Array(a) => {
+				write!(f, "[")?;
+				for (i, v) in a.borrow().iter().enumerate() {
+					if i != 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{v}")?;
+				}
+				write!(f, "]")
+			}
+			Value::Tuple(t) => {
+				let t = t.borrow();
+				write!(f, "(")?;
+				for (i, v) in t.iter().enumerate() {
+					if i != 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{v}")?;
+				}
+				if t.len() == 1 {
+					write!(f, ",")?;
+				}
+				write!(f, ")")
+			}
+			Value::Struct(path, fields) => {
+				let fields = fields.borrow();
+				write!(f, "{}", last_segment(path))?;
+				for (i, (name, v)) in fields.iter().enumerate() {
+					let sep = if i == 0 { " { " } else { ", " };
+					write!(f, "{sep}{name}: {v}")?;
+				}
+				match fields.is_empty() {
+					true => Ok(()),
+					false => write!(f, " }}"),
+				}
+			}
+			Value::Variant(variant, None) => write!(f, "{}", variant.name()),
+			Value::Variant(variant, Some(v)) => write!(f, "{}({v:?})", variant.name()),
+			Value::Function(_) | Value::Host(_) => write!(f, "<function>"),
+			Value::Module(_) => write!(f, "<module>"),
+		}
+	}
+}
+
+impl fmt::Debug for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Str(s) => write!(f, "{s:?}"),
+			other => write!(f, "{other}"),
+		}
+	}
+}
+
+/// Variables visible at some point of the program.
+/// Every `let` starts a new scope, so closures keep seeing the binding they captured even if it's shadowed later.
+#[derive(Default)]
+pub struct Scope {
+	vars: RefCell<HashMap<String, Value>>,
+	parent: Option<Rc<Scope>>,
+}
+
+impl Scope {
+	fn child(parent: &Rc<Scope>) -> Rc<Scope> {
+		Rc::new(Scope {
+			vars: RefCell::default(),
+			parent: Some(parent.clone()),
+		})
+	}
+
+	fn define(&self, name: &str, value: Value) {
+		self.vars.borrow_mut().insert(name.to_owned(), value);
+	}
+
+	fn get(&self, name: &str) -> Option<Value> {
+		match self.vars.borrow().get(name) {
+			Some(v) => Some(v.clone()),
+			None => self.parent.as_ref()?.get(name),
+		}
+	}
+
+	fn set(&self, name: &str, value: Value) -> bool {
+		if let Some(slot) = self.vars.borrow_mut().get_mut(name) {
+			*slot = value;
+			return true;
+		}
+
+		match &self.parent {
+			Some(parent) => parent.set(name, value),
+			None => false,
+		}
+	}
+}
+
+enum Flow {
+	Normal,
+	Break,
+	Continue,
+	Return(Value),
+}
+
+pub struct Interpreter {
+	globals: Rc<Scope>,
+	hosts: HashMap<String, Value>,
+}
+
+impl Default for Interpreter {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Interpreter {
+	/// Starts out with the [builtins](crate::backend::Builtin), printing to stdout and stderr.
+	pub fn new() -> Self {
+		let mut interp = Self {
+			globals: Rc::default(),
+			hosts: HashMap::new(),
+		};
+
+		interp.register("print", |args| {
+			println!("{}", print_line(args));
+			Ok(Value::Unit)
+		});
+		interp.register("write", |args| {
+			print!("{}", print_line(args));
+			Ok(Value::Unit)
+		});
+		interp.register("ewrite", |args| {
+			eprint!("{}", print_line(args));
+			Ok(Value::Unit)
+		});
+
+		// Writers are host functions taking the string, there are no files to write to.
+		interp.register("fwrite", |args| match args {
+			[Value::Host(f), s] => f(std::slice::from_ref(s)),
+			[other, _] => Err(InterpError::Type(format!("can't write to {}", other.type_name()))),
+			_ => Err(InterpError::Arity { expected: 2, got: args.len() }),
+		});
+
+		interp.register("panic", |args| Err(InterpError::Panic(print_line(args))));
+		interp.register("assert", |args| match args {
+			[Value::Bool(true), ..] => Ok(Value::Unit),
+			[Value::Bool(false), message @ ..] => Err(InterpError::Panic(print_line(message))),
+			[other, ..] => Err(InterpError::Type(format!("can't assert {}", other.type_name()))),
+			[] => Err(InterpError::Arity { expected: 2, got: 0 }),
+		});
+
+		interp
+	}
+
+	/// Makes a function callable from the program, either through `extern` declarations or directly by name.
+	/// Host paths like `::Log::info` are registered without the leading `::`.
+	pub fn register(&mut self, name: &str, f: impl Fn(&[Value]) -> Result<Value> + 'static) {
+		self.hosts.insert(name.to_owned(), Value::Host(Rc::new(f)));
+	}
+
+	/// Replaces `print` and `write` with ones that append to the returned buffer instead of stdout.
+	pub fn capture_print(&mut self) -> Rc<RefCell<String>> {
+		let out = Rc::new(RefCell::new(String::new()));
+
+		let buf = out.clone();
+		self.register("print", move |args| {
+			let mut buf = buf.borrow_mut();
+			buf.push_str(&print_line(args));
+			buf.push('\n');
+			Ok(Value::Unit)
+		});
+
+		let buf = out.clone();
+		self.register("write", move |args| {
+			buf.borrow_mut().push_str(&print_line(args));
+			Ok(Value::Unit)
+		});
+
+		out
+	}
+
+	/// Evaluates top level items, defining their functions and modules.
+	pub fn load(&mut self, items: &[Item]) -> Result<()> {
+		let globals = self.globals.clone();
+		match self.exec_block(items, &globals, false)? {
+			Flow::Normal => Ok(()),
+			Flow::Break => Err(InterpError::OutsideLoop("break")),
+			Flow::Continue => Err(InterpError::OutsideLoop("continue")),
+			Flow::Return(_) => Err(InterpError::Unsupported("return outside of a function".into())),
+		}
+	}
+
+	/// Looks up a global variable, function or host function.
+	pub fn get(&self, name: &str) -> Option<Value> {
+		self.lookup(name, &self.globals)
+	}
+
+	pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+		let func = self.get(name).ok_or_else(|| InterpError::Undefined(name.to_owned()))?;
+		self.call_value(&func, args)
+	}
+
+	/// Loads the items and calls `main`, if there is one.
+	pub fn run(&mut self, items: &[Item]) -> Result<Value> {
+		self.load(items)?;
+
+		match self.globals.get("main") {
+			Some(main) => self.call_value(&main, vec![]),
+			None => Ok(Value::Unit),
+		}
+	}
+
+	fn lookup(&self, name: &str, scope: &Rc<Scope>) -> Option<Value> {
+		// Host paths are registered without their leading `::`.
+		if let Some(host) = name.strip_prefix("::") {
+			return self.hosts.get(host).cloned();
+		}
+
+		if let Some(v) = scope.get(name) {
+			return Some(v);
+		}
+
+		// Paths, resolved or not, are looked up through their modules.
+		if let Some((module, rest)) = name.split_once("::") {
+			if let Some(Value::Module(module)) = scope.get(module) {
+				return self.lookup(rest, &module);
+			}
+		}
+
+		self.hosts.get(name).cloned()
+	}
+
+	fn call_value(&mut self, func: &Value, args: Vec<Value>) -> Result<Value> {
+		match func {
+			Value::Host(f) => f(&args),
+			Value::Function(f) => {
+				if f.params.len() != args.len() {
+					return Err(InterpError::Arity {
+						expected: f.params.len(),
+						got: args.len(),
+					});
+				}
+
+				let scope = Scope::child(&f.env);
+				for (param, arg) in f.params.iter().zip(args) {
+					scope.define(param, arg);
+				}
+
+				match self.exec_block(&f.stmts, &scope, false) {
+					Ok(Flow::Return(v)) | Err(InterpError::Propagate(v)) => Ok(v),
+					Ok(Flow::Normal) => Ok(Value::Unit),
+					Ok(Flow::Break) => Err(InterpError::OutsideLoop("break")),
+					Ok(Flow::Continue) => Err(InterpError::OutsideLoop("continue")),
+					Err(e) => Err(e),
+				}
+			}
+			other => Err(InterpError::Type(format!("{} is not callable", other.type_name()))),
+		}
+	}
+
+	/// Runs statements in a new scope. Functions are defined up front, since Rust items are visible in their whole block.
+	fn exec_block(&mut self, stmts: &[Item], parent: &Rc<Scope>, new_scope: bool) -> Result<Flow> {
+		let mut scope = if new_scope { Scope::child(parent) } else { parent.clone() };
+		self.exec_stmts(stmts, &mut scope)
+	}
+
+	/// Runs statements in `scope`, leaving it with every binding they declared.
+	fn exec_stmts(&mut self, stmts: &[Item], scope: &mut Rc<Scope>) -> Result<Flow> {
+		for stmt in stmts {
+			if let ItemKind::FunctionDefinition { name, params, stmts, .. } = &stmt.kind {
+				let func = Function {
+					params: params.clone(),
+					stmts: stmts.clone(),
+					env: scope.clone(),
+				};
+				scope.define(last_segment(name), Value::Function(Rc::new(func)));
+			}
+		}
+
+		for stmt in stmts {
+			match self.exec(stmt, scope)? {
+				Flow::Normal => (),
+				flow => return Ok(flow),
+			}
+		}
+
+		Ok(Flow::Normal)
+	}
+
+	fn exec_loop_body(&mut self, stmts: &[Item], scope: &Rc<Scope>) -> Result<Option<Flow>> {
+		match self.exec_block(stmts, scope, true)? {
+			Flow::Break => Ok(Some(Flow::Normal)),
+			Flow::Return(v) => Ok(Some(Flow::Return(v))),
+			Flow::Normal | Flow::Continue => Ok(None),
+		}
+	}
+
+	fn exec(&mut self, item: &Item, scope: &mut Rc<Scope>) -> Result<Flow> {
+		match &item.kind {
+			// Already defined by exec_block
+			ItemKind::FunctionDefinition { .. } => (),
+
+			ItemKind::VarDecl { name, expr } => {
+				let value = self.eval(expr, scope)?;
+				*scope = Scope::child(scope);
+				scope.define(name, value);
+			}
+
+			// Declared without a value, the bindings hold `()` until they're assigned.
+			ItemKind::Let { pattern, expr, else_stmts } => {
+				let value = match expr {
+					Some(expr) => self.eval(expr, scope)?,
+					None => Value::Unit,
+				};
+
+				if let Some(stmts) = else_stmts {
+					if !self.matches(pattern, &value, scope)? {
+						return match self.exec_block(stmts, scope, true)? {
+							Flow::Normal => Err(InterpError::Type("`let ... else` has to diverge".into())),
+							flow => Ok(flow),
+						};
+					}
+				}
+
+				*scope = Scope::child(scope);
+				match expr {
+					Some(_) => bind(pattern, value, scope)?,
+					None => pattern.bindings().into_iter().for_each(|name| scope.define(name, Value::Unit)),
+				}
+			}
+
+			ItemKind::VarSet { name, expr } => {
+				let value = self.eval(expr, scope)?;
+				if !scope.set(name, value) {
+					return Err(InterpError::Undefined(name.to_owned()));
+				}
+			}
+
+			// Like Rust, the value is evaluated before the place.
+			ItemKind::Assign { place, op, expr } => {
+				let value = self.eval(expr, scope)?;
+				let (container, key) = match &place.kind {
+					ItemKind::ExprField { expr, field } => (self.eval(expr, scope)?, Value::Str(field.as_str().into())),
+					ItemKind::ExprIndex { expr, index } => (self.eval(expr, scope)?, self.eval(index, scope)?),
+					other => return Err(InterpError::Unsupported(format!("assigning to {other:?}"))),
+				};
+
+				let value = match op {
+					Some(op) => binary(op, element(&container, &key)?, value)?,
+					None => value,
+				};
+				set_element(&container, &key, value)?;
+			}
+
+			ItemKind::While { condition, stmts } => {
+				while self.truthy(condition, scope)? {
+					if let Some(flow) = self.exec_loop_body(stmts, scope)? {
+						return Ok(flow);
+					}
+				}
+			}
+
+			ItemKind::ForRange { var, min, max, jump, stmts } => {
+				let min = self.int(min, scope)?;
+				let max = self.int(max, scope)?;
+				let jump = match jump {
+					Some(jump) => self.int(jump, scope)?,
+					None => 1,
+				};
+
+				if jump == 0 {
+					return Err(InterpError::Unsupported("for loop with a step of 0".into()));
+				}
+
+				let mut i = min;
+				while (jump > 0 && i <= max) || (jump < 0 && i >= max) {
+					let iter_scope = Scope::child(scope);
+					iter_scope.define(var, Value::Int(i));
+					if let Some(flow) = self.exec_loop_body(stmts, &iter_scope)? {
+						return Ok(flow);
+					}
+
+					i = match i.checked_add(jump) {
+						Some(i) => i,
+						None => break,
+					};
+				}
+			}
+
+			ItemKind::ForIn { var, expr, stmts } => {
+				let elements = match self.eval(expr, scope)? {
+					Value::Array(a) => a.borrow().clone(),
+					other => return Err(InterpError::Type(format!("can't iterate over {}", other.type_name()))),
+				};
+
+				for element in elements {
+					let iter_scope = Scope::child(scope);
+					iter_scope.define(var, element);
+					if let Some(flow) = self.exec_loop_body(stmts, &iter_scope)? {
+						return Ok(flow);
+					}
+				}
+			}
+
+			ItemKind::IfElif(IfElif { condition, stmts, elif, else_stmts }) => {
+				if self.truthy(condition, scope)? {
+					return self.exec_block(stmts, scope, true);
+				}
+
+				for (cond, stmts) in elif {
+					if self.truthy(cond, scope)? {
+						return self.exec_block(stmts, scope, true);
+					}
+				}
+
+				if let Some(stmts) = else_stmts {
+					return self.exec_block(stmts, scope, true);
+				}
+			}
+
+			ItemKind::IfLet { pattern, expr, stmts, else_stmts } => {
+				let value = self.eval(expr, scope)?;
+				if self.matches(pattern, &value, scope)? {
+					let inner = Scope::child(scope);
+					bind(pattern, value, &inner)?;
+					return self.exec_block(stmts, &inner, false);
+				}
+
+				if let Some(stmts) = else_stmts {
+					return self.exec_block(stmts, scope, true);
+				}
+			}
+
+			ItemKind::Block { stmts } => return self.exec_block(stmts, scope, true),
+
+			ItemKind::Break => return Ok(Flow::Break),
+			ItemKind::Continue => return Ok(Flow::Continue),
+			ItemKind::Return { expr } => {
+				let value = match expr {
+					Some(expr) => self.eval(expr, scope)?,
+					None => Value::Unit,
+				};
+				return Ok(Flow::Return(value));
+			}
+
+			ItemKind::Externs { functions } => {
+				for name in functions {
+					let host = self.hosts.get(name).cloned();
+					scope.define(name, host.ok_or_else(|| InterpError::Undefined(name.to_owned()))?);
+				}
+			}
+
+			ItemKind::Mod { name, items } => {
+				let module = Scope::child(&self.globals);
+				self.exec_block(items, &module, false)?;
+				scope.define(last_segment(name), Value::Module(module));
+			}
+
+			_ => {
+				self.eval(item, scope)?;
+			}
+		}
+
+		Ok(Flow::Normal)
+	}
+
+	fn truthy(&mut self, item: &Item, scope: &Rc<Scope>) -> Result<bool> {
+		match self.eval(item, scope)? {
+			Value::Bool(b) => Ok(b),
+			other => Err(InterpError::Type(format!("expected bool, got {}", other.type_name()))),
+		}
+	}
+
+	fn int(&mut self, item: &Item, scope: &Rc<Scope>) -> Result<i64> {
+		match self.eval(item, scope)? {
+			Value::Int(i) => Ok(i),
+			other => Err(InterpError::Type(format!("expected integer, got {}", other.type_name()))),
+		}
+	}
+
+	fn eval(&mut self, item: &Item, scope: &Rc<Scope>) -> Result<Value> {
+		Ok(match &item.kind {
+			ItemKind::ExprIdent(name) => self
+				.lookup(name, scope)
+				.ok_or_else(|| InterpError::Undefined(name.to_owned()))?,
+
+			ItemKind::ExprInteger(i, _) => Value::Int(*i),
+			ItemKind::ExprDecimal(x) => Value::Float(*x),
+			ItemKind::ExprString(s) => Value::Str(s.as_str().into()),
+			ItemKind::ExprBool(b) => Value::Bool(*b),
+
+			ItemKind::ExprArray { elements } => {
+				let elements = elements.iter().map(|e| self.eval(e, scope)).collect::<Result<_>>()?;
+				Value::Array(Rc::new(RefCell::new(elements)))
+			}
+
+			ItemKind::ExprTuple { elements } => {
+				let elements = elements.iter().map(|e| self.eval(e, scope)).collect::<Result<_>>()?;
+				Value::Tuple(Rc::new(RefCell::new(elements)))
+			}
+
+			ItemKind::ExprStruct { path, fields } => {
+				let fields = fields
+					.iter()
+					.map(|(name, value)| Ok((name.clone(), self.eval(value, scope)?)))
+					.collect::<Result<_>>()?;
+				Value::Struct(path.as_str().into(), Rc::new(RefCell::new(fields)))
+			}
+
+			ItemKind::ExprCall { func, args } => {
+				let func = self.eval(func, scope)?;
+				let args = args.iter().map(|a| self.eval(a, scope)).collect::<Result<_>>()?;
+				self.call_value(&func, args)?
+			}
+
+			ItemKind::ExprBuiltinCall { builtin, args } => {
+				let func = self.hosts.get(builtin.name()).cloned().ok_or_else(|| InterpError::Undefined(builtin.name().into()))?;
+				let args = args.iter().map(|a| self.eval(a, scope)).collect::<Result<_>>()?;
+				self.call_value(&func, args)?
+			}
+
+			ItemKind::ExprClosure { params, stmts } => Value::Function(Rc::new(Function {
+				params: params.clone(),
+				stmts: stmts.clone(),
+				env: scope.clone(),
+			})),
+
+			ItemKind::ExprBinary { lhs, rhs, op: BinaryOp::And } => {
+				Value::Bool(self.truthy(lhs, scope)? && self.truthy(rhs, scope)?)
+			}
+
+			ItemKind::ExprBinary { lhs, rhs, op: BinaryOp::Or } => {
+				Value::Bool(self.truthy(lhs, scope)? || self.truthy(rhs, scope)?)
+			}
+
+			ItemKind::ExprBinary { lhs, rhs, op } => {
+				let lhs = self.eval(lhs, scope)?;
+				let rhs = self.eval(rhs, scope)?;
+				binary(op, lhs, rhs)?
+			}
+
+			ItemKind::ExprUnary { op, expr } => unary(*op, self.eval(expr, scope)?)?,
+			ItemKind::ExprCast { expr, ty } => cast(*ty, self.eval(expr, scope)?)?,
+			ItemKind::ExprWrap { expr, ty } => match self.eval(expr, scope)? {
+				Value::Int(i) => Value::Int(ty.wrap(i)),
+				other => return Err(InterpError::Type(format!("can't wrap {} into {}", other.type_name(), ty.name()))),
+			},
+
+			ItemKind::Block { stmts } => self.eval_block(stmts, scope)?,
+
+			ItemKind::IfElif(IfElif { condition, stmts, elif, else_stmts }) => {
+				if self.truthy(condition, scope)? {
+					return self.eval_block(stmts, scope);
+				}
+
+				for (cond, stmts) in elif {
+					if self.truthy(cond, scope)? {
+						return self.eval_block(stmts, scope);
+					}
+				}
+
+				match else_stmts {
+					Some(stmts) => self.eval_block(stmts, scope)?,
+					None => Value::Unit,
+				}
+			}
+
+			ItemKind::IfLet { pattern, expr, stmts, else_stmts } => {
+				let value = self.eval(expr, scope)?;
+				if self.matches(pattern, &value, scope)? {
+					let inner = Scope::child(scope);
+					bind(pattern, value, &inner)?;
+					return self.eval_block(stmts, &inner);
+				}
+
+				match else_stmts {
+					Some(stmts) => self.eval_block(stmts, scope)?,
+					None => Value::Unit,
+				}
+			}
+
+			ItemKind::ExprField { expr, field } => element(&self.eval(expr, scope)?, &Value::Str(field.as_str().into()))?,
+			ItemKind::ExprIndex { expr, index } => element(&self.eval(expr, scope)?, &self.eval(index, scope)?)?,
+
+			ItemKind::ExprFString { strings, args, values } => {
+				let unsigned = values.iter().map(crate::backend::format::unsigned).collect::<Vec<_>>();
+				let values = values.iter().map(|v| self.eval(v, scope)).collect::<Result<Vec<_>>>()?;
+				Value::Str(format(strings, args, &values, &unsigned)?.into())
+			}
+
+			ItemKind::ExprVariant { variant, value } => {
+				let value = value.as_ref().map(|value| self.eval(value, scope)).transpose()?;
+				Value::Variant(*variant, value.map(Rc::new))
+			}
+
+			ItemKind::ExprTry(expr) => match self.eval(expr, scope)? {
+				Value::Variant(Variant::Some | Variant::Ok, Some(value)) => (*value).clone(),
+				residual @ Value::Variant(Variant::None | Variant::Err, _) => return Err(InterpError::Propagate(residual)),
+				other => return Err(InterpError::Type(format!("can't use `?` on {}", other.type_name()))),
+			},
+
+			ItemKind::ExprMethodCall { receiver, method, args } => {
+				let receiver = self.eval(receiver, scope)?;
+				let args = args.iter().map(|a| self.eval(a, scope)).collect::<Result<Vec<_>>>()?;
+				self.call_method(receiver, method, args)?
+			}
+
+			other => return Err(InterpError::Unsupported(format!("{other:?} as an expression"))),
+		})
+	}
+
+	/// The value of a block's last statement, in a new scope.
+	fn eval_block(&mut self, stmts: &[Item], scope: &Rc<Scope>) -> Result<Value> {
+		let (init, tail) = match stmts.split_last() {
+			Some((tail, init))
+				if tail.kind.is_expr()
+					|| matches!(tail.kind, ItemKind::Block { .. } | ItemKind::IfElif(_) | ItemKind::IfLet { .. }) =>
+			{
+				(init, Some(tail))
+			}
+			_ => (stmts, None),
+		};
+
+		let mut scope = Scope::child(scope);
+		if !matches!(self.exec_stmts(init, &mut scope)?, Flow::Normal) {
+			return Err(InterpError::Unsupported("break, continue or return out of a block expression".into()));
+		}
+
+		match tail {
+			Some(tail) => self.eval(tail, &scope),
+			None => Ok(Value::Unit),
+		}
+	}
+
+	/// Whether the value has the pattern's shape and is equal to its literals.
+	fn matches(&mut self, pattern: &Pattern, value: &Value, scope: &Rc<Scope>) -> Result<bool> {
+		match (pattern, value) {
+			(Pattern::Ident(_) | Pattern::Wildcard | Pattern::Rest, _) => Ok(true),
+			(Pattern::Literal(literal), _) => Ok(self.eval(literal, scope)? == *value),
+			(Pattern::Or(alternatives), _) => {
+				for alternative in alternatives {
+					if self.matches(alternative, value, scope)? {
+						return Ok(true);
+					}
+				}
+				Ok(false)
+			}
+
+			(Pattern::Tuple(patterns) | Pattern::TupleStruct { elements: patterns, .. }, Value::Tuple(elements))
+			| (Pattern::Slice(patterns), Value::Array(elements)) => {
+				let elements = elements.borrow().clone();
+				let Some((before, after)) = split(patterns, elements.len()) else { return Ok(false) };
+
+				let before = patterns[..before].iter().zip(&elements);
+				let after = patterns[patterns.len() - after..].iter().zip(&elements[elements.len() - after..]);
+				for (pattern, element) in before.chain(after) {
+					if !self.matches(pattern, element, scope)? {
+						return Ok(false);
+					}
+				}
+				Ok(true)
+			}
+
+			(Pattern::Struct { fields: patterns, .. }, Value::Struct(_, fields)) => {
+				let fields = fields.borrow().clone();
+				for (name, pattern) in patterns {
+					match fields.iter().find(|(field, _)| field == name) {
+						Some((_, field)) if self.matches(pattern, field, scope)? => (),
+						_ => return Ok(false),
+					}
+				}
+				Ok(true)
+			}
+
+			(Pattern::Variant { variant, value: pattern }, Value::Variant(other, value)) => match (pattern, value) {
+				_ if variant != other => Ok(false),
+				(Some(pattern), Some(value)) => self.matches(pattern, value, scope),
+				_ => Ok(true),
+			},
+
+			_ => Ok(false),
+		}
+	}
+}
+
+/// How many of `patterns` match elements from the start and from the end of `len` of them, if they can.
+fn split(patterns: &[Pattern], len: usize) -> Option<(usize, usize)> {
+	match patterns.iter().position(|p| matches!(p, Pattern::Rest)) {
+		Some(rest) if len + 1 >= patterns.len() => Some((rest, patterns.len() - rest - 1)),
+		None if len == patterns.len() => Some((patterns.len(), 0)),
+		_ => None,
+	}
+}
+
+/// Items are defined by their own name, paths are only used to find them.
+fn last_segment(name: &str) -> &str {
+	name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Defines the pattern's bindings in `scope`, failing if the value doesn't have its shape.
+fn bind(pattern: &Pattern, value: Value, scope: &Scope) -> Result<()> {
+	let mismatch = |value: &Value| Err(InterpError::Type(format!("{} doesn't match {pattern:?}", value.type_name())));
+
+	match (pattern, &value) {
+		(Pattern::Ident(name), _) => scope.define(name, value),
+		// Only checked by [Interpreter::matches], since they don't bind anything.
+		(Pattern::Wildcard | Pattern::Rest | Pattern::Literal(_) | Pattern::Or(_), _) => (),
+
+		(Pattern::Tuple(patterns) | Pattern::TupleStruct { elements: patterns, .. }, Value::Tuple(elements))
+		| (Pattern::Slice(patterns), Value::Array(elements)) => {
+			let elements = elements.borrow().clone();
+			let Some((before, after)) = split(patterns, elements.len()) else { return mismatch(&value) };
+
+			for (pattern, element) in patterns[..before].iter().zip(&elements) {
+				bind(pattern, element.clone(), scope)?;
+			}
+			for (pattern, element) in patterns[patterns.len() - after..].iter().zip(&elements[elements.len() - after..]) {
+				bind(pattern, element.clone(), scope)?;
+			}
+		}
+
+		(Pattern::Struct { fields: patterns, .. }, Value::Struct(_, fields)) => {
+			let fields = fields.borrow();
+			for (name, pattern) in patterns {
+				match fields.iter().find(|(field, _)| field == name) {
+					Some((_, field)) => bind(pattern, field.clone(), scope)?,
+					None => return Err(InterpError::Undefined(format!("field {name}"))),
+				}
+			}
+		}
+
+		(Pattern::Variant { variant, value: pattern }, Value::Variant(other, value)) if variant == other => {
+			if let (Some(pattern), Some(value)) = (pattern, value) {
+				bind(pattern, (**value).clone(), scope)?;
+			}
+		}
+
+		_ => return mismatch(&value),
+	}
+
+	Ok(())
+}
+
+/// Where a field is in a struct, or an index in an array or tuple, keyed by the field's name or the index.
+fn slot(container: &Value, key: &Value) -> Result<usize> {
+	match (container, key) {
+		(Value::Struct(_, fields), Value::Str(name)) => fields
+			.borrow()
+			.iter()
+			.position(|(field, _)| **field == **name)
+			.ok_or_else(|| InterpError::Undefined(format!("field {name}"))),
+		(Value::Array(elements) | Value::Tuple(elements), Value::Int(i)) => {
+			let len = elements.borrow().len();
+			match usize::try_from(*i) {
+				Ok(index) if index < len => Ok(index),
+				_ => Err(InterpError::Panic(format!("index out of bounds: the len is {len} but the index is {i}"))),
+			}
+		}
+		_ => Err(InterpError::Type(format!("can't index {} with {}", container.type_name(), key.type_name()))),
+	}
+}
+
+fn element(container: &Value, key: &Value) -> Result<Value> {
+	let slot = slot(container, key)?;
+	match container {
+		Value::Struct(_, fields) => Ok(fields.borrow()[slot].1.clone()),
+		Value::Array(elements) | Value::Tuple(elements) => Ok(elements.borrow()[slot].clone()),
+		_ => unreachable!("checked by slot"),
+	}
+}
+
+/// Replaces an element in place, so every copy of the container sees it, like tables in Lua.
+fn set_element(container: &Value, key: &Value, value: Value) -> Result<()> {
+	let slot = slot(container, key)?;
+	match container {
+		Value::Struct(_, fields) => fields.borrow_mut()[slot].1 = value,
+		Value::Array(elements) | Value::Tuple(elements) => elements.borrow_mut()[slot] = value,
+		_ => unreachable!("checked by slot"),
+	}
+	Ok(())
+}
+
+/// What `print` writes for a call, like Lua's print separating values with tabs.
+fn print_line(args: &[Value]) -> String {
+	args.iter().map(Value::to_string).collect::<Vec<_>>().join("\t")
+}
+
+pub(crate) fn unary(op: UnaryOp, value: Value) -> Result<Value> {
+	Ok(match (op, &value) {
+		(UnaryOp::Neg, Value::Int(i)) => Value::Int(i.checked_neg().ok_or(InterpError::Overflow)?),
+		(UnaryOp::Neg, Value::Float(x)) => Value::Float(-x),
+		(UnaryOp::Not, Value::Bool(b)) => Value::Bool(!b),
+		(UnaryOp::BitNot, Value::Int(i)) => Value::Int(!i),
+		_ => return Err(InterpError::Type(format!("can't apply {op:?} to {}", value.type_name()))),
+	})
+}
+
+/// `value as ty`.
+pub(crate) fn cast(ty: NumType, value: Value) -> Result<Value> {
+	Ok(match (value, ty) {
+		(Value::Int(i), NumType::F32) => Value::Float(i as f32 as f64),
+		(Value::Int(i), NumType::F64) => Value::Float(i as f64),
+		(Value::Float(x), NumType::F32) => Value::Float(x as f32 as f64),
+		(Value::Float(x), NumType::F64) => Value::Float(x),
+		(Value::Int(i), ty) => Value::Int(ty.wrap(i)),
+		(Value::Float(x), ty) => Value::Int(ty.saturate(x)),
+		(Value::Bool(b), ty) if !ty.is_float() => Value::Int(b as i64),
+		// A char lowered to a string with [crate::backend::CharRepr::String].
+		(Value::Str(s), ty) if !ty.is_float() && s.chars().count() == 1 => {
+			Value::Int(ty.wrap(u32::from(s.chars().next().unwrap()).into()))
+		}
+		(other, ty) => return Err(InterpError::Type(format!("can't cast {} to {}", other.type_name(), ty.name()))),
+	})
+}
+
+pub(crate) fn binary(op: &BinaryOp, lhs: Value, rhs: Value) -> Result<Value> {
+	use Value::{Bool, Float, Int, Str};
+
+	let mismatch = |lhs: &Value, rhs: &Value| {
+		Err(InterpError::Type(format!(
+			"can't apply {op:?} to {} and {}",
+			lhs.type_name(),
+			rhs.type_name()
+		)))
+	};
+
+	Ok(match (op, &lhs, &rhs) {
+		(BinaryOp::Eq, ..) => Bool(lhs == rhs),
+		(BinaryOp::Ne, ..) => Bool(lhs != rhs),
+
+		(BinaryOp::Add, Int(a), Int(b)) => Int(a.checked_add(*b).ok_or(InterpError::Overflow)?),
+		(BinaryOp::Sub, Int(a), Int(b)) => Int(a.checked_sub(*b).ok_or(InterpError::Overflow)?),
+		(BinaryOp::Mul, Int(a), Int(b)) => Int(a.checked_mul(*b).ok_or(InterpError::Overflow)?),
+		(BinaryOp::Div | BinaryOp::IntDiv | BinaryOp::Mod, Int(_), Int(0)) => return Err(InterpError::DivisionByZero),
+		(BinaryOp::Div | BinaryOp::IntDiv, Int(a), Int(b)) => Int(a.checked_div(*b).ok_or(InterpError::Overflow)?),
+		(BinaryOp::Mod, Int(a), Int(b)) => Int(a.checked_rem(*b).ok_or(InterpError::Overflow)?),
+
+		(BinaryOp::Add, Float(a), Float(b)) => Float(a + b),
+		(BinaryOp::Sub, Float(a), Float(b)) => Float(a - b),
+		(BinaryOp::Mul, Float(a), Float(b)) => Float(a * b),
+		(BinaryOp::Div, Float(a), Float(b)) => Float(a / b),
+		(BinaryOp::Mod, Float(a), Float(b)) => Float(a % b),
+
+		(BinaryOp::Add, Str(a), Str(b)) => Str(format!("{a}{b}").into()),
+
+		(BinaryOp::Band, Int(a), Int(b)) => Int(a & b),
+		(BinaryOp::Bor, Int(a), Int(b)) => Int(a | b),
+		(BinaryOp::Bxor, Int(a), Int(b)) => Int(a ^ b),
+		(BinaryOp::Band, Bool(a), Bool(b)) => Bool(a & b),
+		(BinaryOp::Bor, Bool(a), Bool(b)) => Bool(a | b),
+		(BinaryOp::Bxor, Bool(a), Bool(b)) => Bool(a ^ b),
+		(BinaryOp::Bshl, Int(a), Int(b)) => Int(a.checked_shl(*b as u32).ok_or(InterpError::Overflow)?),
+		(BinaryOp::Bshr, Int(a), Int(b)) => Int(a.checked_shr(*b as u32).ok_or(InterpError::Overflow)?),
+
+		(BinaryOp::UDiv | BinaryOp::UMod, Int(_), Int(0)) => return Err(InterpError::DivisionByZero),
+		(BinaryOp::UDiv, Int(a), Int(b)) => Int((*a as u64 / *b as u64) as i64),
+		(BinaryOp::UMod, Int(a), Int(b)) => Int((*a as u64 % *b as u64) as i64),
+		(BinaryOp::ULt | BinaryOp::ULe | BinaryOp::UGe | BinaryOp::UGt, Int(a), Int(b)) => {
+			let (a, b) = (*a as u64, *b as u64);
+			Bool(match op {
+				BinaryOp::ULt => a < b,
+				BinaryOp::ULe => a <= b,
+				BinaryOp::UGe => a >= b,
+				_ => a > b,
+			})
+		}
+
+		(BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, ..) => {
+			let ordering = match (&lhs, &rhs) {
+				(Int(a), Int(b)) => a.partial_cmp(b),
+				(Float(a), Float(b)) => a.partial_cmp(b),
+				(Str(a), Str(b)) => a.partial_cmp(b),
+				(Bool(a), Bool(b)) => a.partial_cmp(b),
+				_ => return mismatch(&lhs, &rhs),
+			};
+
+			Bool(match (op, ordering) {
+				(_, None) => false,
+				(BinaryOp::Lt, Some(o)) => o.is_lt(),
+				(BinaryOp::Le, Some(o)) => o.is_le(),
+				(BinaryOp::Gt, Some(o)) => o.is_gt(),
+				(_, Some(o)) => o.is_ge(),
+			})
+		}
+
+		_ => return mismatch(&lhs, &rhs),
+	})
+}
@@ -0,0 +1,146 @@
+//! Rendering of format strings with the same rules as [std::fmt].
+
+use super::{InterpError, Result, Value};
+use crate::backend::format::{Align, Count, FormatArg, FormatKind, FormatSpec};
+
+/// Fills the gaps between `strings` with the formatted values, of which those `unsigned` says are `u64`s, see
+/// [crate::backend::format::unsigned].
+pub(crate) fn format(strings: &[String], args: &[FormatArg], values: &[Value], unsigned: &[bool]) -> Result<String> {
+	let get = |i: usize| values.get(i).ok_or_else(|| InterpError::Unsupported("missing format argument".into()));
+	let count = |count: Option<Count>| -> Result<Option<usize>> {
+		match count {
+			None => Ok(None),
+			Some(Count::Is(n)) => Ok(Some(n)),
+			Some(Count::Arg(i)) => match get(i)? {
+				Value::Int(n) if *n >= 0 => Ok(Some(*n as usize)),
+				other => Err(InterpError::Type(format!("expected a count, got {}", other.type_name()))),
+			},
+		}
+	};
+
+	let mut out = String::new();
+	for (i, s) in strings.iter().enumerate() {
+		out.push_str(s);
+
+		if let Some(arg) = args.get(i) {
+			let (width, precision) = (count(arg.spec.width)?, count(arg.spec.precision)?);
+			let unsigned = unsigned.get(arg.value).copied().unwrap_or_default();
+			out.push_str(&format_value(get(arg.value)?, unsigned, &arg.spec, width, precision)?);
+		}
+	}
+
+	Ok(out)
+}
+
+fn format_value(value: &Value, unsigned: bool, spec: &FormatSpec, width: Option<usize>, precision: Option<usize>) -> Result<String> {
+	use FormatKind::*;
+
+	// Numbers are split up into their sign, radix prefix and digits, so zeroes can be padded in between.
+	let (negative, prefix, digits) = match (spec.kind, value) {
+		(Display | Debug, Value::Int(i)) if unsigned => (false, "", (*i as u64).to_string()),
+		(Display | Debug, Value::Int(i)) => (*i < 0, "", i.unsigned_abs().to_string()),
+		(Display, Value::Float(x)) => match precision {
+			Some(p) => (x.is_sign_negative(), "", format!("{:.*}", p, x.abs())),
+			None => (x.is_sign_negative(), "", format!("{}", x.abs())),
+		},
+		(Debug, Value::Float(x)) => match precision {
+			Some(p) => (x.is_sign_negative(), "", format!("{:.*?}", p, x.abs())),
+			None => (x.is_sign_negative(), "", format!("{:?}", x.abs())),
+		},
+
+		// Negative numbers are printed as their two's complement, without a sign.
+		(LowerHex, Value::Int(i)) => (false, "0x", format!("{i:x}")),
+		(UpperHex, Value::Int(i)) => (false, "0x", format!("{i:X}")),
+		(Octal, Value::Int(i)) => (false, "0o", format!("{i:o}")),
+		(Binary, Value::Int(i)) => (false, "0b", format!("{i:b}")),
+
+		(LowerExp | UpperExp, Value::Int(_) | Value::Float(_)) => {
+			let (negative, x) = match value {
+				Value::Int(i) => (*i < 0, i.unsigned_abs() as f64),
+				Value::Float(x) => (x.is_sign_negative(), x.abs()),
+				_ => unreachable!(),
+			};
+
+			let digits = match (spec.kind, precision) {
+				(LowerExp, Some(p)) => format!("{:.*e}", p, x),
+				(LowerExp, None) => format!("{x:e}"),
+				(_, Some(p)) => format!("{:.*E}", p, x),
+				(_, None) => format!("{x:E}"),
+			};
+			(negative, "", digits)
+		}
+
+		(Display, other) => {
+			let s = other.to_string();
+			let s = match precision {
+				Some(p) => s.chars().take(p).collect(),
+				None => s,
+			};
+			return Ok(pad(&s, spec, width, Align::Left));
+		}
+		(Debug, other) => return Ok(pad(&debug(other), spec, width, Align::Left)),
+
+		(kind, other) => {
+			return Err(InterpError::Type(format!("can't format {} with `{{:{}}}`", other.type_name(), kind.as_str())))
+		}
+	};
+
+	let sign = match (negative, spec.sign) {
+		(true, _) => "-",
+		(false, true) => "+",
+		(false, false) => "",
+	};
+	let prefix = if spec.alternate { prefix } else { "" };
+
+	let len = sign.len() + prefix.len() + digits.chars().count();
+	match width {
+		// Zeroes go after the sign and prefix, ignoring the fill and alignment.
+		Some(width) if spec.zero && len < width => Ok(format!("{sign}{prefix}{}{digits}", "0".repeat(width - len))),
+		_ => Ok(pad(&format!("{sign}{prefix}{digits}"), spec, width, Align::Right)),
+	}
+}
+
+fn pad(s: &str, spec: &FormatSpec, width: Option<usize>, default: Align) -> String {
+	let len = s.chars().count();
+	let Some(padding) = width.and_then(|width| width.checked_sub(len)) else {
+		return s.to_owned();
+	};
+
+	let (before, after) = match spec.align.unwrap_or(default) {
+		Align::Left => (0, padding),
+		Align::Center => (padding / 2, padding - padding / 2),
+		Align::Right => (padding, 0),
+	};
+
+	let fill = |n| spec.fill.to_string().repeat(n);
+	format!("{}{s}{}", fill(before), fill(after))
+}
+
+/// `{:?}`, which quotes strings, also inside of arrays, tuples and structs.
+fn debug(value: &Value) -> String {
+	match value {
+		Value::Str(s) => format!("{s:?}"),
+		Value::Float(x) => format!("{x:?}"),
+		Value::Array(elements) => {
+			let elements = elements.borrow().iter().map(debug).collect::<Vec<_>>();
+			format!("[{}]", elements.join(", "))
+		}
+		Value::Tuple(elements) => {
+			let elements = elements.borrow().iter().map(debug).collect::<Vec<_>>();
+			match elements.len() {
+				1 => format!("({},)", elements[0]),
+				_ => format!("({})", elements.join(", ")),
+			}
+		}
+		Value::Struct(path, fields) => {
+			let fields = fields.borrow().iter().map(|(name, v)| format!("{name}: {}", debug(v))).collect::<Vec<_>>();
+			let name = path.rsplit("::").next().unwrap_or(path);
+			match fields.is_empty() {
+				true => name.to_owned(),
+				false => format!("{name} {{ {} }}", fields.join(", ")),
+			}
+		}
+		Value::Variant(variant, Some(v)) => format!("{}({})", variant.name(), debug(v)),
+		other => other.to_string(),
+	}
+}
@@ -0,0 +1,52 @@
+//! Methods of `Option` and `Result`, the same ones the generators implement.
+
+use std::rc::Rc;
+
+use super::{InterpError, Interpreter, Result, Value};
+use crate::backend::Variant;
+
+impl Interpreter {
+	pub(super) fn call_method(&mut self, receiver: Value, method: &str, args: Vec<Value>) -> Result<Value> {
+		let Value::Variant(variant, value) = &receiver else {
+			return Err(InterpError::Unsupported(format!("method `{method}` on {}", receiver.type_name())));
+		};
+		let value = value.as_deref().cloned();
+		let some = |value| Value::Variant(Variant::Some, Some(Rc::new(value)));
+		let none = Value::Variant(Variant::None, None);
+
+		Ok(match (method, *variant, value, &args[..]) {
+			("is_some", ..) => Value::Bool(*variant == Variant::Some),
+			("is_none", ..) => Value::Bool(*variant == Variant::None),
+			("is_ok", ..) => Value::Bool(*variant == Variant::Ok),
+			("is_err", ..) => Value::Bool(*variant == Variant::Err),
+
+			("map", variant @ (Variant::Some | Variant::Ok), Some(value), [f]) => {
+				Value::Variant(variant, Some(Rc::new(self.call_value(f, vec![value])?)))
+			}
+			("and_then", Variant::Some | Variant::Ok, Some(value), [f]) => self.call_value(f, vec![value])?,
+			("map" | "and_then", _, _, [_]) => receiver.clone(),
+
+			("unwrap", Variant::Some | Variant::Ok, Some(value), []) => value,
+			("unwrap", Variant::None, _, []) => {
+				return Err(InterpError::Panic("called `Option::unwrap()` on a `None` value".into()))
+			}
+			("unwrap", Variant::Err, Some(e), []) => {
+				return Err(InterpError::Panic(format!("called `Result::unwrap()` on an `Err` value: {e:?}")))
+			}
+
+			("unwrap_or", Variant::Some | Variant::Ok, Some(value), [_]) => value,
+			("unwrap_or", _, _, [default]) => default.clone(),
+
+			("ok_or", Variant::Some, Some(value), [_]) => Value::Variant(Variant::Ok, Some(Rc::new(value))),
+			("ok_or", Variant::None, _, [e]) => Value::Variant(Variant::Err, Some(Rc::new(e.clone()))),
+
+			("ok", Variant::Ok, Some(value), []) => some(value),
+			("ok", Variant::Err, _, []) => none,
+
+			_ => {
+				let kind = receiver.type_name();
+				return Err(InterpError::Unsupported(format!("method `{method}` on {kind} with {} arguments", args.len())));
+			}
+		})
+	}
+}
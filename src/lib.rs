@@ -1,8 +1,12 @@
 pub mod backend;
 pub mod gen;
+pub mod interp;
+pub mod opt;
+pub mod resolve;
+pub mod shadow;
 
 #[cfg(feature = "syn")]
 pub use crate::backend::{Backend, Ast};
 
-#[cfg(feature = "venial")]
+#[cfg(all(feature = "venial", not(feature = "syn")))]
 pub use crate::backend::{Backend, Ast};
\ No newline at end of file
@@ -1,16 +1,237 @@
-mod backend;
-mod gen;
+use smyth::backend::{self, Ast};
+use smyth::gen::{self, CodeGenerator};
+
+const USAGE: &str = "usage: smyth [-O] [--strict-integers] [--from rust|ir|ir-json] [--emit lua|ir|ir-json] [-o <path>|-]
+	[--macro <name>=<function>] [--format-macro <name>=<function>] [--option-repr nil|tagged]
+	[--chars string|code] [--index-base 1|0] [--lua 5.1|5.2|5.3|5.4] [input]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+	Rust,
+	Lua,
+	Ir,
+	IrJson,
+}
+
+impl Format {
+	fn parse(s: &str) -> Result<Self, String> {
+		match s {
+			"rust" => Ok(Self::Rust),
+			"lua" => Ok(Self::Lua),
+			"ir" => Ok(Self::Ir),
+			"ir-json" => Ok(Self::IrJson),
+			other => Err(format!("unknown format `{other}`\n{USAGE}")),
+		}
+	}
+
+	fn extension(self) -> &'static str {
+		match self {
+			Self::Rust => "rs",
+			Self::Lua => "lua",
+			Self::Ir => "ir",
+			Self::IrJson => "json",
+		}
+	}
+}
+
+struct Args {
+	input: String,
+	output: String,
+	from: Format,
+	emit: Format,
+	optimize: bool,
+	strict_integers: bool,
+	chars: backend::CharRepr,
+	macros: backend::Macros,
+	option_repr: gen::lua::OptionRepr,
+	index_base: gen::lua::IndexBase,
+	lua_version: gen::lua::LuaVersion,
+}
+
+impl Args {
+	fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+		let (mut input, mut output) = (None, None);
+		let (mut from, mut emit) = (Format::Rust, Format::Lua);
+		let mut optimize = false;
+		let mut strict_integers = false;
+		let mut chars = backend::CharRepr::default();
+		let mut macros = backend::Macros::new();
+		let mut option_repr = gen::lua::OptionRepr::default();
+		let mut index_base = gen::lua::IndexBase::default();
+		let mut lua_version = gen::lua::LuaVersion::default();
+
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or_else(|| format!("missing value for `{arg}`\n{USAGE}"));
+
+			match arg.as_str() {
+				"--from" => from = Format::parse(&value()?)?,
+				"--emit" => emit = Format::parse(&value()?)?,
+				"-o" => output = Some(value()?),
+				"-O" => optimize = true,
+				"--strict-integers" => strict_integers = true,
+				"--chars" => {
+					chars = match value()?.as_str() {
+						"string" => backend::CharRepr::String,
+						"code" => backend::CharRepr::Code,
+						other => return Err(format!("unknown char representation `{other}`\n{USAGE}")),
+					}
+				}
+				"--macro" | "--format-macro" => {
+					let value = value()?;
+					let (name, function) = value
+						.split_once('=')
+						.ok_or_else(|| format!("expected `<name>=<function>`, got `{value}`\n{USAGE}"))?;
+
+					let rule = match arg.as_str() {
+						"--macro" => backend::Macro::Call(function.to_owned()),
+						_ => backend::Macro::Format(function.to_owned()),
+					};
+					macros.insert(name.trim_end_matches('!'), rule);
+				}
+				"--option-repr" => {
+					option_repr = match value()?.as_str() {
+						"nil" => gen::lua::OptionRepr::Nil,
+						"tagged" => gen::lua::OptionRepr::Tagged,
+						other => return Err(format!("unknown option representation `{other}`\n{USAGE}")),
+					}
+				}
+				"--index-base" => {
+					index_base = match value()?.as_str() {
+						"1" => gen::lua::IndexBase::One,
+						"0" => gen::lua::IndexBase::Zero,
+						other => return Err(format!("unknown index base `{other}`\n{USAGE}")),
+					}
+				}
+				"--lua" => {
+					lua_version = match value()?.as_str() {
+						"5.1" => gen::lua::LuaVersion::Lua51,
+						"5.2" => gen::lua::LuaVersion::Lua52,
+						"5.3" | "5.4" => gen::lua::LuaVersion::Lua53,
+						other => return Err(format!("unknown Lua version `{other}`\n{USAGE}")),
+					}
+				}
+				"-h" | "--help" => return Err(USAGE.to_owned()),
+				_ if input.is_none() && !arg.starts_with('-') => input = Some(arg),
+				_ => return Err(format!("unexpected argument `{arg}`\n{USAGE}")),
+			}
+		}
+
+		if from == Format::Lua {
+			return Err(format!("lua can't be used as an input\n{USAGE}"));
+		}
+
+		if emit == Format::Rust {
+			return Err(format!("rust can't be emitted\n{USAGE}"));
+		}
+
+		Ok(Self {
+			input: input.unwrap_or_else(|| "test.rs".to_owned()),
+			output: output.unwrap_or_else(|| format!("out.{}", emit.extension())),
+			from,
+			emit,
+			optimize,
+			strict_integers,
+			chars,
+			macros,
+			option_repr,
+			index_base,
+			lua_version,
+		})
+	}
+}
+
+#[cfg(feature = "serde")]
+fn from_ir_json(source: &[u8]) -> Result<Vec<backend::Item>, Box<dyn std::error::Error>> {
+	Ok(backend::from_json(source)?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn from_ir_json(_: &[u8]) -> Result<Vec<backend::Item>, Box<dyn std::error::Error>> {
+	Err("smyth was built without the `serde` feature".into())
+}
+
+#[cfg(feature = "serde")]
+fn to_ir_json(items: &[backend::Item]) -> Result<String, Box<dyn std::error::Error>> {
+	Ok(backend::to_json(items)?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn to_ir_json(_: &[backend::Item]) -> Result<String, Box<dyn std::error::Error>> {
+	Err("smyth was built without the `serde` feature".into())
+}
+
+/// Prints an error, with where it is in the input when it's known.
+fn report(input: &str, span: Option<backend::Span>, e: &dyn std::fmt::Display) {
+	match span {
+		Some(span) => eprintln!("{input}:{}:{}: {e}", span.lo.line, span.lo.column + 1),
+		None => eprintln!("{input}: {e}"),
+	}
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-	use backend::Ast;
-	use gen::CodeGenerator;
+	let args = match Args::parse(std::env::args().skip(1)) {
+		Ok(args) => args,
+		Err(msg) => {
+			eprintln!("{msg}");
+			std::process::exit(2);
+		}
+	};
+	let source = std::fs::read(&args.input)?;
+
+	let ast = match args.from {
+		Format::Ir => backend::Backend::from_items(backend::sexpr::from_sexpr(std::str::from_utf8(&source)?)?),
+		Format::IrJson => backend::Backend::from_items(from_ir_json(&source)?),
+		_ => {
+			let options = backend::Options {
+				macros: args.macros.clone(),
+				strict_integers: args.strict_integers,
+				chars: args.chars,
+				..Default::default()
+			};
+			backend::Backend::generate_with(source, &options)?
+		}
+	};
+
+	let items = match smyth::resolve::resolve(ast.items().clone()) {
+		Ok(items) => items,
+		Err(errors) => {
+			for e in errors {
+				report(&args.input, e.span(), &e);
+			}
+			std::process::exit(1);
+		}
+	};
+	let ast = backend::Backend::from_items(items);
 
-	let ast = backend::Backend::generate(include_bytes!("../test.rs"))?;
+	let ast = if args.optimize {
+		backend::Backend::from_items(smyth::opt::optimize(ast.items().clone()))
+	} else {
+		ast
+	};
 
-	let codegen = gen::lua::LuaCodegen::new();
-	let code = codegen.generate(&ast)?;
+	let code = match args.emit {
+		Format::Ir => backend::sexpr::to_sexpr(ast.items()),
+		Format::IrJson => to_ir_json(ast.items())?,
+		_ => {
+			let codegen = gen::lua::LuaCodegen::new()
+				.with_option_repr(args.option_repr)
+				.with_index_base(args.index_base)
+				.with_lua_version(args.lua_version);
+			match codegen.generate(&ast) {
+				Ok(code) => code,
+				Err(e) => {
+					report(&args.input, e.span(), &e);
+					std::process::exit(1);
+				}
+			}
+		}
+	};
 
-	std::fs::write("out.lua", code)?;
+	if args.output == "-" {
+		println!("{code}");
+	} else {
+		std::fs::write(&args.output, code)?;
+	}
 
 	Ok(())
 }
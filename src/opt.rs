@@ -0,0 +1,350 @@
+//! Optimisations over the IR, enabled with `-O`.
+//!
+//! Constant expressions are folded using the same semantics as [crate::interp], so an optimised
+//! program prints the same thing as the original one.
+
+use crate::backend::visit::{self, Fold, Visit};
+use crate::backend::{BinaryOp, IfElif, Item, ItemKind, NumType, Span, UnaryOp};
+use crate::interp::{self, Value};
+
+mod inline;
+pub use inline::inline;
+
+/// Runs every pass over the items of a file.
+pub fn optimize(items: Vec<Item>) -> Vec<Item> {
+	simplify(inline(items))
+}
+
+/// Constant folding and dead code elimination.
+pub fn simplify(items: Vec<Item>) -> Vec<Item> {
+	let mut simplify = Simplify;
+	items.into_iter().map(|item| simplify.fold_item(item)).collect()
+}
+
+struct Simplify;
+
+impl Simplify {
+	/// Folds the statements of a block, where `tail` uses whatever they declare.
+	fn fold_block(&mut self, stmts: Vec<Item>, tail: Option<&Item>) -> Vec<Item> {
+		let mut out = vec![];
+		for stmt in stmts {
+			out.extend(self.fold_stmt(stmt));
+
+			// Anything after this is unreachable.
+			if out.last().is_some_and(diverges) {
+				break;
+			}
+		}
+
+		// Backwards, so a declaration only used by another unused one goes too.
+		for i in (0..out.len()).rev() {
+			if let ItemKind::VarDecl { name, expr } = &out[i].kind {
+				if is_pure(expr) && !uses(&out[i + 1..], name) && !tail.is_some_and(|tail| uses(std::slice::from_ref(tail), name)) {
+					out.remove(i);
+				}
+			}
+		}
+
+		out
+	}
+
+	/// Folds the statements of a block or branch used as a value. The last statement is that value, so
+	/// it has to stay even if evaluating it does nothing.
+	fn fold_value(&mut self, mut stmts: Vec<Item>) -> Vec<Item> {
+		if !stmts.last().is_some_and(|s| s.kind.is_expr() || is_compound(s)) {
+			return self.fold_block(stmts, None);
+		}
+
+		let tail = stmts.pop().map(|tail| self.fold_item(tail)).unwrap();
+		let mut stmts = self.fold_block(stmts, Some(&tail));
+		if !stmts.last().is_some_and(diverges) {
+			stmts.push(tail);
+		}
+
+		stmts
+	}
+}
+
+impl Fold for Simplify {
+	fn fold_item(&mut self, item: Item) -> Item {
+		let span = item.span;
+
+		// Blocks and branches reached from here are used as values, see `fold_value`.
+		let item = match item.kind {
+			ItemKind::Block { stmts } => return Item::new(ItemKind::Block { stmts: self.fold_value(stmts) }, span),
+			ItemKind::IfElif(IfElif { condition, stmts, elif, else_stmts }) => {
+				let if_elif = IfElif {
+					condition: Box::new(self.fold_item(*condition)),
+					stmts: self.fold_value(stmts),
+					elif: elif.into_iter().map(|(cond, stmts)| (self.fold_item(cond), self.fold_value(stmts))).collect(),
+					else_stmts: else_stmts.map(|stmts| self.fold_value(stmts)),
+				};
+
+				return Item::new(ItemKind::IfElif(if_elif), span);
+			}
+			ItemKind::IfLet { pattern, expr, stmts, else_stmts } => {
+				let if_let = ItemKind::IfLet {
+					pattern,
+					expr: Box::new(self.fold_item(*expr)),
+					stmts: self.fold_value(stmts),
+					else_stmts: else_stmts.map(|stmts| self.fold_value(stmts)),
+				};
+
+				return Item::new(if_let, span);
+			}
+			kind => visit::fold_children(self, Item::new(kind, span)),
+		};
+
+		match item.kind {
+			ItemKind::ExprBinary { lhs, rhs, op } => fold_binary(*lhs, *rhs, op, span),
+			ItemKind::ExprUnary { op, expr } => fold_unary(op, *expr, span),
+			ItemKind::ExprCast { expr, ty } => match literal(&expr).and_then(|value| interp::cast(ty, value).ok()) {
+				Some(Value::Int(i)) => Item::new(ItemKind::ExprInteger(i, Some(ty)), span),
+				Some(Value::Float(x)) => Item::new(ItemKind::ExprDecimal(x), span),
+				_ => Item::new(ItemKind::ExprCast { expr, ty }, span),
+			},
+			ItemKind::ExprWrap { expr, ty } => match expr.kind {
+				ItemKind::ExprInteger(i, _) => Item::new(ItemKind::ExprInteger(ty.wrap(i), Some(ty)), span),
+				kind => Item::new(ItemKind::ExprWrap { expr: Box::new(Item::new(kind, expr.span)), ty }, span),
+			},
+			ItemKind::ExprFString { strings, args, values } => {
+				// Floats are left alone, Lua doesn't format them like Rust does.
+				let literals = values.iter().map(literal).collect::<Option<Vec<_>>>();
+				let formatted = literals
+					.filter(|literals| !literals.iter().any(|v| matches!(v, Value::Float(_))))
+					.and_then(|literals| {
+						let unsigned = values.iter().map(crate::backend::format::unsigned).collect::<Vec<_>>();
+						interp::format(&strings, &args, &literals, &unsigned).ok()
+					});
+
+				match formatted {
+					Some(out) => Item::new(ItemKind::ExprString(out), span),
+					None => Item::new(ItemKind::ExprFString { strings, args, values }, span),
+				}
+			}
+			kind => Item::new(kind, span),
+		}
+	}
+
+	fn fold_stmts(&mut self, stmts: Vec<Item>) -> Vec<Item> {
+		self.fold_block(stmts, None)
+	}
+
+	fn fold_stmt(&mut self, stmt: Item) -> Vec<Item> {
+		// As a statement, the value of a block or `if` isn't used.
+		let stmt = match is_compound(&stmt) {
+			true => visit::fold_children(self, stmt),
+			false => self.fold_item(stmt),
+		};
+		let span = stmt.span;
+
+		// Evaluating it does nothing.
+		if stmt.kind.is_expr() && is_pure(&stmt) {
+			return vec![];
+		}
+
+		match stmt.kind {
+			ItemKind::IfElif(if_elif) => fold_if(if_elif, span),
+			ItemKind::Block { stmts } => scoped(stmts, span),
+
+			ItemKind::While { condition, .. } if matches!(condition.kind, ItemKind::ExprBool(false)) => vec![],
+			// The condition still runs once.
+			ItemKind::While { condition, stmts } if matches!(stmts.first().map(|s| &s.kind), Some(ItemKind::Break)) => {
+				match is_pure(&condition) {
+					true => vec![],
+					false => vec![*condition],
+				}
+			}
+
+			ItemKind::ForRange { min, max, jump: None, .. }
+				if matches!((&min.kind, &max.kind), (ItemKind::ExprInteger(min, _), ItemKind::ExprInteger(max, _)) if min > max) =>
+			{
+				vec![]
+			}
+
+			kind => vec![Item::new(kind, span)],
+		}
+	}
+}
+
+/// The type of an integer literal, which folding keeps so it's printed the same way.
+fn num_type(item: &Item) -> Option<NumType> {
+	match item.kind {
+		ItemKind::ExprInteger(_, ty) => ty,
+		_ => None,
+	}
+}
+
+fn literal(item: &Item) -> Option<Value> {
+	match &item.kind {
+		ItemKind::ExprInteger(i, _) => Some(Value::Int(*i)),
+		ItemKind::ExprDecimal(x) => Some(Value::Float(*x)),
+		ItemKind::ExprString(s) => Some(Value::Str(s.as_str().into())),
+		ItemKind::ExprBool(b) => Some(Value::Bool(*b)),
+		_ => None,
+	}
+}
+
+fn fold_binary(lhs: Item, rhs: Item, op: BinaryOp, span: Option<Span>) -> Item {
+	match (&op, &lhs.kind) {
+		(BinaryOp::And, ItemKind::ExprBool(false)) | (BinaryOp::Or, ItemKind::ExprBool(true)) => {
+			return Item::new(lhs.kind, span)
+		}
+		(BinaryOp::And, ItemKind::ExprBool(true)) | (BinaryOp::Or, ItemKind::ExprBool(false)) => return rhs,
+		(BinaryOp::And | BinaryOp::Or, _) => (),
+
+		_ => {
+			// Anything that would fail at runtime (overflow, division by zero) is kept as is.
+			let value = literal(&lhs).zip(literal(&rhs)).and_then(|(a, b)| interp::binary(&op, a, b).ok());
+			// Shifts have the type of what's shifted, other operands are of the same type.
+			let ty = match op {
+				BinaryOp::Bshl | BinaryOp::Bshr => num_type(&lhs),
+				_ => num_type(&lhs).or(num_type(&rhs)),
+			};
+			let kind = match value {
+				Some(Value::Int(i)) => Some(ItemKind::ExprInteger(i, ty)),
+				Some(Value::Float(x)) if x.is_finite() => Some(ItemKind::ExprDecimal(x)),
+				Some(Value::Str(s)) => Some(ItemKind::ExprString(s.to_string())),
+				Some(Value::Bool(b)) => Some(ItemKind::ExprBool(b)),
+				_ => None,
+			};
+
+			if let Some(kind) = kind {
+				return Item::new(kind, span);
+			}
+		}
+	}
+
+	Item::new(ItemKind::ExprBinary { lhs: Box::new(lhs), rhs: Box::new(rhs), op }, span)
+}
+
+fn fold_unary(op: UnaryOp, expr: Item, span: Option<Span>) -> Item {
+	let kind = match literal(&expr).and_then(|value| interp::unary(op, value).ok()) {
+		Some(Value::Int(i)) => ItemKind::ExprInteger(i, num_type(&expr)),
+		Some(Value::Float(x)) => ItemKind::ExprDecimal(x),
+		Some(Value::Bool(b)) => ItemKind::ExprBool(b),
+		_ => ItemKind::ExprUnary { op, expr: Box::new(expr) },
+	};
+
+	Item::new(kind, span)
+}
+
+/// Drops branches that can never run, and the `if` itself when only one is left.
+fn fold_if(if_elif: IfElif, span: Option<Span>) -> Vec<Item> {
+	let IfElif { condition, stmts, elif, mut else_stmts } = if_elif;
+
+	let mut arms = vec![];
+	for (cond, stmts) in std::iter::once((*condition, stmts)).chain(elif) {
+		match cond.kind {
+			ItemKind::ExprBool(false) => continue,
+			ItemKind::ExprBool(true) => {
+				else_stmts = Some(stmts);
+				break;
+			}
+			_ => arms.push((cond, stmts)),
+		}
+	}
+
+	if arms.is_empty() {
+		return scoped(else_stmts.unwrap_or_default(), span);
+	}
+
+	let mut arms = arms.into_iter();
+	let (condition, stmts) = arms.next().unwrap();
+	vec![Item::new(
+		ItemKind::IfElif(IfElif {
+			condition: Box::new(condition),
+			stmts,
+			elif: arms.collect(),
+			else_stmts: else_stmts.filter(|stmts| !stmts.is_empty()),
+		}),
+		span,
+	)]
+}
+
+/// Statements of a block whose value isn't used, which only need a scope if they declare something.
+fn scoped(stmts: Vec<Item>, span: Option<Span>) -> Vec<Item> {
+	// Keep a scope around declarations so they can't shadow anything after the block.
+	if stmts.iter().any(|s| matches!(s.kind, ItemKind::VarDecl { .. } | ItemKind::Let { .. } | ItemKind::FunctionDefinition { .. })) {
+		return vec![Item::new(ItemKind::Block { stmts }, span)];
+	}
+
+	stmts
+}
+
+/// Whether the item is a block or an `if`, which can be used as a value.
+fn is_compound(item: &Item) -> bool {
+	matches!(item.kind, ItemKind::Block { .. } | ItemKind::IfElif(_) | ItemKind::IfLet { .. })
+}
+
+/// Whether control never reaches the statement after this one.
+fn diverges(item: &Item) -> bool {
+	let ends = |stmts: &[Item]| stmts.last().is_some_and(diverges);
+
+	match &item.kind {
+		ItemKind::Break | ItemKind::Continue | ItemKind::Return { .. } => true,
+		ItemKind::Block { stmts } => ends(stmts),
+		ItemKind::IfElif(IfElif { stmts, elif, else_stmts: Some(else_stmts), .. }) => {
+			ends(stmts) && elif.iter().all(|(_, stmts)| ends(stmts)) && ends(else_stmts)
+		}
+		ItemKind::IfLet { stmts, else_stmts: Some(else_stmts), .. } => ends(stmts) && ends(else_stmts),
+		_ => false,
+	}
+}
+
+/// Whether evaluating the expression can neither fail nor have side effects.
+fn is_pure(item: &Item) -> bool {
+	match &item.kind {
+		ItemKind::ExprIdent(_)
+		| ItemKind::ExprDecimal(_)
+		| ItemKind::ExprInteger(..)
+		| ItemKind::ExprString(_)
+		| ItemKind::ExprBool(_)
+		| ItemKind::ExprClosure { .. } => true,
+
+		ItemKind::ExprArray { elements: values }
+		| ItemKind::ExprTuple { elements: values }
+		| ItemKind::ExprFString { values, .. } => values.iter().all(is_pure),
+		ItemKind::ExprStruct { fields, .. } => fields.iter().all(|(_, value)| is_pure(value)),
+		ItemKind::ExprVariant { value, .. } => value.as_deref().is_none_or(is_pure),
+
+		// Arithmetic can overflow or divide by zero.
+		ItemKind::ExprBinary { lhs, rhs, op } => {
+			let safe = matches!(
+				op,
+				BinaryOp::And | BinaryOp::Or | BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+					| BinaryOp::ULt | BinaryOp::ULe | BinaryOp::UGe | BinaryOp::UGt
+			);
+
+			safe && is_pure(lhs) && is_pure(rhs)
+		}
+		// Negating the smallest integer overflows.
+		ItemKind::ExprUnary { op, expr } => *op != UnaryOp::Neg && is_pure(expr),
+		ItemKind::ExprCast { expr, .. } | ItemKind::ExprWrap { expr, .. } => is_pure(expr),
+
+		_ => false,
+	}
+}
+
+/// Whether any of the statements read or assign to `name`.
+fn uses(stmts: &[Item], name: &str) -> bool {
+	struct Uses<'a> {
+		name: &'a str,
+		found: bool,
+	}
+
+	impl Visit for Uses<'_> {
+		fn visit_expr_ident(&mut self, name: &str) {
+			self.found |= name == self.name;
+		}
+
+		fn visit_var_set(&mut self, name: &str, expr: &Item) {
+			self.found |= name == self.name;
+			self.visit_item(expr);
+		}
+	}
+
+	let mut v = Uses { name, found: false };
+	v.visit_stmts(stmts);
+	v.found
+}
@@ -0,0 +1,524 @@
+//! Inlining of small private functions.
+//!
+//! A call is replaced with the function's body when it's cheap to do so. Locals declared by the body are
+//! renamed so they can't capture anything at the call site, and arguments are bound to fresh locals unless
+//! they're a literal or variable that can be substituted as is.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::backend::visit::{self, Fold, Visit, VisitMut};
+use crate::backend::{BinaryOp, Item, ItemKind, Pattern, Span};
+
+/// Functions with more items than this in their body are left alone.
+pub const MAX_SIZE: usize = 16;
+
+/// Inlines calls to private, non-recursive functions defined at the top of the file, then removes the
+/// ones that aren't referenced anymore.
+pub fn inline(items: Vec<Item>) -> Vec<Item> {
+	let mut inliner = Inliner {
+		candidates: candidates(&items),
+		locals: HashSet::new(),
+		counter: 0,
+	};
+
+	if inliner.candidates.is_empty() {
+		return items;
+	}
+
+	let mut items: Vec<Item> = items.into_iter().map(|item| inliner.fold_item(item)).collect();
+
+	// Removing a function can leave another one unused.
+	loop {
+		let before = items.len();
+		let referenced = names(&items).referenced;
+
+		items.retain(|item| match &item.kind {
+			ItemKind::FunctionDefinition { name, .. } => {
+				!inliner.candidates.contains_key(name) || referenced.contains(name)
+			}
+			_ => true,
+		});
+
+		if items.len() == before {
+			return items;
+		}
+	}
+}
+
+struct Candidate {
+	params: Vec<String>,
+	stmts: Vec<Item>,
+
+	// Names declared inside of the body, not counting parameters, which get renamed when inlining.
+	declared: HashSet<String>,
+	// Names the body refers to but doesn't declare, which must mean the same thing at the call site.
+	free: HashSet<String>,
+	// Names the body assigns to.
+	assigned: HashSet<String>,
+}
+
+impl Candidate {
+	/// The expression returned by a function whose body is only `return expr`.
+	fn tail(&self) -> Option<&Item> {
+		match self.stmts.as_slice() {
+			[Item { kind: ItemKind::Return { expr: Some(expr) }, .. }] => Some(expr),
+			_ => None,
+		}
+	}
+
+	/// Whether the argument can replace the parameter directly, instead of needing a local.
+	fn substitutes(&self, param: &str, arg: &Item) -> bool {
+		match &arg.kind {
+			_ if self.assigned.contains(param) || self.declared.contains(param) => false,
+			ItemKind::ExprInteger(..) | ItemKind::ExprDecimal(_) | ItemKind::ExprString(_) | ItemKind::ExprBool(_) => true,
+			// The variable could change while the body runs, and the parameter wouldn't.
+			ItemKind::ExprIdent(name) => !self.assigned.contains(name),
+			_ => false,
+		}
+	}
+}
+
+fn candidates(items: &[Item]) -> HashMap<String, Candidate> {
+	let mut calls = HashMap::new();
+	let mut candidates = HashMap::new();
+
+	for item in items {
+		let ItemKind::FunctionDefinition { name, public, params, stmts } = &item.kind else {
+			continue;
+		};
+
+		let names = names(stmts);
+		calls.insert(name.as_str(), names.referenced.clone());
+
+		// main is called by whatever runs the script.
+		if *public || name == "main" || size(stmts) > MAX_SIZE {
+			continue;
+		}
+
+		// Bodies are either spliced in place of a call statement, or are a single returned expression.
+		let returns = stmts.iter().any(|stmt| contains(stmt, |kind| matches!(kind, ItemKind::Return { .. })));
+		let candidate = Candidate {
+			params: params.clone(),
+			stmts: stmts.clone(),
+			free: names.referenced.difference(&names.declared).filter(|n| !params.contains(n)).cloned().collect(),
+			declared: names.declared,
+			assigned: names.assigned,
+		};
+
+		let nested = stmts.iter().any(|stmt| contains(stmt, |kind| matches!(kind, ItemKind::FunctionDefinition { .. })));
+		// `?` returns from the function it's written in, which would become the caller.
+		let tries = stmts.iter().any(|stmt| contains(stmt, |kind| matches!(kind, ItemKind::ExprTry(_))));
+		if (!returns || candidate.tail().is_some()) && !nested && !tries {
+			candidates.insert(name.clone(), candidate);
+		}
+	}
+
+	// Recursive functions would be inlined forever.
+	let recursive: Vec<String> = candidates
+		.keys()
+		.filter(|name| {
+			let mut stack: Vec<&str> = calls[name.as_str()].iter().map(String::as_str).collect();
+			let mut seen = HashSet::new();
+
+			while let Some(callee) = stack.pop() {
+				if callee == name.as_str() {
+					return true;
+				}
+
+				if seen.insert(callee) {
+					if let Some(refs) = calls.get(callee) {
+						stack.extend(refs.iter().map(String::as_str));
+					}
+				}
+			}
+
+			false
+		})
+		.cloned()
+		.collect();
+
+	for name in recursive {
+		candidates.remove(&name);
+	}
+
+	candidates
+}
+
+struct Inliner {
+	candidates: HashMap<String, Candidate>,
+
+	// Every name declared in the function being folded, which the inlined code mustn't refer to.
+	locals: HashSet<String>,
+	counter: usize,
+}
+
+impl Inliner {
+	fn candidate(&self, item: &Item) -> Option<(&String, &Candidate)> {
+		let ItemKind::ExprCall { func, args } = &item.kind else {
+			return None;
+		};
+
+		let ItemKind::ExprIdent(name) = &func.kind else {
+			return None;
+		};
+
+		let (name, candidate) = self.candidates.get_key_value(name)?;
+		let shadowed = self.locals.contains(name) || !candidate.free.is_disjoint(&self.locals);
+
+		(!shadowed && candidate.params.len() == args.len()).then_some((name, candidate))
+	}
+
+	fn fresh(&mut self, name: &str) -> String {
+		self.counter += 1;
+		format!("__{name}_{}", self.counter)
+	}
+
+	/// Renames the body's locals and binds its parameters.
+	/// Arguments that can't be substituted are declared as locals, which are returned alongside the body.
+	fn expand(&mut self, call: &Item, span: Option<Span>) -> (Vec<Item>, Vec<Item>) {
+		let (name, candidate) = self.candidate(call).unwrap();
+		let ItemKind::ExprCall { args, .. } = &call.kind else { unreachable!() };
+
+		let (name, params, stmts, declared) = (
+			name.clone(),
+			candidate.params.clone(),
+			candidate.stmts.clone(),
+			candidate.declared.clone(),
+		);
+		let substitutes: Vec<bool> = params.iter().zip(args).map(|(p, a)| candidate.substitutes(p, a)).collect();
+
+		// Sorted, so the generated names don't depend on hash order.
+		let mut declared: Vec<String> = declared.into_iter().collect();
+		declared.sort();
+
+		let mut rename = Rename { map: HashMap::new() };
+		for name in &declared {
+			let fresh = self.fresh(name);
+			rename.map.insert(name.clone(), Item::new(ItemKind::ExprIdent(fresh), span));
+		}
+
+		let mut bindings = vec![];
+		for ((param, arg), substitute) in params.iter().zip(args).zip(substitutes) {
+			if substitute {
+				rename.map.insert(param.clone(), arg.clone());
+			} else {
+				let fresh = self.fresh(&format!("{name}_{param}"));
+				bindings.push(Item::new(ItemKind::VarDecl { name: fresh.clone(), expr: Box::new(arg.clone()) }, span));
+				rename.map.insert(param.clone(), Item::new(ItemKind::ExprIdent(fresh), span));
+			}
+		}
+
+		let mut stmts = stmts;
+		rename.visit_stmts_mut(&mut stmts);
+		(bindings, stmts)
+	}
+
+	/// Inlines a call at the root of a statement, where arguments can be bound to locals before it.
+	fn inline_stmt(&mut self, stmt: Item) -> Vec<Item> {
+		let span = stmt.span;
+
+		let call = match &stmt.kind {
+			ItemKind::ExprCall { .. } => &stmt,
+			ItemKind::VarDecl { expr, .. } | ItemKind::VarSet { expr, .. } => expr,
+			ItemKind::Return { expr: Some(expr) } => expr,
+			_ => return vec![stmt],
+		};
+
+		match self.candidate(call) {
+			Some((_, candidate)) if candidate.tail().is_some() || std::ptr::eq(call, &stmt) => (),
+			_ => return vec![stmt],
+		}
+
+		let (mut out, mut body) = self.expand(call, span);
+		let tail = match body.pop() {
+			Some(Item { kind: ItemKind::Return { expr: Some(expr) }, .. }) if body.is_empty() => Some(*expr),
+			last => {
+				body.extend(last);
+				None
+			}
+		};
+
+		match (stmt.kind, tail) {
+			(ItemKind::ExprCall { .. }, Some(tail)) if matches!(tail.kind, ItemKind::ExprCall { .. } | ItemKind::ExprBuiltinCall { .. }) => {
+				out.push(tail)
+			}
+			// Only called for its side effects, but the value still has to go somewhere to be valid Lua.
+			(ItemKind::ExprCall { .. }, Some(tail)) => {
+				let name = self.fresh("unused");
+				out.push(Item::new(ItemKind::VarDecl { name, expr: Box::new(tail) }, span));
+			}
+			(ItemKind::ExprCall { .. }, None) => out.extend(body),
+			(ItemKind::VarDecl { name, .. }, Some(tail)) => {
+				out.push(Item::new(ItemKind::VarDecl { name, expr: Box::new(tail) }, span))
+			}
+			(ItemKind::VarSet { name, .. }, Some(tail)) => {
+				out.push(Item::new(ItemKind::VarSet { name, expr: Box::new(tail) }, span))
+			}
+			(ItemKind::Return { .. }, Some(tail)) => {
+				out.push(Item::new(ItemKind::Return { expr: Some(Box::new(tail)) }, span))
+			}
+			_ => unreachable!(),
+		}
+
+		// The body can call other small functions.
+		self.fold_stmts(out)
+	}
+}
+
+impl Fold for Inliner {
+	fn fold_item(&mut self, item: Item) -> Item {
+		if let ItemKind::FunctionDefinition { params, stmts, .. } = &item.kind {
+			let mut locals = names(stmts).declared;
+			locals.extend(params.iter().cloned());
+			self.locals = locals;
+		}
+
+		let item = match item.kind {
+			// The last statement is the block's value, so it can only be inlined as an expression.
+			ItemKind::Block { mut stmts } if stmts.last().is_some_and(|s| s.kind.is_expr()) => {
+				let tail = stmts.pop().map(|tail| self.fold_item(tail));
+				let mut stmts = self.fold_stmts(stmts);
+				stmts.extend(tail);
+				return Item::new(ItemKind::Block { stmts }, item.span);
+			}
+			kind => visit::fold_children(self, Item::new(kind, item.span)),
+		};
+
+		// Calls inside of expressions can only be inlined if nothing needs to be declared first.
+		let substitutable = self.candidate(&item).is_some_and(|(_, candidate)| {
+			let ItemKind::ExprCall { args, .. } = &item.kind else { unreachable!() };
+			candidate.tail().is_some() && candidate.params.iter().zip(args).all(|(p, a)| candidate.substitutes(p, a))
+		});
+
+		if substitutable {
+			let (_, body) = self.expand(&item, item.span);
+			if let Some(Item { kind: ItemKind::Return { expr: Some(expr) }, .. }) = body.into_iter().next() {
+				return self.fold_item(Item::new(expr.kind, item.span));
+			}
+		}
+
+		item
+	}
+
+	fn fold_stmt(&mut self, stmt: Item) -> Vec<Item> {
+		// A call statement can't be replaced with an expression, so it's left for inline_stmt.
+		let stmt = match stmt.kind {
+			ItemKind::ExprCall { .. } => visit::fold_children(self, stmt),
+			_ => self.fold_item(stmt),
+		};
+
+		self.inline_stmt(stmt)
+	}
+}
+
+/// Replaces identifiers, and renames declarations and assignments along with them.
+struct Rename {
+	map: HashMap<String, Item>,
+}
+
+impl Rename {
+	fn rename(&self, name: &mut String) {
+		if let Some(Item { kind: ItemKind::ExprIdent(new), .. }) = self.map.get(name) {
+			*name = new.clone();
+		}
+	}
+}
+
+impl VisitMut for Rename {
+	fn visit_item_mut(&mut self, item: &mut Item) {
+		if let ItemKind::ExprIdent(name) = &item.kind {
+			if let Some(new) = self.map.get(name) {
+				item.kind = new.kind.clone();
+				return;
+			}
+		}
+
+		visit::walk_item_mut(self, item);
+	}
+
+	fn visit_var_decl_mut(&mut self, name: &mut String, expr: &mut Item) {
+		self.rename(name);
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_let_mut(&mut self, pattern: &mut Pattern, expr: Option<&mut Item>, else_stmts: Option<&mut Vec<Item>>) {
+		for name in pattern.bindings_mut() {
+			self.rename(name);
+		}
+		if let Some(expr) = expr {
+			self.visit_item_mut(expr);
+		}
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_if_let_mut(
+		&mut self,
+		pattern: &mut Pattern,
+		expr: &mut Item,
+		stmts: &mut Vec<Item>,
+		else_stmts: Option<&mut Vec<Item>>,
+	) {
+		for name in pattern.bindings_mut() {
+			self.rename(name);
+		}
+		self.visit_item_mut(expr);
+		self.visit_stmts_mut(stmts);
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_var_set_mut(&mut self, name: &mut String, expr: &mut Item) {
+		self.rename(name);
+		self.visit_item_mut(expr);
+	}
+
+	fn visit_for_in_mut(&mut self, var: &mut String, expr: &mut Item, stmts: &mut Vec<Item>) {
+		self.rename(var);
+		self.visit_item_mut(expr);
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_for_range_mut(
+		&mut self,
+		var: &mut String,
+		min: &mut Item,
+		max: &mut Item,
+		jump: Option<&mut Item>,
+		stmts: &mut Vec<Item>,
+	) {
+		self.rename(var);
+		self.visit_item_mut(min);
+		self.visit_item_mut(max);
+		if let Some(jump) = jump {
+			self.visit_item_mut(jump);
+		}
+		self.visit_stmts_mut(stmts);
+	}
+
+	fn visit_expr_closure_mut(&mut self, params: &mut Vec<String>, stmts: &mut Vec<Item>) {
+		for param in params {
+			self.rename(param);
+		}
+		self.visit_stmts_mut(stmts);
+	}
+}
+
+#[derive(Default)]
+struct Names {
+	declared: HashSet<String>,
+	referenced: HashSet<String>,
+	assigned: HashSet<String>,
+}
+
+impl Visit for Names {
+	fn visit_expr_ident(&mut self, name: &str) {
+		self.referenced.insert(name.to_owned());
+	}
+
+	fn visit_var_set(&mut self, name: &str, expr: &Item) {
+		self.referenced.insert(name.to_owned());
+		self.assigned.insert(name.to_owned());
+		self.visit_item(expr);
+	}
+
+	fn visit_var_decl(&mut self, name: &str, expr: &Item) {
+		self.declared.insert(name.to_owned());
+		self.visit_item(expr);
+	}
+
+	// Assigning to a field or element changes the variable it's in, so an argument can't be copied in its place.
+	fn visit_assign(&mut self, place: &Item, _op: Option<&BinaryOp>, expr: &Item) {
+		let mut root = place;
+		while let ItemKind::ExprField { expr, .. } | ItemKind::ExprIndex { expr, .. } = &root.kind {
+			root = expr;
+		}
+		if let ItemKind::ExprIdent(name) = &root.kind {
+			self.assigned.insert(name.clone());
+		}
+
+		self.visit_item(place);
+		self.visit_item(expr);
+	}
+
+	fn visit_let(&mut self, pattern: &Pattern, expr: Option<&Item>, else_stmts: Option<&[Item]>) {
+		self.declared.extend(pattern.bindings().into_iter().cloned());
+		if let Some(expr) = expr {
+			self.visit_item(expr);
+		}
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_if_let(&mut self, pattern: &Pattern, expr: &Item, stmts: &[Item], else_stmts: Option<&[Item]>) {
+		self.declared.extend(pattern.bindings().into_iter().cloned());
+		self.visit_item(expr);
+		self.visit_stmts(stmts);
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_for_in(&mut self, var: &str, expr: &Item, stmts: &[Item]) {
+		self.declared.insert(var.to_owned());
+		self.visit_item(expr);
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_for_range(&mut self, var: &str, min: &Item, max: &Item, jump: Option<&Item>, stmts: &[Item]) {
+		self.declared.insert(var.to_owned());
+		self.visit_item(min);
+		self.visit_item(max);
+		if let Some(jump) = jump {
+			self.visit_item(jump);
+		}
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_expr_closure(&mut self, params: &[String], stmts: &[Item]) {
+		self.declared.extend(params.iter().cloned());
+		self.visit_stmts(stmts);
+	}
+}
+
+fn names(stmts: &[Item]) -> Names {
+	let mut names = Names::default();
+	names.visit_stmts(stmts);
+	names
+}
+
+fn size(stmts: &[Item]) -> usize {
+	struct Size(usize);
+
+	impl Visit for Size {
+		fn visit_item(&mut self, item: &Item) {
+			self.0 += 1;
+			visit::walk_item(self, item);
+		}
+	}
+
+	let mut size = Size(0);
+	size.visit_stmts(stmts);
+	size.0
+}
+
+/// Whether the item or anything inside of it matches, not counting closures which have their own returns.
+fn contains(item: &Item, f: fn(&ItemKind) -> bool) -> bool {
+	struct Contains(fn(&ItemKind) -> bool, bool);
+
+	impl Visit for Contains {
+		fn visit_item(&mut self, item: &Item) {
+			self.1 |= (self.0)(&item.kind);
+			visit::walk_item(self, item);
+		}
+
+		fn visit_expr_closure(&mut self, _params: &[String], _stmts: &[Item]) {}
+	}
+
+	let mut v = Contains(f, false);
+	v.visit_item(item);
+	v.1
+}
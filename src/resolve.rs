@@ -0,0 +1,392 @@
+//! Name resolution.
+//!
+//! The backend lowers paths to identifiers separated by `::`. [resolve] checks that every name refers to something,
+//! and rewrites references to module items with their full path from the crate root, so `test()` inside of `mod a`
+//! becomes `a::test()`. Functions and modules are renamed the same way, leaving it up to each generator to turn
+//! those paths into table fields or mangled names.
+//!
+//! Names that aren't found in the current module are looked up from the crate root, which also makes resolving
+//! twice harmless. Locals keep their names, and functions declared inside of blocks aren't qualified.
+//! Paths starting with `::` belong to the host, and are left as they are.
+//!
+//! The names of [Builtin]s are always defined. Calls of them become [ItemKind::ExprBuiltinCall]s, unless a local
+//! or item of the same name hides the builtin.
+
+use std::collections::HashMap;
+
+use crate::backend::visit::{self, VisitMut};
+use crate::backend::{Builtin, Item, ItemKind, Pattern, Span};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+	#[error("Unresolved name `{name}`")]
+	Unresolved { name: String, span: Option<Span> },
+
+	#[error("Expected a value, found module `{name}`")]
+	Module { name: String, span: Option<Span> },
+}
+
+impl ResolveError {
+	pub fn span(&self) -> Option<Span> {
+		match self {
+			Self::Unresolved { span, .. } | Self::Module { span, .. } => *span,
+		}
+	}
+}
+
+/// Resolves every name in a file, returning all of the names that couldn't be.
+pub fn resolve(mut items: Vec<Item>) -> Result<Vec<Item>, Vec<ResolveError>> {
+	let mut resolver = Resolver {
+		modules: vec![Module::default()],
+		module: 0,
+		frames: vec![],
+		errors: vec![],
+	};
+
+	resolver.collect(&items, 0);
+	for item in &mut items {
+		resolver.visit_item_mut(item);
+	}
+
+	if resolver.errors.is_empty() {
+		Ok(items)
+	} else {
+		Err(resolver.errors)
+	}
+}
+
+/// Last segment of a path, so already resolved items can be resolved again.
+fn last_segment(name: &str) -> &str {
+	name.rsplit("::").next().unwrap_or(name)
+}
+
+#[derive(Default)]
+struct Module {
+	path: Vec<String>,
+	parent: Option<usize>,
+	defs: HashMap<String, Def>,
+}
+
+enum Def {
+	/// Resolved name of the function.
+	Function(String),
+	Module(usize),
+	Extern,
+}
+
+enum Binding {
+	Local,
+	/// A function declared in a block, visible in all of it.
+	Item,
+}
+
+struct Frame {
+	names: HashMap<String, Binding>,
+	// Locals outside of a function aren't visible from inside of it.
+	function: bool,
+}
+
+struct Resolver {
+	modules: Vec<Module>,
+	module: usize,
+	frames: Vec<Frame>,
+	errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+	fn qualify(&self, module: usize, name: &str) -> String {
+		let mut path = self.modules[module].path.clone();
+		path.push(name.to_owned());
+		path.join("::")
+	}
+
+	/// Builds the symbol table of every module before resolving anything, since items can be used before they're declared.
+	fn collect(&mut self, items: &[Item], module: usize) {
+		for item in items {
+			match &item.kind {
+				ItemKind::FunctionDefinition { name, .. } => {
+					let name = last_segment(name);
+					let def = Def::Function(self.qualify(module, name));
+					self.modules[module].defs.insert(name.to_owned(), def);
+				}
+
+				ItemKind::Mod { name, items } => {
+					let name = last_segment(name);
+					let mut path = self.modules[module].path.clone();
+					path.push(name.to_owned());
+
+					let id = self.modules.len();
+					self.modules.push(Module { path, parent: Some(module), defs: HashMap::new() });
+					self.modules[module].defs.insert(name.to_owned(), Def::Module(id));
+					self.collect(items, id);
+				}
+
+				ItemKind::Externs { functions } => {
+					for name in functions {
+						self.modules[module].defs.insert(name.to_owned(), Def::Extern);
+					}
+				}
+
+				_ => (),
+			}
+		}
+	}
+
+	fn error(&mut self, name: &str, span: Option<Span>) -> Option<String> {
+		self.errors.push(ResolveError::Unresolved { name: name.to_owned(), span });
+		None
+	}
+
+	fn def(&mut self, module: usize, name: &str, full: &str, span: Option<Span>) -> Option<String> {
+		match self.modules[module].defs.get(name) {
+			Some(Def::Function(resolved)) => Some(resolved.clone()),
+			// Host functions keep their own name.
+			Some(Def::Extern) => Some(name.to_owned()),
+			Some(Def::Module(_)) => {
+				self.errors.push(ResolveError::Module { name: full.to_owned(), span });
+				None
+			}
+			None => self.error(full, span),
+		}
+	}
+
+	/// The resolved name of a value, or None if it's an error.
+	fn value(&mut self, name: &str, span: Option<Span>) -> Option<String> {
+		// Host paths, like the functions user macros are lowered to.
+		if name.starts_with("::") {
+			return Some(name.to_owned());
+		}
+
+		let mut segments: Vec<&str> = name.split("::").collect();
+		let last = segments.pop().unwrap();
+
+		if segments.is_empty() {
+			let mut crossed = false;
+			for frame in self.frames.iter().rev() {
+				match frame.names.get(name) {
+					Some(Binding::Local) if !crossed => return Some(name.to_owned()),
+					Some(Binding::Item) => return Some(name.to_owned()),
+					_ => (),
+				}
+
+				crossed |= frame.function;
+			}
+
+			if self.builtin(name).is_some() {
+				return Some(name.to_owned());
+			}
+
+			let module = self.fallback(name);
+
+			return self.def(module, name, name, span);
+		}
+
+		let mut module = self.fallback(segments[0]);
+		for (i, segment) in segments.into_iter().enumerate() {
+			module = match segment {
+				"crate" if i == 0 => 0,
+				"self" if i == 0 => self.module,
+				"super" if i == 0 => match self.modules[self.module].parent {
+					Some(parent) => parent,
+					None => return self.error(name, span),
+				},
+				"super" => match self.modules[module].parent {
+					Some(parent) => parent,
+					None => return self.error(name, span),
+				},
+				_ => match self.modules[module].defs.get(segment) {
+					Some(Def::Module(id)) => *id,
+					_ => return self.error(name, span),
+				},
+			};
+		}
+
+		self.def(module, last, name, span)
+	}
+
+	/// The builtin `name` refers to, unless a local or item of the same name hides it.
+	fn builtin(&self, name: &str) -> Option<Builtin> {
+		let builtin = Builtin::parse(name)?;
+
+		let mut crossed = false;
+		for frame in self.frames.iter().rev() {
+			match frame.names.get(name) {
+				Some(Binding::Local) if !crossed => return None,
+				Some(Binding::Item) => return None,
+				_ => (),
+			}
+
+			crossed |= frame.function;
+		}
+
+		match self.modules[self.fallback(name)].defs.contains_key(name) {
+			true => None,
+			false => Some(builtin),
+		}
+	}
+
+	/// The current module if it has the name, otherwise the crate root.
+	fn fallback(&self, name: &str) -> usize {
+		if self.modules[self.module].defs.contains_key(name) {
+			self.module
+		} else {
+			0
+		}
+	}
+
+	fn declare(&mut self, name: &str, binding: Binding) {
+		if let Some(frame) = self.frames.last_mut() {
+			frame.names.insert(name.to_owned(), binding);
+		}
+	}
+
+	fn push(&mut self, function: bool, names: &[String]) {
+		let names = names.iter().map(|name| (name.clone(), Binding::Local)).collect();
+		self.frames.push(Frame { names, function });
+	}
+}
+
+impl VisitMut for Resolver {
+	fn visit_item_mut(&mut self, item: &mut Item) {
+		let builtin = match &item.kind {
+			ItemKind::ExprCall { func, .. } => match &func.kind {
+				ItemKind::ExprIdent(name) => self.builtin(name),
+				_ => None,
+			},
+			_ => None,
+		};
+		if let (Some(builtin), ItemKind::ExprCall { args, .. }) = (builtin, &mut item.kind) {
+			item.kind = ItemKind::ExprBuiltinCall { builtin, args: std::mem::take(args) };
+		}
+
+		match &mut item.kind {
+			ItemKind::ExprIdent(name) | ItemKind::VarSet { name, .. } => {
+				if let Some(resolved) = self.value(name, item.span) {
+					*name = resolved;
+				}
+			}
+			_ => (),
+		}
+
+		visit::walk_item_mut(self, item);
+	}
+
+	fn visit_stmts_mut(&mut self, stmts: &mut Vec<Item>) {
+		self.push(false, &[]);
+
+		for stmt in stmts.iter() {
+			if let ItemKind::FunctionDefinition { name, .. } = &stmt.kind {
+				self.declare(name, Binding::Item);
+			}
+		}
+
+		for stmt in stmts {
+			self.visit_item_mut(stmt);
+		}
+
+		self.frames.pop();
+	}
+
+	fn visit_function_definition_mut(
+		&mut self,
+		name: &mut String,
+		_public: &mut bool,
+		params: &mut Vec<String>,
+		stmts: &mut Vec<Item>,
+	) {
+		if self.frames.is_empty() {
+			*name = self.qualify(self.module, last_segment(name));
+		}
+
+		self.push(true, params);
+		self.visit_stmts_mut(stmts);
+		self.frames.pop();
+	}
+
+	fn visit_mod_mut(&mut self, name: &mut String, items: &mut Vec<Item>) {
+		let Some(Def::Module(id)) = self.modules[self.module].defs.get(last_segment(name)) else {
+			return;
+		};
+
+		let parent = std::mem::replace(&mut self.module, *id);
+		*name = self.modules[self.module].path.join("::");
+
+		for item in items {
+			self.visit_item_mut(item);
+		}
+
+		self.module = parent;
+	}
+
+	fn visit_var_decl_mut(&mut self, name: &mut String, expr: &mut Item) {
+		self.visit_item_mut(expr);
+		self.declare(name, Binding::Local);
+	}
+
+	// Struct paths in patterns only describe the shape of the value, so they're left as written.
+	fn visit_let_mut(&mut self, pattern: &mut Pattern, expr: Option<&mut Item>, else_stmts: Option<&mut Vec<Item>>) {
+		if let Some(expr) = expr {
+			self.visit_item_mut(expr);
+		}
+		// The bindings aren't in scope in the `else`, which has to diverge.
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+
+		for name in pattern.bindings() {
+			self.declare(name, Binding::Local);
+		}
+	}
+
+	fn visit_if_let_mut(
+		&mut self,
+		pattern: &mut Pattern,
+		expr: &mut Item,
+		stmts: &mut Vec<Item>,
+		else_stmts: Option<&mut Vec<Item>>,
+	) {
+		self.visit_item_mut(expr);
+
+		let names = pattern.bindings().into_iter().cloned().collect::<Vec<_>>();
+		self.push(false, &names);
+		self.visit_stmts_mut(stmts);
+		self.frames.pop();
+
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_for_in_mut(&mut self, var: &mut String, expr: &mut Item, stmts: &mut Vec<Item>) {
+		self.visit_item_mut(expr);
+
+		self.push(false, std::slice::from_ref(var));
+		self.visit_stmts_mut(stmts);
+		self.frames.pop();
+	}
+
+	fn visit_for_range_mut(
+		&mut self,
+		var: &mut String,
+		min: &mut Item,
+		max: &mut Item,
+		jump: Option<&mut Item>,
+		stmts: &mut Vec<Item>,
+	) {
+		self.visit_item_mut(min);
+		self.visit_item_mut(max);
+		if let Some(jump) = jump {
+			self.visit_item_mut(jump);
+		}
+
+		self.push(false, std::slice::from_ref(var));
+		self.visit_stmts_mut(stmts);
+		self.frames.pop();
+	}
+
+	fn visit_expr_closure_mut(&mut self, params: &mut Vec<String>, stmts: &mut Vec<Item>) {
+		self.push(false, params);
+		self.visit_stmts_mut(stmts);
+		self.frames.pop();
+	}
+}
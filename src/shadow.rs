@@ -0,0 +1,282 @@
+//! Renaming of shadowed bindings.
+//!
+//! Rust lets a `let` shadow any binding, including one a closure has already captured, and locals live in a
+//! different namespace than modules, so `let shapes = 1; shapes::area()` is fine. [unshadow] gives every
+//! binding that shadows another one its own name, so generators can treat names as unique within a function
+//! and can't accidentally refer to a local where Rust meant a module.
+//!
+//! It runs after [crate::resolve], since it relies on module items having their full path.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::backend::visit::{self, Visit, VisitMut};
+use crate::backend::{Item, ItemKind, Pattern};
+
+/// Renames locals, parameters and functions declared in blocks that shadow something, as `{name}_{n}`.
+pub fn unshadow(mut items: Vec<Item>) -> Vec<Item> {
+	let mut names = Names::default();
+	names.visit_stmts(&items);
+
+	// Items of the crate root, which generated code may refer to from anywhere.
+	let binding = |name: &String| (name.clone(), Binding { name: name.clone(), local: false });
+	let mut root = HashMap::new();
+	for item in &items {
+		match &item.kind {
+			ItemKind::FunctionDefinition { name, .. } | ItemKind::Mod { name, .. } => root.extend([binding(name)]),
+			ItemKind::Externs { functions } => root.extend(functions.iter().map(binding)),
+			_ => (),
+		}
+	}
+
+	let mut unshadow = Unshadow { frames: vec![root], barrier: 0, taken: names.0 };
+	for item in &mut items {
+		unshadow.visit_item_mut(item);
+	}
+
+	items
+}
+
+/// Every name in the file, so renamed bindings can't collide with any of them.
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Visit for Names {
+	fn visit_function_definition(&mut self, name: &str, _public: bool, params: &[String], stmts: &[Item]) {
+		self.0.insert(name.to_owned());
+		self.0.extend(params.iter().cloned());
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_for_in(&mut self, var: &str, expr: &Item, stmts: &[Item]) {
+		self.0.insert(var.to_owned());
+		self.visit_item(expr);
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_for_range(&mut self, var: &str, min: &Item, max: &Item, jump: Option<&Item>, stmts: &[Item]) {
+		self.0.insert(var.to_owned());
+		self.visit_item(min);
+		self.visit_item(max);
+		if let Some(jump) = jump {
+			self.visit_item(jump);
+		}
+		self.visit_stmts(stmts);
+	}
+
+	fn visit_var_set(&mut self, name: &str, expr: &Item) {
+		self.0.insert(name.to_owned());
+		self.visit_item(expr);
+	}
+
+	fn visit_var_decl(&mut self, name: &str, expr: &Item) {
+		self.0.insert(name.to_owned());
+		self.visit_item(expr);
+	}
+
+	fn visit_let(&mut self, pattern: &Pattern, expr: Option<&Item>, else_stmts: Option<&[Item]>) {
+		self.0.extend(pattern.bindings().into_iter().cloned());
+		if let Some(expr) = expr {
+			self.visit_item(expr);
+		}
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_if_let(&mut self, pattern: &Pattern, expr: &Item, stmts: &[Item], else_stmts: Option<&[Item]>) {
+		self.0.extend(pattern.bindings().into_iter().cloned());
+		self.visit_item(expr);
+		self.visit_stmts(stmts);
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts(stmts);
+		}
+	}
+
+	fn visit_externs(&mut self, functions: &[String]) {
+		self.0.extend(functions.iter().cloned());
+	}
+
+	fn visit_mod(&mut self, name: &str, items: &[Item]) {
+		self.0.insert(name.to_owned());
+		self.visit_stmts(items);
+	}
+
+	fn visit_expr_ident(&mut self, name: &str) {
+		self.0.insert(name.to_owned());
+	}
+
+	fn visit_expr_closure(&mut self, params: &[String], stmts: &[Item]) {
+		self.0.extend(params.iter().cloned());
+		self.visit_stmts(stmts);
+	}
+}
+
+struct Binding {
+	name: String,
+	local: bool,
+}
+
+struct Unshadow {
+	// Original names of the bindings. The first frame holds the crate root.
+	frames: Vec<HashMap<String, Binding>>,
+	// Locals in frames before this one belong to an enclosing function, and aren't visible.
+	barrier: usize,
+	taken: HashSet<String>,
+}
+
+impl Unshadow {
+	fn declare(&mut self, name: &mut String, local: bool) {
+		if name == "_" {
+			return;
+		}
+
+		if self.lookup(name).is_some() {
+			let mut n = 1;
+			while self.taken.contains(&format!("{name}_{n}")) {
+				n += 1;
+			}
+
+			let renamed = format!("{name}_{n}");
+			self.taken.insert(renamed.clone());
+			let original = std::mem::replace(name, renamed.clone());
+			self.frames.last_mut().unwrap().insert(original, Binding { name: renamed, local });
+		} else {
+			self.frames.last_mut().unwrap().insert(name.clone(), Binding { name: name.clone(), local });
+		}
+	}
+
+	fn lookup(&self, name: &str) -> Option<&String> {
+		let frames = self.frames.iter().enumerate().rev();
+		let mut visible = frames.filter_map(|(i, frame)| frame.get(name).filter(|b| i >= self.barrier || !b.local));
+		visible.next().map(|binding| &binding.name)
+	}
+
+	/// Renames a reference. Paths always refer to module items, never to bindings.
+	fn rename(&self, name: &mut String) {
+		if let Some(renamed) = self.lookup(name) {
+			*name = renamed.clone();
+		}
+	}
+
+	fn scoped(&mut self, names: &mut [String], f: impl FnOnce(&mut Self)) {
+		self.frames.push(HashMap::new());
+		for name in names {
+			self.declare(name, true);
+		}
+
+		f(self);
+		self.frames.pop();
+	}
+}
+
+impl VisitMut for Unshadow {
+	fn visit_item_mut(&mut self, item: &mut Item) {
+		match &mut item.kind {
+			ItemKind::ExprIdent(name) | ItemKind::VarSet { name, .. } => self.rename(name),
+			_ => (),
+		}
+
+		visit::walk_item_mut(self, item);
+	}
+
+	fn visit_stmts_mut(&mut self, stmts: &mut Vec<Item>) {
+		self.frames.push(HashMap::new());
+
+		// Functions are visible in their whole block.
+		for stmt in stmts.iter_mut() {
+			if let ItemKind::FunctionDefinition { name, .. } = &mut stmt.kind {
+				self.declare(name, false);
+			}
+		}
+
+		for stmt in stmts {
+			self.visit_item_mut(stmt);
+		}
+
+		self.frames.pop();
+	}
+
+	fn visit_function_definition_mut(
+		&mut self,
+		_name: &mut String,
+		_public: &mut bool,
+		params: &mut Vec<String>,
+		stmts: &mut Vec<Item>,
+	) {
+		// Functions can't capture locals, only refer to the functions declared around them.
+		let barrier = std::mem::replace(&mut self.barrier, self.frames.len());
+		self.scoped(params, |this| this.visit_stmts_mut(stmts));
+		self.barrier = barrier;
+	}
+
+	fn visit_mod_mut(&mut self, _name: &mut String, items: &mut Vec<Item>) {
+		for item in items {
+			self.visit_item_mut(item);
+		}
+	}
+
+	fn visit_var_decl_mut(&mut self, name: &mut String, expr: &mut Item) {
+		self.visit_item_mut(expr);
+		self.declare(name, true);
+	}
+
+	fn visit_let_mut(&mut self, pattern: &mut Pattern, expr: Option<&mut Item>, else_stmts: Option<&mut Vec<Item>>) {
+		if let Some(expr) = expr {
+			self.visit_item_mut(expr);
+		}
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+
+		for name in pattern.bindings_mut() {
+			self.declare(name, true);
+		}
+	}
+
+	fn visit_if_let_mut(
+		&mut self,
+		pattern: &mut Pattern,
+		expr: &mut Item,
+		stmts: &mut Vec<Item>,
+		else_stmts: Option<&mut Vec<Item>>,
+	) {
+		self.visit_item_mut(expr);
+
+		self.frames.push(HashMap::new());
+		for name in pattern.bindings_mut() {
+			self.declare(name, true);
+		}
+		self.visit_stmts_mut(stmts);
+		self.frames.pop();
+
+		if let Some(stmts) = else_stmts {
+			self.visit_stmts_mut(stmts);
+		}
+	}
+
+	fn visit_for_in_mut(&mut self, var: &mut String, expr: &mut Item, stmts: &mut Vec<Item>) {
+		self.visit_item_mut(expr);
+		self.scoped(std::slice::from_mut(var), |this| this.visit_stmts_mut(stmts));
+	}
+
+	fn visit_for_range_mut(
+		&mut self,
+		var: &mut String,
+		min: &mut Item,
+		max: &mut Item,
+		jump: Option<&mut Item>,
+		stmts: &mut Vec<Item>,
+	) {
+		self.visit_item_mut(min);
+		self.visit_item_mut(max);
+		if let Some(jump) = jump {
+			self.visit_item_mut(jump);
+		}
+
+		self.scoped(std::slice::from_mut(var), |this| this.visit_stmts_mut(stmts));
+	}
+
+	fn visit_expr_closure_mut(&mut self, params: &mut Vec<String>, stmts: &mut Vec<Item>) {
+		self.scoped(params, |this| this.visit_stmts_mut(stmts));
+	}
+}
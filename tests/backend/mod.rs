@@ -0,0 +1,310 @@
+use smyth::backend::{Ast, ItemKind, Options, SourceMap};
+
+#[test]
+fn spans() -> Result<(), Box<dyn std::error::Error>> {
+	let source = "fn main() {\n\tlet x = 5;\n\tprint(x);\n}";
+
+	let mut sources = SourceMap::new();
+	let file = sources.add("spans.rs", source);
+
+	let ast = smyth::backend::Backend::generate_with(source, &Options { file, ..Default::default() })?;
+
+	let main = &ast.items()[0];
+	let span = main.span.expect("function should have a span");
+	assert_eq!(span.file, file);
+	assert_eq!(sources.snippet(&span), Some(source));
+
+	let ItemKind::FunctionDefinition { stmts, .. } = &main.kind else {
+		panic!("expected function, got {:?}", main.kind)
+	};
+
+	let span = stmts[1].span.expect("call should have a span");
+	assert_eq!(sources.snippet(&span), Some("print(x)"));
+	assert_eq!((span.lo.line, span.lo.column), (3, 1));
+
+	Ok(())
+}
+
+#[test]
+fn visitors() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{visit, Fold, Item, Visit, VisitMut};
+
+	struct Idents(Vec<String>);
+	impl Visit for Idents {
+		fn visit_expr_ident(&mut self, name: &str) {
+			self.0.push(name.to_owned());
+		}
+	}
+
+	struct Rename;
+	impl VisitMut for Rename {
+		fn visit_expr_ident_mut(&mut self, name: &mut String) {
+			name.make_ascii_uppercase();
+		}
+	}
+
+	struct NoBreaks;
+	impl Fold for NoBreaks {
+		fn fold_stmt(&mut self, stmt: Item) -> Vec<Item> {
+			match stmt.kind {
+				ItemKind::Break => vec![],
+				_ => vec![visit::fold_children(self, stmt)],
+			}
+		}
+	}
+
+	let source = "fn main() { loop { print(x); break; } }";
+	let ast = smyth::backend::Backend::generate(source)?;
+	let mut items: Vec<Item> = NoBreaks.fold_stmts(ast.items);
+
+	Rename.visit_stmts_mut(&mut items);
+
+	let mut idents = Idents(vec![]);
+	idents.visit_stmts(&items);
+	assert_eq!(idents.0, ["PRINT", "X"]);
+
+	let ItemKind::FunctionDefinition { stmts, .. } = &items[0].kind else { unreachable!() };
+	let ItemKind::While { stmts, .. } = &stmts[0].kind else { unreachable!() };
+	assert_eq!(stmts.len(), 1);
+	assert!(stmts[0].span.is_some(), "folding should keep spans");
+
+	Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+	let source = std::fs::read_to_string("tests/lua/examples/1.rs")?;
+	let ast = smyth::backend::Backend::generate(source)?;
+
+	let json = smyth::backend::to_json(ast.items())?;
+	let items = smyth::backend::from_json(&json)?;
+	assert_eq!(smyth::backend::to_json(&items)?, json);
+
+	use smyth::gen::CodeGenerator;
+	let codegen = smyth::gen::lua::LuaCodegen::new();
+	assert_eq!(
+		codegen.generate(&smyth::backend::Backend::from_items(items))?,
+		codegen.generate(&ast)?
+	);
+
+	// Literals keep their type.
+	use smyth::backend::sexpr::to_sexpr;
+	let ast = smyth::backend::Backend::generate("fn f() { g(1u8, 1, 18446744073709551615u64); }")?;
+	let items = smyth::backend::from_json(smyth::backend::to_json(ast.items())?)?;
+	assert_eq!(to_sexpr(&items), "(fn f ()\n\t(call g 1u8 1 18446744073709551615u64))\n");
+
+	Ok(())
+}
+
+#[test]
+fn sexpr() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+
+	let source = "fn main() { let x = 1.5; if x < 2 { print(\"a\\n\"); } else { f(x, [1, 2]); } }";
+	let ast = smyth::backend::Backend::generate(source)?;
+
+	let printed = to_sexpr(ast.items());
+	assert_eq!(
+		printed,
+		"(fn main ()\n\t(let x 1.5)\n\t(if (< x 2)\n\t\t(then\n\t\t\t(call print \"a\\n\"))\n\t\t(else\n\t\t\t(call f x (array 1 2)))))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let err = from_sexpr("(fn main ()\n\t(let x))").unwrap_err();
+	assert_eq!(err.line, 2);
+
+	Ok(())
+}
+
+#[test]
+fn format_strings() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::format::{self, Align, Argument, Count, FormatKind};
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+
+	let (strings, args) = format::parse("{{x}} {:>8.2} {name:#x} {:.*}!", |arg| match arg {
+		Argument::Index(i) => Ok(i),
+		Argument::Name("name") => Ok(9),
+		Argument::Name(name) => Err(format::FormatError(format!("unknown {name}"))),
+	})?;
+	assert_eq!(strings, ["{x} ", " ", " ", "!"]);
+	assert_eq!(args.iter().map(|arg| arg.value).collect::<Vec<_>>(), [0, 9, 2]);
+	assert_eq!(args[0].spec.align, Some(Align::Right));
+	assert_eq!((args[0].spec.width, args[0].spec.precision), (Some(Count::Is(8)), Some(Count::Is(2))));
+	assert!(args[1].spec.alternate && args[1].spec.kind == FormatKind::LowerHex);
+	assert_eq!(args[2].spec.precision, Some(Count::Arg(1)));
+	assert!(format::parse("{:q}", |_| Ok(0)).is_err());
+	assert!(format::parse("}", |_| Ok(0)).is_err());
+
+	let source = r#"fn main() { let n = 1; let s = format!("{n:>4} {0:?} {x}", "a", x = 2); }"#;
+	let ast = smyth::backend::Backend::generate(source)?;
+	let printed = to_sexpr(ast.items());
+	assert!(printed.contains(r#"(fstring ("" " " " " "") ("{2:>4}" "{0:?}" "{1}") "a" 2 n)"#), "{printed}");
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let err = smyth::backend::Backend::generate(r#"fn main() { let s = format!("{}", 1, 2); }"#).unwrap_err();
+	assert!(err.to_string().contains("argument never used"), "{err}");
+
+	Ok(())
+}
+
+#[test]
+fn patterns() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+
+	let source = "fn f((a, _): (i32, i32)) { let Point { x, y: [first, ..] } = p; let z; let _ = g(); }";
+	let ast = smyth::backend::Backend::generate(source)?;
+
+	let printed = to_sexpr(ast.items());
+	assert_eq!(
+		printed,
+		"(fn f ($__arg0)\n\t(let (tuple a _) $__arg0)\n\t(let (struct Point (x x) (y (slice first ..))) p)\n\t(declare z)\n\t(let _ (call g)))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let err = smyth::backend::Backend::generate("fn main() { let (1, x) = (1, 2); }").unwrap_err();
+	assert!(err.to_string().contains("refutable"), "{err}");
+
+	let source = "fn f(v: [i32; 2]) { if let [-1 | 2, n] = v { g(n); } else if v == 0 {} let (true, s) = h() else { return; }; }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(
+		printed,
+		"(fn f (v)\n\t(if-let (slice (or -1 2) n) v\n\t\t(then\n\t\t\t(call g n))\n\t\t(else\n\t\t\t(if (== v 0)\n\t\t\t\t(then))))\n\t(let (tuple true s) (call h)\n\t\t(else\n\t\t\t(return))))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let err = smyth::backend::Backend::generate("fn main() { if let (1, x) | (x, 2) = (1, 2) {} }").unwrap_err();
+	assert!(err.to_string().contains("can't bind"), "{err}");
+
+	Ok(())
+}
+
+#[test]
+fn assignments() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+
+	let source = "fn f(r: &mut Point, a: [i32; 2]) { (*r).x = 1; r.y >>= 2; a[g()] %= 3; n = 4; n -= 1; }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(
+		printed,
+		"(fn f (r a)\n\t(set (field r x) 1)\n\t(>>= (field r y) 2)\n\t(%= (index a (call g)) 3)\n\t(set n 4)\n\t(set n (- n 1)))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let err = smyth::backend::Backend::generate("fn f(x: &mut i32) { *x += 1; }").unwrap_err();
+	assert!(err.to_string().contains("through a reference"), "{err}");
+
+	Ok(())
+}
+
+#[test]
+fn unary_operators() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+
+	let source = "fn f(n: &u32, b: bool) { let m = 3; g(-*n, !n, !b, !m, !(m > 1), !h(), &mut -2.5); }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(
+		printed,
+		"(fn f (n b)\n\t(let m 3)\n\t(call g (neg n) (wrap u32 (bnot n)) (not b) (bnot m) (not (> m 1)) (not (call h)) -2.5))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	Ok(())
+}
+
+#[test]
+fn casts() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+	use smyth::backend::Options;
+
+	let source = "fn f(x: f64, n: i32) { g(x as u8, n as u8, n as usize, n as f32, true as i32, 18446744073709551615u64); }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(printed, "(fn f (x n)\n\t(call g (as u8 x) (wrap u8 n) n (as f32 n) (as i32 true) 18446744073709551615u64))\n");
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let source = "fn f(x: f64, n: u32, v: [i32; 2]) { g(x / 2.0, n / 2, 7 / 2, n % 2, v[0] / v[1]); v[1] /= 3; }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(
+		printed,
+		"(fn f (x n v)\n\t(call g (/ x 2.0) (// n 2) (// 7 2) (% n 2) (/ (index v 0) (index v 1)))\n\t(//= (index v 1) 3))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let err = smyth::backend::Backend::generate("fn f(x: u8) { g(x as char); }").unwrap_err();
+	assert!(err.to_string().contains("only casts to number types"), "{err}");
+	let err = smyth::backend::Backend::generate("fn f() { g(18446744073709551616u128); }").unwrap_err();
+	assert!(err.to_string().contains("larger than `u64::MAX`"), "{err}");
+
+	let source = "fn f(a: u8, b: i64) -> u8 { let mut c = a + 1; c *= 2; let d = b * 2; c - a }";
+	let ast = smyth::backend::Backend::generate_with(source, &Options { strict_integers: true, ..Default::default() })?;
+	assert_eq!(
+		to_sexpr(ast.items()),
+		"(fn f (a b)\n\t(let c (wrap u8 (+ a 1)))\n\t(set c (wrap u8 (* c 2)))\n\t(let d (* b 2))\n\t(return (wrap u8 (- c a))))\n"
+	);
+
+	Ok(())
+}
+
+/// A variable's type is forgotten at the end of its scope, and bindings of patterns hide the type they shadow.
+#[test]
+fn scoped_types() -> Result<(), Box<dyn std::error::Error>> {
+	let source = "fn f(o: Option<f64>) -> f64 {
+		let d = h();
+		let x = 1.0;
+		{ let x = 2; }
+		let n = 5;
+		if let Some(n) = o { g(n / d); }
+		let m = 1.5;
+		for m in 0..3 {}
+		g(m / d);
+		x / 2.0
+	}";
+	let printed = smyth::backend::sexpr::to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert!(printed.contains("(call g (/ n d))"), "{printed}");
+	assert!(printed.contains("(call g (/ m d))"), "{printed}");
+	assert!(printed.contains("(return (/ x 2.0))"), "{printed}");
+
+	Ok(())
+}
+
+#[test]
+fn literals() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+	use smyth::backend::{CharRepr, Options};
+
+	let source = "fn f() { g('a', b'b', b\"hi\", 0x1F, 1_000, 0o17u8, 0.1f32, r\"\\n\", -5i32, 18446744073709551615); }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(
+		printed,
+		"(fn f ()\n\t(call g \"a\" \"b\" \"hi\" 31 1000 15u8 0.10000000149011612 \"\\\\n\" -5i32 18446744073709551615u64))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	let ast = smyth::backend::Backend::generate_with(source, &Options { chars: CharRepr::Code, ..Default::default() })?;
+	assert!(to_sexpr(ast.items()).contains("(call g 97 98u8 (array 104u8 105u8) 31"));
+
+	let err = smyth::backend::Backend::generate("fn f() { g(b\"\\xff\", b'\\x80'); }").unwrap_err();
+	assert!(err.to_string().contains("aren't UTF-8"), "{err}");
+
+	// The end of a half-open range is one less, unless that doesn't fit.
+	let source = "fn f() { for i in 1..5u8 {} for j in 0..-9223372036854775808i64 {} }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(printed, "(fn f ()\n\t(for-range i 1 4u8 _)\n\t(for-range j 0 (- -9223372036854775808i64 1) _))\n");
+
+	Ok(())
+}
+
+#[test]
+fn options_and_results() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::sexpr::{from_sexpr, to_sexpr};
+
+	let source = "fn f(x: Option<i32>) -> Result<i32, ()> { if let Some(0) | None = x { h(Option::None); } let v = g()?.len(); Ok(x.unwrap_or(v)) }";
+	let printed = to_sexpr(smyth::backend::Backend::generate(source)?.items());
+	assert_eq!(
+		printed,
+		"(fn f (x)\n\t(if-let (or (some 0) (none)) x\n\t\t(then\n\t\t\t(call h (none))))\n\t(let v (method (try (call g)) len))\n\t(return (ok (method x unwrap_or v))))\n"
+	);
+	assert_eq!(to_sexpr(&from_sexpr(&printed)?), printed);
+
+	Ok(())
+}
@@ -0,0 +1,241 @@
+use smyth::backend::Ast;
+use smyth::interp::{Interpreter, Value};
+
+fn run(source: &str) -> Result<(Value, String), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(source)?;
+
+	let mut interp = Interpreter::new();
+	let out = interp.capture_print();
+	let result = interp.run(ast.items())?;
+
+	let out = out.borrow().clone();
+	Ok((result, out))
+}
+
+#[test]
+fn control_flow() -> Result<(), Box<dyn std::error::Error>> {
+	let (result, out) = run(r#"
+		mod math {
+			fn square(x: i32) -> i32 { x * x }
+		}
+
+		fn main() -> i32 {
+			let mut total = 0;
+			for i in 0..10 {
+				if i == 2 { continue; }
+				if i == 5 { break; }
+				total += math::square(i);
+			}
+
+			let mut n = 0;
+			loop {
+				n += 1;
+				if n > 3 { break; }
+			}
+
+			print("total", total, n);
+			total
+		}
+	"#)?;
+
+	assert_eq!(result, Value::Int(26));
+	assert_eq!(out, "total\t26\t4\n");
+
+	Ok(())
+}
+
+#[test]
+fn closures_capture_shadowed_bindings() -> Result<(), Box<dyn std::error::Error>> {
+	let (result, _) = run(r#"
+		fn main() -> i32 {
+			let y = 1;
+			let get = || y;
+			let y = 2;
+			get() * 10 + y
+		}
+	"#)?;
+
+	assert_eq!(result, Value::Int(12));
+
+	Ok(())
+}
+
+#[test]
+fn host_functions() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(r#"
+		extern "C" { fn host_add(a: i32, b: i32) -> i32; }
+		fn add_twice(a: i32) -> i32 { return host_add(a, a); }
+	"#)?;
+
+	let mut interp = Interpreter::new();
+	interp.register("host_add", |args| match args {
+		[Value::Int(a), Value::Int(b)] => Ok(Value::Int(a + b)),
+		_ => Err(smyth::interp::InterpError::Host("expected two integers".into())),
+	});
+
+	interp.load(ast.items())?;
+	assert_eq!(interp.call("add_twice", vec![Value::Int(21)])?, Value::Int(42));
+
+	Ok(())
+}
+
+#[test]
+fn formatting() -> Result<(), Box<dyn std::error::Error>> {
+	let source = std::fs::read_to_string("tests/lua/examples/7.rs")?;
+	let (_, out) = run(&format!("{source}\nfn start() {{ main(); }}"))?;
+
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/7.expected")?);
+
+	Ok(())
+}
+
+#[test]
+fn unsigned_arithmetic() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(crate::lua::UNSIGNED)?;
+	assert_eq!(out, crate::lua::UNSIGNED_OUTPUT);
+
+	Ok(())
+}
+
+#[test]
+fn assignment_order() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(crate::lua::ASSIGNMENT_ORDER)?;
+	assert_eq!(out, crate::lua::ASSIGNMENT_ORDER_OUTPUT);
+
+	Ok(())
+}
+
+#[test]
+fn destructuring() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/9.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/9.expected")?);
+
+	let err = run("fn main() { let [a, b] = [1, 2, 3]; }").unwrap_err();
+	assert!(err.to_string().contains("doesn't match"), "{err}");
+
+	Ok(())
+}
+
+#[test]
+fn refutable_patterns() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/10.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/10.expected")?);
+
+	let err = run("fn main() { let 1 = 2 else { print(0); }; }").unwrap_err();
+	assert!(err.to_string().contains("diverge"), "{err}");
+
+	Ok(())
+}
+
+#[test]
+fn options_and_results() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/11.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/11.expected")?);
+
+	let err = run("fn main() { let x: Option<i32> = None; x.unwrap(); }").unwrap_err();
+	assert_eq!(err.to_string(), "Panicked: called `Option::unwrap()` on a `None` value");
+
+	Ok(())
+}
+
+#[test]
+fn assignments() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/12.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/12.expected")?);
+
+	let err = run("fn main() { let mut a = [1, 2]; a[2] += 1; }").unwrap_err();
+	assert_eq!(err.to_string(), "Panicked: index out of bounds: the len is 2 but the index is 2");
+
+	Ok(())
+}
+
+#[test]
+fn unary_operators() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/13.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/13.expected")?);
+
+	let err = run("fn main() { let b = true; let n = -b; }").unwrap_err();
+	assert!(err.to_string().contains("can't apply Neg to bool"), "{err}");
+
+	Ok(())
+}
+
+#[test]
+fn casts() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::Options;
+
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/14.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/14.expected")?);
+
+	let source = "fn main() { let mut x: i32 = 2147483647; x += 1; let y: u8 = 250; print(x, y + 10, 2.5f32 as u8); }";
+	let ast = smyth::backend::Backend::generate_with(source, &Options { strict_integers: true, ..Default::default() })?;
+	let mut interp = Interpreter::new();
+	let out = interp.capture_print();
+	interp.run(ast.items())?;
+	assert_eq!(*out.borrow(), "-2147483648\t4\t2\n");
+
+	let (_, out) = run("fn main() { print('a' as u32, 'é' as u8); }")?;
+	assert_eq!(out, "97\t233\n");
+
+	Ok(())
+}
+
+#[test]
+fn division() -> Result<(), Box<dyn std::error::Error>> {
+	let (_, out) = run(&std::fs::read_to_string("tests/lua/examples/15.rs")?)?;
+	assert_eq!(out, std::fs::read_to_string("tests/lua/examples/15.expected")?);
+
+	Ok(())
+}
+
+#[test]
+fn if_values() -> Result<(), Box<dyn std::error::Error>> {
+	for example in ["17", "18"] {
+		let (_, out) = run(&std::fs::read_to_string(format!("tests/lua/examples/{example}.rs"))?)?;
+		assert_eq!(out, std::fs::read_to_string(format!("tests/lua/examples/{example}.expected"))?);
+	}
+
+	Ok(())
+}
+
+#[test]
+fn panics() -> Result<(), Box<dyn std::error::Error>> {
+	let panic = |source: &str| match run(source) {
+		Err(e) => e.to_string(),
+		Ok(_) => panic!("expected a panic from {source}"),
+	};
+
+	assert_eq!(
+		panic("fn main() { let x = vec![1]; assert_ne!(x, [1]); }"),
+		"Panicked: assertion `left != right` failed\n  left: [1]\n right: [1]"
+	);
+	assert_eq!(panic("fn main() { let x = 1; assert!(x == 2, \"x is {x}\"); }"), "Panicked: x is 1");
+	assert_eq!(panic("fn main() { unreachable!(); }"), "Panicked: internal error: entered unreachable code");
+	assert_eq!(panic("fn main() { panic!(\"at {}\", 3); }"), "Panicked: at 3");
+
+	let (_, out) = run(r#"fn main() { print!("a{}", 1); println!("b"); debug_assert_eq!(vec![1, 2], [1, 2]); }"#)?;
+	assert_eq!(out, "a1b\n");
+
+	Ok(())
+}
+
+#[test]
+fn host_paths() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{Macro, Macros, Options};
+
+	let mut macros = Macros::new();
+	macros.insert("double", Macro::Call("Math.double".into()));
+
+	let source = "fn main() -> i32 { double!(::Math::double(5)) }";
+	let ast = smyth::backend::Backend::generate_with(source, &Options { macros, ..Default::default() })?;
+
+	let mut interp = Interpreter::new();
+	interp.register("Math::double", |args| match args {
+		[Value::Int(x)] => Ok(Value::Int(x * 2)),
+		_ => Err(smyth::interp::InterpError::Host("expected an integer".into())),
+	});
+
+	assert_eq!(interp.run(ast.items())?, Value::Int(20));
+
+	Ok(())
+}
@@ -1 +1 @@
-;local test = _G.test;function main()local x = test();print("hello world!",true,x,55,"c")local x = string.format("%s %s",22,"test");for i = 1,2 do print(5) ::__continue__:: end;for i in expr do  ::__continue__:: end;end;
\ No newline at end of file
+;local test = _G.test;;local expr = _G.expr;function main()local x = test();print("hello world!",true,x,55,"c")local x_1 = string.format("%s %s",22,"test");for i = 1,1 do print(5) ::__continue__:: end;do local __array = expr; for __index = 1,#__array do local i = __array[__index]; ::__continue__:: end; end;end;
\ No newline at end of file
@@ -1,5 +1,6 @@
 extern "C" {
 	fn test() -> String;
+	fn expr() -> Vec<i32>;
 }
 
 fn main() {
@@ -0,0 +1,75 @@
+struct Point {
+	x: i32,
+	y: i32,
+}
+
+fn describe(values: [i32; 3]) -> &'static str {
+	if let [0, 0, 0] = values {
+		"zeros"
+	} else if let [1 | 2, ..] = values {
+		"small start"
+	} else if let [_, 7, _] = values {
+		"seven in the middle"
+	} else {
+		"other"
+	}
+}
+
+fn shrink(n: i32) -> i32 {
+	let (true, smaller) = (n > 5, n - 5) else {
+		return 0;
+	};
+	smaller
+}
+
+fn on_axis(p: Point) -> &'static str {
+	let Point { x: 0, y } = p else {
+		return "off the axis";
+	};
+	if y > 0 {
+		"above"
+	} else {
+		"below"
+	}
+}
+
+fn count_down(from: i32) {
+	let mut n = from;
+	loop {
+		let (false, m) = (n == 0, n) else {
+			break;
+		};
+		print!("{} ", m);
+		n -= 1;
+	}
+	println!("liftoff");
+}
+
+fn main() {
+	println!("{}", describe([0, 0, 0]));
+	println!("{}", describe([2, 9, 9]));
+	println!("{}", describe([5, 7, 1]));
+	println!("{}", describe([5, 6, 1]));
+
+	println!("{} {}", shrink(12), shrink(3));
+	println!("{} {}", on_axis(Point { x: 0, y: 3 }), on_axis(Point { x: 1, y: 3 }));
+
+	let greeting = "hi";
+	if let "hi" | "hello" = greeting {
+		println!("greeted");
+	}
+
+	let flag = true;
+	if let (Point { x: 1, y }, true) = (Point { x: 1, y: 4 }, flag) {
+		println!("y is {}", y);
+	}
+
+	let mut i = 0;
+	while let (true, square) = (i < 4, i * i) {
+		print!("{} ", square);
+		i += 1;
+	}
+	println!();
+
+	count_down(3);
+}
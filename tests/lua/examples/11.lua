@@ -0,0 +1 @@
+local __fmt do local base = 1; local function unsigned(v) if v >= 0 then return string.format("%d", v) end local q = (v >> 1) // 5 return q .. v - q * 10 end local is_float = math.type and function(v) return math.type(v) == "float" end or function(v) return v ~= math.floor(v) or v == math.huge or v == -math.huge end local nibbles = {} for i = 0, 15 do local bits = "" for b = 3, 0, -1 do bits = bits .. math.floor(i / 2 ^ b) % 2 end nibbles[string.format("%x", i)] = bits end local function len(s) local _, n = s:gsub("[^\128-\191]", "") return n end local function truncate(s, n) for i = 1, #s do local b = s:byte(i) if b < 128 or b >= 192 then if n == 0 then return s:sub(1, i - 1) end n = n - 1 end end return s end local function float(v, debug) if v ~= v then return "NaN" end if v == math.huge then return "inf" end if v == -math.huge then return "-inf" end if v == math.floor(v) and math.abs(v) < 2^53 then return string.format(debug and "%.1f" or "%d", v) end for p = 1, 17 do local s = string.format("%." .. p .. "g", v) if tonumber(s) == v then return s end end end local function debug(v) if v == nil then return "None" elseif type(v) == "table" and v.tag then if v.tag == "None" then return "None" end return v.tag .. "(" .. debug(v[1]) .. ")" elseif type(v) == "string" then return '"' .. v:gsub('[\\"]', "\\%0"):gsub("\n", "\\n"):gsub("\r", "\\r"):gsub("\t", "\\t") .. '"' elseif type(v) == "table" then local parts, i = {}, base while v[i] ~= nil do parts[#parts + 1] = debug(v[i]) i = i + 1 end return "[" .. table.concat(parts, ", ") .. "]" elseif type(v) == "number" and is_float(v) then return float(v, true) end return tostring(v) end function __fmt(v, spec) local kind, precision, width = spec.kind, spec.precision, spec.width local numeric, s, sign, prefix = type(v) == "number" or spec.unsigned, nil, "", "" if spec.unsigned then s = unsigned(v) if spec.sign then sign = "+" end elseif numeric and (kind == "x" or kind == "X" or kind == "o" or kind == "b") then if kind == "b" then s = string.format("%x", v):gsub(".", nibbles):match("^0*(.-.)$") else s = string.format("%" .. kind, v) end if spec.alternate then prefix = kind == "o" and "0o" or kind == "b" and "0b" or "0x" end elseif numeric then if v < 0 or (v == 0 and 1 / v < 0) then sign, v = "-", -v elseif spec.sign then sign = "+" end if kind == "e" or kind == "E" then local p = precision if not p then p = 0 while p < 17 and tonumber(string.format("%." .. p .. "e", v)) ~= v do p = p + 1 end end local m, e = string.format("%." .. p .. "e", v):match("^%-?(.-)e(.*)$") s = m .. kind .. string.format("%d", tonumber(e)) elseif is_float(v) then s = precision and string.format("%." .. precision .. "f", v) or float(v, kind == "?") else s = string.format("%d", v):gsub("^%-", "") end else s = kind == "?" and debug(v) or tostring(v) if precision and kind ~= "?" then s = truncate(s, precision) end end s = sign .. prefix .. s local chars = len(s) if not width or chars >= width then return s end if numeric and spec.zero then return sign .. prefix .. string.rep("0", width - chars) .. s:sub(#sign + #prefix + 1) end local pad = width - chars local before = (spec.align or (numeric and ">" or "<")) == ">" and pad or spec.align == "^" and math.floor(pad / 2) or 0 return string.rep(spec.fill or " ", before) .. s .. string.rep(spec.fill or " ", pad - before) end end;local __is_some, __is_none, __is_ok, __is_err, __map, __and_then, __unwrap, __unwrap_or, __ok_or, __ok, __branch, __try, __catch do local none = nil; local function some(x) return x end; local function is_some(v) return v ~= nil end; local function get(v) return v end; local function result(v) return type(v) == "table" and (v.tag == "Ok" or v.tag == "Err") end function __is_some(v) return is_some(v) end function __is_none(v) return not is_some(v) end function __is_ok(v) return v.tag == "Ok" end function __is_err(v) return v.tag == "Err" end function __map(v, f) if result(v) then if v.tag == "Ok" then return {tag = "Ok", f(v[1])} end return v end if is_some(v) then return some(f(get(v))) end return none end function __and_then(v, f) if result(v) then if v.tag == "Ok" then return f(v[1]) end return v end if is_some(v) then return f(get(v)) end return none end function __unwrap(v) if result(v) then if v.tag == "Ok" then return v[1] end error("called `Result::unwrap()` on an `Err` value: " .. tostring(v[1]), 2) end if is_some(v) then return get(v) end error("called `Option::unwrap()` on a `None` value", 2) end function __unwrap_or(v, default) if result(v) then if v.tag == "Ok" then return v[1] end return default end if is_some(v) then return get(v) end return default end function __ok_or(v, err) if is_some(v) then return {tag = "Ok", get(v)} end return {tag = "Err", err} end function __ok(v) if v.tag == "Ok" then return some(v[1]) end return none end local residual = {} function __branch(v) if result(v) then if v.tag == "Ok" then return false, v[1] end elseif is_some(v) then return false, get(v) end return true, v end function __try(v) local early, value = __branch(v) if early then error({[residual] = true, value = value}, 0) end return value end function __catch(ok, ...) if ok then return ... end local e = ... if type(e) == "table" and e[residual] then return e.value end error(e, 0) end end;function smaller(n)if n>3 then return n-3; else return nil; end;end;function twice_smaller(n)local __residual, m = __branch(smaller(n)); if __residual then return m end;return smaller(m);end;function checked_sub(a,b)if b>a then return {tag="Err","negative"}; end;return {tag="Ok",a-b};end;function differences(a,b,c)local __residual, x = __branch(checked_sub(a,b)); if __residual then return x end;local __residual, y = __branch(checked_sub(x,c)); if __residual then return y end;return {tag="Ok",x+y};end;function both_smaller(a,b)do local __residual, __value = __branch(smaller(a)); if __residual then return __value end end;return smaller(b);end;function flatten(r)do local _, __value = __branch(r); return __value end;end;function describe(r)if r.tag == "Ok" then local v = r[1];print(string.format("ok %s",__fmt(v,{kind=""}))) else if r.tag == "Err" then local e = r[1];print(string.format("error: %s",__fmt(e,{kind=""}))) end; end;end;function main()print(string.format("%s %s",__fmt(__unwrap_or(twice_smaller(12),0),{kind=""}),__fmt(__unwrap_or(twice_smaller(5),0),{kind=""})))print(string.format("%s",__fmt(__unwrap(__map(smaller(8),function(x)return x+1;end)),{kind=""})))print(string.format("%s %s",__fmt(__is_none(smaller(3)),{kind=""}),__fmt(__is_some(smaller(4)),{kind=""})))local found = __and_then(smaller(10),smaller);if found ~= nil then local n = found;print(string.format("found %s",__fmt(n,{kind=""}))) else print("nothing") end;describe(differences(20,2,5))describe(differences(1,4,0))describe(flatten({tag="Ok",{tag="Ok",4}}))describe(flatten({tag="Ok",{tag="Err","inner"}}))describe(flatten({tag="Err","outer"}))print(string.format("%s %s",__fmt(__unwrap_or(both_smaller(9,5),0),{kind=""}),__fmt(__unwrap_or(both_smaller(2,5),0),{kind=""})))describe(__ok_or(smaller(2),"too small"))describe(__ok_or(smaller(14),"too small"))print(string.format("%s %s",__fmt(__unwrap_or(__ok(checked_sub(9,3)),0),{kind=""}),__fmt(__is_err(checked_sub(3,9)),{kind=""})))do local __let = __map(smaller(5),function(__arg0)return __arg0;end);if __let ~= nil and __let ~= nil then local v = __let;print(string.format("nested %s",__fmt(v,{kind=""}))) end; end;local missing = nil;print(string.format("%s",__fmt(__unwrap_or(missing,7),{kind=""})))local add = function(a,b) return __catch(pcall(function()return __try(a)+__try(b);end)) end;print(string.format("%s %s",__fmt(__unwrap_or(add(1,2),0),{kind=""}),__fmt(__unwrap_or(add(1,nil),0),{kind=""})))end;
\ No newline at end of file
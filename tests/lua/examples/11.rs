@@ -0,0 +1,75 @@
+fn smaller(n: i32) -> Option<i32> {
+	if n > 3 {
+		Some(n - 3)
+	} else {
+		None
+	}
+}
+
+fn twice_smaller(n: i32) -> Option<i32> {
+	let m = smaller(n)?;
+	smaller(m)
+}
+
+fn checked_sub(a: i32, b: i32) -> Result<i32, &'static str> {
+	if b > a {
+		return Err("negative");
+	}
+	Ok(a - b)
+}
+
+fn differences(a: i32, b: i32, c: i32) -> Result<i32, &'static str> {
+	let x = checked_sub(a, b)?;
+	let y = checked_sub(x, c)?;
+	Ok(x + y)
+}
+
+fn both_smaller(a: i32, b: i32) -> Option<i32> {
+	smaller(a)?;
+	smaller(b)
+}
+
+fn flatten(r: Result<Result<i32, &'static str>, &'static str>) -> Result<i32, &'static str> {
+	return r?;
+}
+
+fn describe(r: Result<i32, &'static str>) {
+	if let Ok(v) = r {
+		println!("ok {}", v);
+	} else if let Err(e) = r {
+		println!("error: {}", e);
+	}
+}
+
+fn main() {
+	println!("{} {}", twice_smaller(12).unwrap_or(0), twice_smaller(5).unwrap_or(0));
+	println!("{}", smaller(8).map(|x| x + 1).unwrap());
+	println!("{} {}", smaller(3).is_none(), smaller(4).is_some());
+
+	let found = smaller(10).and_then(smaller);
+	if let Some(n) = found {
+		println!("found {}", n);
+	} else {
+		println!("nothing");
+	}
+
+	describe(differences(20, 2, 5));
+	describe(differences(1, 4, 0));
+	describe(flatten(Ok(Ok(4))));
+	describe(flatten(Ok(Err("inner"))));
+	describe(flatten(Err("outer")));
+	println!("{} {}", both_smaller(9, 5).unwrap_or(0), both_smaller(2, 5).unwrap_or(0));
+	describe(smaller(2).ok_or("too small"));
+	describe(smaller(14).ok_or("too small"));
+	println!("{} {}", checked_sub(9, 3).ok().unwrap_or(0), checked_sub(3, 9).is_err());
+
+	if let Some(Some(v)) = smaller(5).map(Some) {
+		println!("nested {}", v);
+	}
+
+	let missing: Option<i32> = Option::None;
+	println!("{}", missing.unwrap_or(7));
+
+	let add = |a: Option<i32>, b: Option<i32>| -> Option<i32> { Some(a? + b?) };
+	println!("{} {}", add(Some(1), Some(2)).unwrap_or(0), add(Some(1), None).unwrap_or(0));
+}
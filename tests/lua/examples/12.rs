@@ -0,0 +1,54 @@
+struct Point {
+	x: i32,
+	y: i32,
+}
+
+struct Grid {
+	cells: [i32; 4],
+	origin: Point,
+}
+
+fn pick(i: usize) -> usize {
+	println!("pick {}", i);
+	i
+}
+
+fn main() {
+	let mut p = Point { x: 1, y: 2 };
+	p.x = 10;
+	p.y += p.x;
+	println!("{} {}", p.x, p.y);
+
+	let mut values = [1, 2, 3, 4];
+	values[0] = 7;
+	values[1] *= 5;
+	values[2] %= 2;
+	values[3] <<= 3;
+	println!("{} {} {} {}", values[0], values[1], values[2], values[3]);
+
+	let mut flags = 12;
+	flags ^= 5;
+	flags &= 7;
+	flags |= 16;
+	flags >>= 1;
+	println!("{}", flags);
+
+	let mut g = Grid { cells: [0, 0, 0, 0], origin: Point { x: 0, y: 0 } };
+	g.origin.x = 3;
+	g.origin.y -= 4;
+	let i: usize = 2;
+	g.cells[i] += 4;
+	g.cells[pick(3)] += 2;
+	g.cells[pick(3)] *= 5;
+	println!("{} {} {} {}", g.origin.x, g.origin.y, g.cells[2], g.cells[3]);
+
+	let mut pair = (1, 2);
+	pair.0 += 5;
+	pair.1 = pair.0;
+	println!("{} {}", pair.0, pair.1);
+
+	let mut nested = [[1, 2], [3, 4]];
+	nested[1][0] *= 10;
+	nested[0] = [5, 6];
+	println!("{} {} {}", nested[0][1], nested[1][0], nested[1][1]);
+}
@@ -0,0 +1 @@
+local __fmt do local base = 1; local function unsigned(v) if v >= 0 then return string.format("%d", v) end local q = (v >> 1) // 5 return q .. v - q * 10 end local is_float = math.type and function(v) return math.type(v) == "float" end or function(v) return v ~= math.floor(v) or v == math.huge or v == -math.huge end local nibbles = {} for i = 0, 15 do local bits = "" for b = 3, 0, -1 do bits = bits .. math.floor(i / 2 ^ b) % 2 end nibbles[string.format("%x", i)] = bits end local function len(s) local _, n = s:gsub("[^\128-\191]", "") return n end local function truncate(s, n) for i = 1, #s do local b = s:byte(i) if b < 128 or b >= 192 then if n == 0 then return s:sub(1, i - 1) end n = n - 1 end end return s end local function float(v, debug) if v ~= v then return "NaN" end if v == math.huge then return "inf" end if v == -math.huge then return "-inf" end if v == math.floor(v) and math.abs(v) < 2^53 then return string.format(debug and "%.1f" or "%d", v) end for p = 1, 17 do local s = string.format("%." .. p .. "g", v) if tonumber(s) == v then return s end end end local function debug(v) if v == nil then return "None" elseif type(v) == "table" and v.tag then if v.tag == "None" then return "None" end return v.tag .. "(" .. debug(v[1]) .. ")" elseif type(v) == "string" then return '"' .. v:gsub('[\\"]', "\\%0"):gsub("\n", "\\n"):gsub("\r", "\\r"):gsub("\t", "\\t") .. '"' elseif type(v) == "table" then local parts, i = {}, base while v[i] ~= nil do parts[#parts + 1] = debug(v[i]) i = i + 1 end return "[" .. table.concat(parts, ", ") .. "]" elseif type(v) == "number" and is_float(v) then return float(v, true) end return tostring(v) end function __fmt(v, spec) local kind, precision, width = spec.kind, spec.precision, spec.width local numeric, s, sign, prefix = type(v) == "number" or spec.unsigned, nil, "", "" if spec.unsigned then s = unsigned(v) if spec.sign then sign = "+" end elseif numeric and (kind == "x" or kind == "X" or kind == "o" or kind == "b") then if kind == "b" then s = string.format("%x", v):gsub(".", nibbles):match("^0*(.-.)$") else s = string.format("%" .. kind, v) end if spec.alternate then prefix = kind == "o" and "0o" or kind == "b" and "0b" or "0x" end elseif numeric then if v < 0 or (v == 0 and 1 / v < 0) then sign, v = "-", -v elseif spec.sign then sign = "+" end if kind == "e" or kind == "E" then local p = precision if not p then p = 0 while p < 17 and tonumber(string.format("%." .. p .. "e", v)) ~= v do p = p + 1 end end local m, e = string.format("%." .. p .. "e", v):match("^%-?(.-)e(.*)$") s = m .. kind .. string.format("%d", tonumber(e)) elseif is_float(v) then s = precision and string.format("%." .. precision .. "f", v) or float(v, kind == "?") else s = string.format("%d", v):gsub("^%-", "") end else s = kind == "?" and debug(v) or tostring(v) if precision and kind ~= "?" then s = truncate(s, precision) end end s = sign .. prefix .. s local chars = len(s) if not width or chars >= width then return s end if numeric and spec.zero then return sign .. prefix .. string.rep("0", width - chars) .. s:sub(#sign + #prefix + 1) end local pad = width - chars local before = (spec.align or (numeric and ">" or "<")) == ">" and pad or spec.align == "^" and math.floor(pad / 2) or 0 return string.rep(spec.fill or " ", before) .. s .. string.rep(spec.fill or " ", pad - before) end end;function bump(counter,slot)counter.hits[slot+1] = counter.hits[slot+1]+1;counter.total = counter.total+1;end;function fill(values,start)local next = start;for i = 0,3 do values[i+1] = next;next = next-3; ::__continue__:: end;end;function sum(values)local total = 0;for i = 0,3 do total = total+values[i+1]; ::__continue__:: end;return total;end;function invert(on)return not on;end;function describe(values)if #values >= 2 then local first,last = values[1],values[#values];return first-last; end;return 0;end;function main()local x = 5;local y = -x;local z = -((-x)+2);print(string.format("%s %s %s %s",__fmt(y,{kind=""}),__fmt(z,{kind=""}),__fmt(x-(-3),{kind=""}),__fmt((-y)*2,{kind=""})))local mask = 12;local bits = 3;print(string.format("%s %s %s",__fmt(~mask,{kind=""}),__fmt((~bits)&7,{kind=""}),__fmt(~(mask|1),{kind=""})))local done = x>3;print(string.format("%s %s %s %s",__fmt(not done,{kind=""}),__fmt(not (x==5),{kind=""}),__fmt(invert(done),{kind=""}),__fmt(invert(not done),{kind=""})))local counter = {hits={0,0,0},total=0};bump(counter,0)bump(counter,2)bump(counter,2)print(string.format("%s %s %s %s",__fmt(counter.hits[1],{kind=""}),__fmt(counter.hits[2],{kind=""}),__fmt(counter.hits[3],{kind=""}),__fmt(counter.total,{kind=""})))local values = {0,0,0,0};fill(values,10)local r = values;print(string.format("%s %s %s %s",__fmt(r[1],{kind=""}),__fmt(r[4],{kind=""}),__fmt(sum(values),{kind=""}),__fmt(describe(r),{kind=""})))local n = x;local m = n;print(string.format("%s",__fmt(n+m,{kind=""})))local pair = {1.5,-2.5};print(string.format("%s %s",__fmt(-pair[1],{kind=""}),__fmt(-pair[2],{kind=""})))end;
\ No newline at end of file
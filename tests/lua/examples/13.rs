@@ -0,0 +1,68 @@
+struct Counter {
+	hits: [i32; 3],
+	total: i32,
+}
+
+fn bump(counter: &mut Counter, slot: usize) {
+	counter.hits[slot] += 1;
+	counter.total += 1;
+}
+
+fn fill(values: &mut [i32; 4], start: i32) {
+	let mut next = start;
+	for i in 0..4 {
+		values[i] = next;
+		next -= 3;
+	}
+}
+
+fn sum(values: &[i32; 4]) -> i32 {
+	let mut total = 0;
+	for i in 0..4 {
+		total += values[i];
+	}
+	total
+}
+
+fn invert(on: bool) -> bool {
+	!on
+}
+
+fn describe(values: &[i32; 4]) -> i32 {
+	if let [first, .., last] = values {
+		return *first - *last;
+	}
+	0
+}
+
+fn main() {
+	let x = 5;
+	let y = -x;
+	let z = -(-x + 2);
+	println!("{} {} {} {}", y, z, x - -3, -y * 2);
+
+	let mask: i64 = 12;
+	let bits = 3;
+	println!("{} {} {}", !mask, !bits & 7, !(mask | 1));
+
+	let done = x > 3;
+	println!("{} {} {} {}", !done, !(x == 5), invert(done), invert(!done));
+
+	let mut counter = Counter { hits: [0, 0, 0], total: 0 };
+	bump(&mut counter, 0);
+	bump(&mut counter, 2);
+	bump(&mut counter, 2);
+	println!("{} {} {} {}", counter.hits[0], counter.hits[1], counter.hits[2], counter.total);
+
+	let mut values = [0, 0, 0, 0];
+	fill(&mut values, 10);
+	let r = &values;
+	println!("{} {} {} {}", r[0], (*r)[3], sum(&values), describe(r));
+
+	let n = &x;
+	let m = &&n;
+	println!("{}", *n + ***m);
+
+	let pair = (1.5, -2.5);
+	println!("{} {}", -pair.0, -pair.1);
+}
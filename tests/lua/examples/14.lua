@@ -0,0 +1 @@
+local __fmt do local base = 1; local function unsigned(v) if v >= 0 then return string.format("%d", v) end local q = (v >> 1) // 5 return q .. v - q * 10 end local is_float = math.type and function(v) return math.type(v) == "float" end or function(v) return v ~= math.floor(v) or v == math.huge or v == -math.huge end local nibbles = {} for i = 0, 15 do local bits = "" for b = 3, 0, -1 do bits = bits .. math.floor(i / 2 ^ b) % 2 end nibbles[string.format("%x", i)] = bits end local function len(s) local _, n = s:gsub("[^\128-\191]", "") return n end local function truncate(s, n) for i = 1, #s do local b = s:byte(i) if b < 128 or b >= 192 then if n == 0 then return s:sub(1, i - 1) end n = n - 1 end end return s end local function float(v, debug) if v ~= v then return "NaN" end if v == math.huge then return "inf" end if v == -math.huge then return "-inf" end if v == math.floor(v) and math.abs(v) < 2^53 then return string.format(debug and "%.1f" or "%d", v) end for p = 1, 17 do local s = string.format("%." .. p .. "g", v) if tonumber(s) == v then return s end end end local function debug(v) if v == nil then return "None" elseif type(v) == "table" and v.tag then if v.tag == "None" then return "None" end return v.tag .. "(" .. debug(v[1]) .. ")" elseif type(v) == "string" then return '"' .. v:gsub('[\\"]', "\\%0"):gsub("\n", "\\n"):gsub("\r", "\\r"):gsub("\t", "\\t") .. '"' elseif type(v) == "table" then local parts, i = {}, base while v[i] ~= nil do parts[#parts + 1] = debug(v[i]) i = i + 1 end return "[" .. table.concat(parts, ", ") .. "]" elseif type(v) == "number" and is_float(v) then return float(v, true) end return tostring(v) end function __fmt(v, spec) local kind, precision, width = spec.kind, spec.precision, spec.width local numeric, s, sign, prefix = type(v) == "number" or spec.unsigned, nil, "", "" if spec.unsigned then s = unsigned(v) if spec.sign then sign = "+" end elseif numeric and (kind == "x" or kind == "X" or kind == "o" or kind == "b") then if kind == "b" then s = string.format("%x", v):gsub(".", nibbles):match("^0*(.-.)$") else s = string.format("%" .. kind, v) end if spec.alternate then prefix = kind == "o" and "0o" or kind == "b" and "0b" or "0x" end elseif numeric then if v < 0 or (v == 0 and 1 / v < 0) then sign, v = "-", -v elseif spec.sign then sign = "+" end if kind == "e" or kind == "E" then local p = precision if not p then p = 0 while p < 17 and tonumber(string.format("%." .. p .. "e", v)) ~= v do p = p + 1 end end local m, e = string.format("%." .. p .. "e", v):match("^%-?(.-)e(.*)$") s = m .. kind .. string.format("%d", tonumber(e)) elseif is_float(v) then s = precision and string.format("%." .. precision .. "f", v) or float(v, kind == "?") else s = string.format("%d", v):gsub("^%-", "") end else s = kind == "?" and debug(v) or tostring(v) if precision and kind ~= "?" then s = truncate(s, precision) end end s = sign .. prefix .. s local chars = len(s) if not width or chars >= width then return s end if numeric and spec.zero then return sign .. prefix .. string.rep("0", width - chars) .. s:sub(#sign + #prefix + 1) end local pad = width - chars local before = (spec.align or (numeric and ">" or "<")) == ">" and pad or spec.align == "^" and math.floor(pad / 2) or 0 return string.rep(spec.fill or " ", before) .. s .. string.rep(spec.fill or " ", pad - before) end end;local __int do function __int(v, bits, signed) if type(v) == "boolean" then return v and 1 or 0 end if type(v) == "string" then v = utf8.codepoint(v) end if math.type(v) == "float" then if v ~= v then return 0 end local lo = signed and -2.0 ^ (bits - 1) or 0.0 local hi = signed and 2.0 ^ (bits - 1) or 2.0 ^ bits if v <= lo then return math.tointeger(lo) end if v >= hi then return signed and (1 << (bits - 1)) - 1 or (1 << bits) - 1 end v = v >= 0 and math.floor(v) or math.ceil(v) if v >= 2.0 ^ 63 then v = v - 2.0 ^ 64 end return math.tointeger(v) end if bits == 64 then return v end v = v & ((1 << bits) - 1) if signed and v >= 1 << (bits - 1) then v = v - (1 << bits) end return v end end;function half(x)return x*0.5;end;function to_byte(x)return x&255;end;function main()local big = 300;local small = -1;print(string.format("%s %s %s %s",big&255,small&255,((200+128)&255)-128,small&4294967295))print(string.format("%s %s %s",__fmt(to_byte(513),{kind=""}),(((big*2)+32768)&65535)-32768,(((-129)+128)&255)-128))local x = 3.99;print(string.format("%s %s %s %s",__int(x,32,true),__int(-x,32,true),__int(300.5,8,false),__int(-5.0,8,false)))print(string.format("%s %s %s",__int(1e20,32,true),__int(half(9.0),64,true),__int(half(-9.0),16,false)))local yes = true;print(string.format("%s %s",__fmt((__int(yes,32,true))+(__int(false,32,true)),{kind=""}),__int(not yes,8,false)))local n = 7;print(string.format("%s %s",__fmt((n+0.0)/2.0,{kind=""}),__fmt((1+0.0)+0.25,{kind=""})))local max = -1;local min = math.mininteger;print(string.format("%s %s %s %s",max&255,max,((min+2147483648)&4294967295)-2147483648,(min+1)&65535))local mask = 10;print(string.format("%s %s",(~mask)&255,__fmt((((~mask)&255)&4294967295)<<4,{kind=""})))end;
\ No newline at end of file
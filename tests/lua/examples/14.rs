@@ -0,0 +1,31 @@
+fn half(x: f64) -> f64 {
+	x * 0.5
+}
+
+fn to_byte(x: i32) -> u8 {
+	x as u8
+}
+
+fn main() {
+	let big = 300;
+	let small: i32 = -1;
+	println!("{} {} {} {}", big as u8, small as u8, 200u8 as i8, small as u32);
+	println!("{} {} {}", to_byte(513), (big * 2) as i16, -129i32 as i8);
+
+	let x = 3.99;
+	println!("{} {} {} {}", x as i32, -x as i32, 300.5 as u8, -5.0 as u8);
+	println!("{} {} {}", 1e20 as i32, half(9.0) as i64, half(-9.0) as u16);
+
+	let yes = true;
+	println!("{} {}", yes as i32 + false as i32, !yes as u8);
+
+	let n = 7;
+	println!("{} {}", n as f64 / 2.0, (1 as f64) + 0.25);
+
+	let max = 18446744073709551615u64;
+	let min = -9223372036854775808i64;
+	println!("{} {} {} {}", max as u8, max as i64, min as i32, (min + 1) as u16);
+
+	let mask: u8 = 0b1010;
+	println!("{} {}", !mask, (!mask as u32) << 4);
+}
@@ -0,0 +1,52 @@
+struct Average {
+	sum: i64,
+	count: i64,
+}
+
+fn digits(mut n: u64) -> u64 {
+	let mut sum = 0;
+	while n > 0 {
+		sum += n % 10;
+		n /= 10;
+	}
+	sum
+}
+
+fn luhn(number: [i32; 6]) -> bool {
+	let mut total = 0;
+	for i in 0..6 {
+		let mut d = number[5 - i];
+		if i % 2 == 1 {
+			d *= 2;
+			if d > 9 {
+				d -= 9;
+			}
+		}
+		total += d;
+	}
+	total % 10 == 0
+}
+
+fn main() {
+	let a = 7;
+	let b = -7;
+	println!("{} {} {} {}", a / 2, b / 2, a / -2, b / -2);
+	println!("{} {} {} {}", a % 3, b % 3, a % -3, b % -3);
+	println!("{} {}", -8 / 2, 1 / 3);
+
+	let x = 7.5;
+	println!("{} {} {}", x / 2.0, x % 2.0, -x % 2.0);
+
+	let mut avg = Average { sum: -17, count: 4 };
+	println!("{} {}", avg.sum / 4, avg.sum % avg.count);
+	avg.sum /= 2;
+	avg.sum %= 5;
+	println!("{}", avg.sum);
+
+	let mut n: i32 = -100;
+	n /= 7;
+	println!("{} {} {}", n, digits(987654321), luhn([7, 9, 9, 2, 7, 0]));
+
+	let seconds = 3725;
+	println!("{}:{}:{}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+}
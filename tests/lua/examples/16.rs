@@ -0,0 +1,37 @@
+fn grade(score: i32) -> char {
+	if score >= 90 {
+		'A'
+	} else if score >= 80 {
+		'B'
+	} else {
+		'C'
+	}
+}
+
+fn kind(c: char) -> &'static str {
+	if c == 'a' || c == 'e' || c == 'i' || c == 'o' || c == 'u' {
+		"vowel"
+	} else if c == ' ' {
+		"space"
+	} else if c == '\n' {
+		"newline"
+	} else {
+		"other"
+	}
+}
+
+fn main() {
+	println!("{} {} {}", grade(95), grade(85), grade(12));
+	println!("{} {} {} {}", kind('e'), kind(' '), kind('\n'), kind('z'));
+
+	let quote = "she said \"hi\"\tthen\\left";
+	println!("{}", quote);
+	println!("{}", "caf\u{e9} \u{1F980} \x41\u{7f}1");
+	println!(r"raw \n {}", r#"with "quotes""#);
+	println!("{}{}", 'é', '\'');
+
+	let big = 1_000_000;
+	println!("{} {} {} {}", big, 0xff, 0o17, 0b1010_1010);
+	println!("{} {} {}", 0xFFFF_FFFFu32, 1_u8, 7i64 / 2);
+	println!("{} {}", 0.1f32 as f64 == 0.1, 0.5f32 as f64 == 0.5);
+}
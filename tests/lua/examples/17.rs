@@ -0,0 +1,41 @@
+fn sign(n: i32) -> i32 {
+	let s = if n > 0 {
+		1
+	} else if n < 0 {
+		-1
+	} else {
+		0
+	};
+	s
+}
+
+fn grade(score: i32) -> &'static str {
+	return if score >= 90 { "A" } else if score >= 70 { "B" } else { "C" };
+}
+
+fn clamp(n: i32, lo: i32, hi: i32) -> i32 {
+	let clamped = {
+		let low = if n < lo { lo } else { n };
+		if low > hi {
+			hi
+		} else {
+			low
+		}
+	};
+	clamped
+}
+
+fn main() {
+	let mut label = "none";
+	let numbers = [-4, 0, 7];
+	for i in 0..3 {
+		let n = numbers[i];
+		label = if sign(n) == 0 { "zero" } else { "nonzero" };
+		println!("{} {} {}", n, sign(n), label);
+	}
+	println!("{} {} {}", grade(95), grade(75), grade(10));
+	println!("{} {} {}", clamp(-3, 0, 10), clamp(5, 0, 10), clamp(42, 0, 10));
+	let parity = if label == "nonzero" { if 7 % 2 == 0 { "even" } else { "odd" } } else { "?" };
+	println!("{}", parity);
+	println!("{} {}", if parity == "odd" { 1 } else { 2 }, sign(if parity == "even" { 3 } else { -3 }));
+}
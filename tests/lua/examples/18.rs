@@ -0,0 +1,25 @@
+fn half(n: i32) -> Option<i32> {
+	if n % 2 == 0 {
+		Some(n / 2)
+	} else {
+		None
+	}
+}
+
+fn describe(n: i32) -> i32 {
+	return if let Some(h) = half(n) { h } else { -1 };
+}
+
+fn main() {
+	let o = Some(5);
+	let y = if let Some(v) = o { v * 2 } else { 0 };
+	let mut total = 0;
+	total = if let Some(h) = half(y) {
+		if let Some(q) = half(h) { q } else { h + 100 }
+	} else {
+		-1
+	};
+	let pair = (3, 4);
+	let sum = if let (a, 4) = pair { a + 4 } else { 0 };
+	println!("{} {} {} {} {}", y, total, describe(8), describe(7), sum);
+}
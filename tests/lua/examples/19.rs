@@ -0,0 +1,34 @@
+struct Point {
+	x: i32,
+	y: i32,
+}
+
+fn main() {
+	let pairs = [(1, "one"), (2, "two"), (3, "three")];
+	for (n, name) in pairs {
+		if n == 2 {
+			continue;
+		}
+		println!("{n} {name}");
+	}
+
+	let points = [Point { x: 1, y: 2 }, Point { x: -3, y: 4 }];
+	let mut total = 0;
+	for Point { x, y } in points {
+		total += x * y;
+	}
+	println!("{total}");
+
+	let grid = [[1, 2], [3, 4]];
+	for [a, b] in grid {
+		for c in [a, b] {
+			print!("{c} ");
+		}
+		println!("{}", a + b);
+	}
+
+	let empty: [(i32, i32); 0] = [];
+	for (a, b) in empty {
+		println!("{a} {b}");
+	}
+}
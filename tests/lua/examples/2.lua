@@ -0,0 +1 @@
+function add(a,b)return a+b;end;function main()local double = function(x)return x*2;end;for i = 0,2 do print(add(i,double(i))) ::__continue__:: end;local total = 0;for i = 1,4 do total = total+i; ::__continue__:: end;print("total",total,true)end;
\ No newline at end of file
@@ -0,0 +1,18 @@
+fn add(a: i32, b: i32) -> i32 {
+	a + b
+}
+
+fn main() {
+	let double = |x| x * 2;
+
+	for i in 0..3 {
+		println!(add(i, double(i)));
+	}
+
+	let mut total = 0;
+	for i in 1..=4 {
+		total += i;
+	}
+
+	println!("total", total, true);
+}
@@ -0,0 +1 @@
+local shapes = {};shapes.units = {};function shapes.area(w,h)return shapes.scale(w*h);end;function shapes.scale(x)return x*shapes.units.FACTOR();end;function shapes.units.FACTOR()return 2;end;function shapes.units.describe()print("area",shapes.area(3,4))end;function main()shapes.units.describe()print(shapes.area(1,1))end;
\ No newline at end of file
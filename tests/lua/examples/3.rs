@@ -0,0 +1,24 @@
+mod shapes {
+	pub fn area(w: i32, h: i32) -> i32 {
+		scale(w * h)
+	}
+
+	fn scale(x: i32) -> i32 {
+		x * units::FACTOR()
+	}
+
+	pub mod units {
+		pub fn FACTOR() -> i32 {
+			2
+		}
+
+		pub fn describe() {
+			println!("area", super::area(3, 4));
+		}
+	}
+}
+
+fn main() {
+	shapes::units::describe();
+	println!(crate::shapes::area(1, 1));
+}
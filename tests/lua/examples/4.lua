@@ -0,0 +1 @@
+local local_ = {};function local_.end_(until_)local repeat_ = until_*2;return repeat_;end;function string_(nil_)local end__ = nil_+1;return end__;end;function main()local not_ = local_.end_(3);for in_ = 0,1 do print(not_,string_(in_)) ::__continue__:: end;end;
\ No newline at end of file
@@ -0,0 +1,18 @@
+mod r#local {
+	pub fn r#end(until: i32) -> i32 {
+		let r#repeat = until * 2;
+		r#repeat
+	}
+}
+
+fn string(nil: i32) -> i32 {
+	let end_ = nil + 1;
+	end_
+}
+
+fn main() {
+	let not = r#local::r#end(3);
+	for r#in in 0..2 {
+		println!(not, string(r#in));
+	}
+}
@@ -0,0 +1 @@
+local count = {};function count.twice(x)return x*2;end;function helper()return 1;end;function main()local helper_1,inner;function helper_1()return 2;end;function inner()return helper_1()+1;end;local y = 1;local first = function()return y;end;local y_1 = y+10;local count_1 = count.twice(y_1);print(first(),y_1,count_1)for i = 0,1 do local i_1 = i*100;print(i_1) ::__continue__:: end;print(helper_1(),inner())end;
\ No newline at end of file
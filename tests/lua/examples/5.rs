@@ -0,0 +1,32 @@
+mod count {
+	pub fn twice(x: i32) -> i32 {
+		x * 2
+	}
+}
+
+fn helper() -> i32 {
+	1
+}
+
+fn main() {
+	let y = 1;
+	let first = || y;
+	let y = y + 10;
+	let count = count::twice(y);
+	println!(first(), y, count);
+
+	for i in 0..2 {
+		let i = i * 100;
+		println!(i);
+	}
+
+	println!(helper(), inner());
+
+	fn helper() -> i32 {
+		2
+	}
+
+	fn inner() -> i32 {
+		helper() + 1
+	}
+}
@@ -0,0 +1 @@
+function area(w,h)do local w_1 = w*2;return w_1*h;end;end;function main()local x = 1;do local x_1 = 2;print(x_1)end;local y;do local a = x+10;y = a*2;end;local z;do z = y+1;end;print(x,y,z)print((function()local b = 3;return b*b;end)(),area(2,3))end;
\ No newline at end of file
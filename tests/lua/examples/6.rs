@@ -0,0 +1,26 @@
+fn area(w: i32, h: i32) -> i32 {
+	{
+		let w = w * 2;
+		w * h
+	}
+}
+
+fn main() {
+	let x = 1;
+	{
+		let x = 2;
+		println!(x);
+	}
+
+	let y = {
+		let a = x + 10;
+		a * 2
+	};
+
+	let z = unsafe { y + 1 };
+	println!(x, y, z);
+	println!({
+		let b = 3;
+		b * b
+	}, area(2, 3));
+}
@@ -0,0 +1 @@
+local __fmt do local base = 1; local function unsigned(v) if v >= 0 then return string.format("%d", v) end local q = (v >> 1) // 5 return q .. v - q * 10 end local is_float = math.type and function(v) return math.type(v) == "float" end or function(v) return v ~= math.floor(v) or v == math.huge or v == -math.huge end local nibbles = {} for i = 0, 15 do local bits = "" for b = 3, 0, -1 do bits = bits .. math.floor(i / 2 ^ b) % 2 end nibbles[string.format("%x", i)] = bits end local function len(s) local _, n = s:gsub("[^\128-\191]", "") return n end local function truncate(s, n) for i = 1, #s do local b = s:byte(i) if b < 128 or b >= 192 then if n == 0 then return s:sub(1, i - 1) end n = n - 1 end end return s end local function float(v, debug) if v ~= v then return "NaN" end if v == math.huge then return "inf" end if v == -math.huge then return "-inf" end if v == math.floor(v) and math.abs(v) < 2^53 then return string.format(debug and "%.1f" or "%d", v) end for p = 1, 17 do local s = string.format("%." .. p .. "g", v) if tonumber(s) == v then return s end end end local function debug(v) if v == nil then return "None" elseif type(v) == "table" and v.tag then if v.tag == "None" then return "None" end return v.tag .. "(" .. debug(v[1]) .. ")" elseif type(v) == "string" then return '"' .. v:gsub('[\\"]', "\\%0"):gsub("\n", "\\n"):gsub("\r", "\\r"):gsub("\t", "\\t") .. '"' elseif type(v) == "table" then local parts, i = {}, base while v[i] ~= nil do parts[#parts + 1] = debug(v[i]) i = i + 1 end return "[" .. table.concat(parts, ", ") .. "]" elseif type(v) == "number" and is_float(v) then return float(v, true) end return tostring(v) end function __fmt(v, spec) local kind, precision, width = spec.kind, spec.precision, spec.width local numeric, s, sign, prefix = type(v) == "number" or spec.unsigned, nil, "", "" if spec.unsigned then s = unsigned(v) if spec.sign then sign = "+" end elseif numeric and (kind == "x" or kind == "X" or kind == "o" or kind == "b") then if kind == "b" then s = string.format("%x", v):gsub(".", nibbles):match("^0*(.-.)$") else s = string.format("%" .. kind, v) end if spec.alternate then prefix = kind == "o" and "0o" or kind == "b" and "0b" or "0x" end elseif numeric then if v < 0 or (v == 0 and 1 / v < 0) then sign, v = "-", -v elseif spec.sign then sign = "+" end if kind == "e" or kind == "E" then local p = precision if not p then p = 0 while p < 17 and tonumber(string.format("%." .. p .. "e", v)) ~= v do p = p + 1 end end local m, e = string.format("%." .. p .. "e", v):match("^%-?(.-)e(.*)$") s = m .. kind .. string.format("%d", tonumber(e)) elseif is_float(v) then s = precision and string.format("%." .. precision .. "f", v) or float(v, kind == "?") else s = string.format("%d", v):gsub("^%-", "") end else s = kind == "?" and debug(v) or tostring(v) if precision and kind ~= "?" then s = truncate(s, precision) end end s = sign .. prefix .. s local chars = len(s) if not width or chars >= width then return s end if numeric and spec.zero then return sign .. prefix .. string.rep("0", width - chars) .. s:sub(#sign + #prefix + 1) end local pad = width - chars local before = (spec.align or (numeric and ">" or "<")) == ">" and pad or spec.align == "^" and math.floor(pad / 2) or 0 return string.rep(spec.fill or " ", before) .. s .. string.rep(spec.fill or " ", pad - before) end end;function main()local name = "smyth";local n = 255;local x = 3.14159;print(string.format("hello %s!",name))print(string.format("%s + %s = %s",1,2,__fmt(1+2,{kind=""})))print((function(_1,_2) return string.format("%s %s %s",_2,_1,_2) end)("a","b"))print(string.format("%s, %s","hi",name))print(string.format("[%8s] [%-6s] [%s] [%s]",name,"ab",__fmt("mid",{kind="",align="^",width=9}),__fmt(1,{kind="",fill="*",align="^",width=7})))print(string.format("%x %X %o %s %s %s",n&4294967295,n&4294967295,n&4294967295,__fmt(5&4294967295,{kind="b",alternate=true}),__fmt(n&4294967295,{kind="x",alternate=true}),__fmt(x,{kind="",zero=true,width=8,precision=3})))print(string.format("%s %s %s %s",__fmt(x,{kind="",precision=2}),__fmt(7,{kind="",sign=true}),__fmt(1500,{kind="e"}),__fmt(0-42,{kind="",zero=true,width=5})))print(string.format("%s %s %s",__fmt("quoted",{kind="?"}),__fmt(2.0,{kind="?"}),__fmt(1,{kind="?"})))print((function(_1,_2,_3,_4) return string.format("%s %s",__fmt(_1,{kind="",align=">",width=_4}),__fmt(_3,{kind="",precision=_2})) end)(7,1,x,4))print("{literal} 100%")print(string.format("%s [%21s] [%-22s]",__fmt(math.mininteger,{kind="?"}),math.mininteger,math.mininteger))local s = string.format("%5s|",n);print(s)end;
\ No newline at end of file
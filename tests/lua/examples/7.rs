@@ -0,0 +1,19 @@
+fn main() {
+	let name = "smyth";
+	let n = 255;
+	let x = 3.14159;
+
+	println!("hello {name}!");
+	println!("{} + {} = {}", 1, 2, 1 + 2);
+	println!("{1} {0} {1}", "a", "b");
+	println!("{greeting}, {name}", greeting = "hi");
+	println!("[{:>8}] [{:<6}] [{:^9}] [{:*^7}]", name, "ab", "mid", 1);
+	println!("{:x} {:X} {:o} {:#b} {:#x} {:08.3}", n, n, n, 5, n, x);
+	println!("{:.2} {:+} {:e} {:05}", x, 7, 1500, 0 - 42);
+	println!("{:?} {:?} {:?}", "quoted", 2.0, 1);
+	println!("{:>width$} {:.*}", 7, 1, x, width = 4);
+	println!("{{literal}} 100%");
+	println!("{:?} [{:>21}] [{:<22}]", -9223372036854775808i64, -9223372036854775808i64, -9223372036854775808i64);
+	let s = format!("{n:>5}|");
+	println!(s);
+}
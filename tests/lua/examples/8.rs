@@ -0,0 +1,17 @@
+fn check(x: i32) -> i32 {
+	assert!(x > 0, "x must be positive, got {}", x);
+	assert_eq!(x % 2, 0);
+	assert_ne!(x, 4, "four is {}", "unlucky");
+	debug_assert!(x < 100 && x != 3);
+	x + 1
+}
+
+fn main() {
+	print!("a");
+	print!("b{}", 1);
+	println!();
+	let v = vec![1, 2, 3];
+	println!("{:?} {:?}", v, vec!["x"]);
+	println!(check(10), check((2 + 4) * 2));
+	eprint!("");
+}
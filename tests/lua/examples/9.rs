@@ -0,0 +1,44 @@
+struct Point {
+	x: i32,
+	y: i32,
+}
+
+struct Meters(f64, &'static str);
+
+fn swap((a, b): (i32, i32)) -> (i32, i32) {
+	(b, a)
+}
+
+fn manhattan(Point { x, y }: Point, _: bool) -> i32 {
+	x + y
+}
+
+fn main() {
+	let (a, b) = (1, 2);
+	let (mut c, _) = swap((a, b));
+	c += 10;
+	println!("{} {} {}", a, b, c);
+
+	let origin = Point { x: 3, y: 4 };
+	let Point { x, y: ref height } = origin;
+	println!("{} {} {}", x, height, manhattan(Point { y: 20, x: 10 }, true));
+
+	let Meters(length, unit) = Meters(2.5, "m");
+	println!("{}{}", length, unit);
+
+	let [first, .., second_last, last] = [1, 2, 3, 4, 5];
+	let ((n, m), o): ((i32, i32), i32) = ((7, 8), 9);
+	println!("{} {} {} {} {} {}", first, second_last, last, n, m, o);
+
+	let pair = (a, b);
+	let (p, q) = pair;
+	let sum = |(l, r): (i32, i32)| l + r;
+	println!("{} {}", sum((p, q)), sum(swap((5, 6))));
+
+	let _ = swap((0, 0));
+	let _unused: i32;
+	for _ in 0..2 {
+		print!(".");
+	}
+	println!();
+}
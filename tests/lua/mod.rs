@@ -1,30 +1,462 @@
-use include_dir::include_dir;
-
-#[test]
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-	let dir = std::fs::read_dir("tests/lua/examples").unwrap();
-	for entry in dir {
-		let entry = entry?;
-		let path = entry.path();
-
-		match path.extension() {
-			Some(extension) if extension == "rs" => {
-				let source = std::fs::read_to_string(&path)?;
-
-				use smyth::backend::Ast;
-				use smyth::gen::CodeGenerator;
-			
-				let ast = smyth::backend::Backend::generate(source)?;
-				let codegen = smyth::gen::lua::LuaCodegen::new();
-				let code = codegen.generate(&ast)?;
-			
-				let expected = std::fs::read_to_string(path.with_extension("lua"))?;
-				assert_eq!(code, expected);
+use smyth::gen::CodeGenerator;
+
+/// Runs a generated script in a fresh Lua VM, calling `main` if it defines one, and returns everything it printed
+/// or wrote to stdout.
+#[cfg(feature = "lua-vm")]
+pub fn execute(code: &str) -> mlua::Result<String> {
+	use std::{cell::RefCell, rc::Rc};
+
+	let lua = mlua::Lua::new();
+	let out = Rc::new(RefCell::new(String::new()));
+
+	let print = lua.create_function({
+		let out = out.clone();
+		move |lua, args: mlua::Variadic<mlua::Value>| {
+			let tostring: mlua::Function = lua.globals().get("tostring")?;
+			let args = args
+				.into_iter()
+				.map(|arg| tostring.call::<_, String>(arg))
+				.collect::<mlua::Result<Vec<_>>>()?;
+
+			let mut out = out.borrow_mut();
+			out.push_str(&args.join("\t"));
+			out.push('\n');
+			Ok(())
+		}
+	})?;
+	lua.globals().set("print", print)?;
+
+	let write = lua.create_function({
+		let out = out.clone();
+		move |_, args: mlua::Variadic<mlua::String>| {
+			for arg in args.iter() {
+				out.borrow_mut().push_str(arg.to_str()?);
 			}
-			_ => ()
+			Ok(())
+		}
+	})?;
+	lua.globals().get::<_, mlua::Table>("io")?.set("write", write)?;
+
+	lua.load(code).set_name("out.lua").exec()?;
+	if let mlua::Value::Function(main) = lua.globals().get("main")? {
+		main.call::<_, ()>(())?;
+	}
+
+	let out = out.borrow().clone();
+	Ok(out)
+}
+
+/// Examples with an `.expected` file are run, and what they print is compared against it.
+/// Not every example is runnable on its own, so the rest are only checked by [crate::snapshot].
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+	let codegen = smyth::gen::lua::LuaCodegen::new();
+	let mut failures = vec![];
+
+	for path in crate::snapshot::examples("lua")? {
+		let expected = path.with_extension("expected");
+		if !expected.exists() {
+			continue;
+		}
+
+		let ast = crate::snapshot::lower(&std::fs::read_to_string(&path)?)?;
+		match execute(&codegen.generate(&ast)?) {
+			Ok(out) => failures.extend(crate::snapshot::check(&expected, &out).err()),
+			Err(e) => failures.push(format!("{}: {e}", path.display())),
+		}
+	}
+
+	crate::snapshot::report(failures);
+	Ok(())
+}
+
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn panics() -> Result<(), Box<dyn std::error::Error>> {
+	let codegen = smyth::gen::lua::LuaCodegen::new();
+	let run = |source: &str| -> Result<String, Box<dyn std::error::Error>> {
+		let ast = crate::snapshot::lower(source)?;
+		Ok(execute(&codegen.generate(&ast)?).unwrap_err().to_string())
+	};
+
+	let err = run("fn main() { let v = vec![1]; assert_eq!(1 + 1, 3, \"math is {}\", \"hard\"); }")?;
+	assert!(err.contains("assertion `left == right` failed: math is hard\n  left: 2\n right: 3"), "{err}");
+
+	let err = run("fn main() { let x = 2; assert!(x < 1); }")?;
+	assert!(err.contains("assertion failed: x < 1"), "{err}");
+
+	let err = run("fn main() { todo!(\"later\"); }")?;
+	assert!(err.contains("not yet implemented: later"), "{err}");
+
+	Ok(())
+}
+
+/// A user's function or local named like a builtin is still theirs, and the std macros still reach the builtin.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn builtin_names() -> Result<(), Box<dyn std::error::Error>> {
+	let source = "fn write(x: i32) { println!(\"user {}\", x); } fn main() { write(1); print!(\"std\\n\"); }";
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(source)?)?;
+	assert_eq!(execute(&code)?, "user 1\nstd\n");
+
+	let source = "fn main() { let print = 3; let __let = 2; let __left = 3; assert_eq!(print, __left); if let Some(x) = Some(print) { println!(\"{}\", x + __let); } }";
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(source)?)?;
+	assert_eq!(execute(&code)?, "5\n");
+
+	Ok(())
+}
+
+/// Options as tagged tables behave the same as `nil`, and print as their variant.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn tagged_options() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::lua::{LuaCodegen, OptionRepr};
+
+	let ast = crate::snapshot::lower(&std::fs::read_to_string("tests/lua/examples/11.rs")?)?;
+	let code = LuaCodegen::new().with_option_repr(OptionRepr::Tagged).generate(&ast)?;
+	assert_eq!(execute(&code)?, std::fs::read_to_string("tests/lua/examples/11.expected")?);
+
+	let source = r#"fn main() { let r: Result<i32, &str> = Ok(6); let n: Option<i32> = None; println!("{:?} {:?} {:?} {:?}", r, Some(5), n, Err::<i32, &str>("no")); }"#;
+	let code = LuaCodegen::new().with_option_repr(OptionRepr::Tagged).generate(&crate::snapshot::lower(source)?)?;
+	assert_eq!(execute(&code)?, "Ok(6) Some(5) None Err(\"no\")\n");
+
+	Ok(())
+}
+
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn zero_based_indexes() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::lua::{IndexBase, LuaCodegen};
+
+	for example in ["8", "9", "10", "12", "13", "19"] {
+		let ast = crate::snapshot::lower(&std::fs::read_to_string(format!("tests/lua/examples/{example}.rs"))?)?;
+		let code = LuaCodegen::new().with_index_base(IndexBase::Zero).generate(&ast)?;
+		assert_eq!(execute(&code)?, std::fs::read_to_string(format!("tests/lua/examples/{example}.expected"))?);
+	}
+
+	Ok(())
+}
+
+// Every number is a float before 5.3, and division has to be rounded by hand.
+#[cfg(all(feature = "lua-vm", lua51_vm))]
+#[test]
+fn lua51_division() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::lua::{LuaCodegen, LuaVersion};
+
+	let ast = crate::snapshot::lower(&std::fs::read_to_string("tests/lua/examples/15.rs")?)?;
+	let code = LuaCodegen::new().with_lua_version(LuaVersion::Lua51).generate(&ast)?;
+	assert!(!code.contains("//"), "{code}");
+	assert_eq!(execute(&code)?, std::fs::read_to_string("tests/lua/examples/15.expected")?);
+
+	Ok(())
+}
+
+/// Casts and strict integers wrap with `%` when there are no integers.
+#[cfg(all(feature = "lua-vm", lua51_vm))]
+#[test]
+fn lua51_casts() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{Ast, Options};
+	use smyth::gen::lua::{LuaCodegen, LuaVersion};
+
+	let source = r#"
+		fn main() {
+			let y: u8 = 250;
+			let z: i8 = 127;
+			let n = -7;
+			println!("{} {} {} {} {} {}", y + 10, z + 1, n as u8, 3.7 as i32, (-3e9 - 0.5) as i32, true as u8);
+			println!("{} {} {}", 'a' as u32, 'é' as u8, '€' as u32);
 		}
+	"#;
+	let ast = smyth::backend::Backend::generate_with(source, &Options { strict_integers: true, ..Default::default() })?;
+	let code = LuaCodegen::new().with_lua_version(LuaVersion::Lua51).generate(&ast)?;
+	assert_eq!(execute(&code)?, "4 -128 249 3 -2147483648 1\n97 233 8364\n");
 
+	Ok(())
+}
+
+/// Examples run the same on Lua 5.1, unless they use something it can't express. Example 7 prints `{:?}` of a
+/// whole float, which 5.1 can't tell apart from an integer.
+#[cfg(all(feature = "lua-vm", lua51_vm))]
+#[test]
+fn lua51_examples() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::lua::{LuaCodegen, LuaVersion};
+	use smyth::gen::CodegenError;
+
+	let codegen = LuaCodegen::new().with_lua_version(LuaVersion::Lua51);
+	let mut failures = vec![];
+
+	for path in crate::snapshot::examples("lua")? {
+		let expected = path.with_extension("expected");
+		if !expected.exists() || path.ends_with("7.rs") {
+			continue;
+		}
+
+		let ast = crate::snapshot::lower(&std::fs::read_to_string(&path)?)?;
+		let code = match codegen.generate(&ast) {
+			Err(CodegenError::Unsupported { .. }) => continue,
+			code => code?,
+		};
+		match execute(&code) {
+			Ok(out) => failures.extend(crate::snapshot::check(&expected, &out).err()),
+			Err(e) => failures.push(format!("{}: {e}", path.display())),
+		}
 	}
 
+	crate::snapshot::report(failures);
+	Ok(())
+}
+
+#[test]
+fn lua51_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::lua::{LuaCodegen, LuaVersion};
+
+	let source = "fn main() {\n\tlet x = 6;\n\tlet y = x & 3;\n}";
+	let err = LuaCodegen::new().with_lua_version(LuaVersion::Lua51).generate(&crate::snapshot::lower(source)?).unwrap_err();
+	assert_eq!(err.to_string(), "bitwise operators can't be expressed in Lua 5.1");
+	assert_eq!(err.span().map(|span| span.lo.line), Some(3));
+
+	let source = "fn main() { let big = 18446744073709551615u64; }";
+	let err = LuaCodegen::new().with_lua_version(LuaVersion::Lua51).generate(&crate::snapshot::lower(source)?).unwrap_err();
+	assert_eq!(err.to_string(), "`u64`s above `i64::MAX` can't be expressed in Lua 5.1");
+
+	Ok(())
+}
+
+/// Lua 5.2 has `goto`, so `continue` works, but numbers are still floats.
+#[test]
+fn lua52_continue() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::lua::{LuaCodegen, LuaVersion};
+
+	let source = "fn main() { for i in 0..3 { if i == 1 { continue; } let half = i / 2; } }";
+	let ast = crate::snapshot::lower(source)?;
+	let err = LuaCodegen::new().with_lua_version(LuaVersion::Lua51).generate(&ast).unwrap_err();
+	assert_eq!(err.to_string(), "`continue` can't be expressed in Lua 5.1");
+	let code = LuaCodegen::new().with_lua_version(LuaVersion::Lua52).generate(&ast)?;
+	assert!(code.contains("goto __continue__") && code.contains("::__continue__::"), "{code}");
+	assert!(!code.contains("//"), "{code}");
+
+	let source = "fn main() { let x = 7 / 2; let y = x | 1; }";
+	let err = LuaCodegen::new().with_lua_version(LuaVersion::Lua52).generate(&crate::snapshot::lower(source)?).unwrap_err();
+	assert_eq!(err.to_string(), "bitwise operators can't be expressed in Lua 5.2");
+
+	Ok(())
+}
+
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn char_codes() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{Ast, CharRepr, Options};
+
+	let source = r#"
+		fn shift(c: u8) -> u8 {
+			if c < b'a' || c > b'z' {
+				return c;
+			}
+			b'a' + (c - b'a' + 3) % 26
+		}
+
+		fn main() {
+			let word = b"xyz!";
+			println!("{} {} {} {}", shift(word[0]), shift(word[2]), shift(word[3]), 'A' as u32 + 1);
+			if let b"xyz!" = word {
+				println!("matched");
+			}
+			let bytes: &[u8] = word;
+			if let b"xy" = bytes {
+				println!("prefix");
+			}
+		}
+	"#;
+	let ast = smyth::backend::Backend::generate_with(source, &Options { chars: CharRepr::Code, ..Default::default() })?;
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&ast)?;
+	assert_eq!(execute(&code)?, "97 99 33 66\nmatched\n");
+
+	Ok(())
+}
+
+/// Casts of chars lowered to strings take their code.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn char_string_casts() -> Result<(), Box<dyn std::error::Error>> {
+	let source = "fn main() { let c = '€'; println!(\"{} {} {}\", 'a' as u32, 'é' as u8, c as u32 + 1); }";
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(source)?)?;
+	assert_eq!(execute(&code)?, "97 233 8365\n");
+
+	Ok(())
+}
+
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn strict_integers() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{Ast, Options};
+
+	let source = r#"
+		fn checksum(data: [u8; 5]) -> u32 {
+			let mut hash: u32 = 2166136261;
+			for i in 0..5 {
+				hash ^= data[i] as u32;
+				hash *= 16777619;
+			}
+			hash
+		}
+
+		fn main() {
+			let mut x: i32 = 2147483647;
+			x += 1;
+			let y: u8 = 250;
+			println!("{} {} {}", checksum([104, 101, 108, 108, 111]), x, y + 10);
+		}
+	"#;
+	let ast = smyth::backend::Backend::generate_with(source, &Options { strict_integers: true, ..Default::default() })?;
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&ast)?;
+	assert_eq!(execute(&code)?, "1335831723 -2147483648 4\n");
+
+	Ok(())
+}
+
+/// Unsigned literals above `i64::MAX` print as themselves, not as the negative integer Lua stores them as.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn unsigned_literals() -> Result<(), Box<dyn std::error::Error>> {
+	let source = r#"fn main() { println!("{} {:?} {:>21}| {:x} {}", 18446744073709551615u64, 18446744073709551615u64, 9223372036854775808u64, 18446744073709551615u64, -1i64); }"#;
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(source)?)?;
+	assert_eq!(execute(&code)?, "18446744073709551615 18446744073709551615   9223372036854775808| ffffffffffffffff -1\n");
+
+	Ok(())
+}
+
+/// Names the generated code numbers are reserved whatever their number.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn reserved_prefixes() -> Result<(), Box<dyn std::error::Error>> {
+	let source = r#"
+		fn f(__arg1: i32, (a, b): (i32, i32)) -> i32 { __arg1 * 100 + a * 10 + b }
+		fn main() { println!("{}", f(1, (2, 3))); }
+	"#;
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(source)?)?;
+	assert!(code.contains("function f(__arg1_,__arg1)"), "{code}");
+	assert_eq!(execute(&code)?, "123\n");
+
+	Ok(())
+}
+
+/// `u64`s above `i64::MAX` are divided, compared and printed as unsigned, wherever they come from.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn unsigned_arithmetic() -> Result<(), Box<dyn std::error::Error>> {
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(UNSIGNED)?)?;
+	assert_eq!(execute(&code)?, UNSIGNED_OUTPUT);
+
+	Ok(())
+}
+
+/// Shared with [crate::interp], which has to agree.
+pub const UNSIGNED: &str = r#"fn main() {
+	let big: u64 = 18446744073709551615;
+	println!("{}", big);
+	println!("{}", 1u64 << 63);
+	println!("{} {} {} {}", big / 2, big % 10, big > 5, 5 < big);
+	println!("{:?} {:>22}|", big, big);
+	let mut x: u64 = big;
+	x /= 3;
+	println!("{x} {}", x >= big);
+}"#;
+
+pub const UNSIGNED_OUTPUT: &str = "18446744073709551615\n9223372036854775808\n9223372036854775807 5 true true\n\
+	18446744073709551615   18446744073709551615|\n6148914691236517205 false\n";
+
+/// The value of an assignment is evaluated before its place, like Rust does, even though Lua evaluates the table and key first.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn assignment_order() -> Result<(), Box<dyn std::error::Error>> {
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(ASSIGNMENT_ORDER)?)?;
+	assert_eq!(execute(&code)?, ASSIGNMENT_ORDER_OUTPUT);
+
+	Ok(())
+}
+
+/// Shared with [crate::interp], which has to agree.
+pub const ASSIGNMENT_ORDER: &str = r#"struct Point { x: i32 }
+fn main() {
+	let mut v = [1, 2, 3];
+	let mut i = 0;
+	v[i] += { i = 2; 10 };
+	v[i] = { i = 1; 20 };
+	let mut points = [Point { x: 1 }, Point { x: 2 }];
+	let mut j = 0;
+	points[j].x *= { j = 1; 5 };
+	println!("{:?} {} {} {} {}", v, i, points[0].x, points[1].x, j);
+}"#;
+
+pub const ASSIGNMENT_ORDER_OUTPUT: &str = "[1, 20, 13] 1 1 10 1\n";
+
+/// `{}` prints floats like Rust does, and hex, octal and binary print negative numbers as the bits of their type.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn display_numbers() -> Result<(), Box<dyn std::error::Error>> {
+	let source = r#"fn main() {
+		let x = 1.0;
+		let y = 0.1 + 0.2;
+		let n: i32 = -123;
+		let b: i8 = -1;
+		println!("{} {} {} {:x} {:X} {:b} {:o} {n} {:x}", x, y, x * 2.0, n, n, b, -8i16, -1i64);
+		println!("{0} {0:x}", n);
+	}"#;
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&crate::snapshot::lower(source)?)?;
+	assert_eq!(
+		execute(&code)?,
+		"1 0.30000000000000004 2 ffffff85 FFFFFF85 11111111 177770 -123 ffffffffffffffff\n-123 ffffff85\n"
+	);
+
+	Ok(())
+}
+
+#[test]
+fn user_macros() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{Ast, Macro, Macros, Options};
+
+	let mut macros = Macros::new();
+	macros.insert("log_info", Macro::Format("Log.info".into()));
+	macros.insert("metric", Macro::Call("Metrics::record".into()));
+
+	let source = r#"fn main() { let n = 3; log_info!("{} items", n); metric!("items", n); ::Log::flush(); }"#;
+	let ast = smyth::backend::Backend::generate_with(source, &Options { macros, ..Default::default() })?;
+	let ast = smyth::backend::Backend::from_items(smyth::resolve::resolve(ast.items().clone()).unwrap());
+
+	let code = smyth::gen::lua::LuaCodegen::new().generate(&ast)?;
+	assert_eq!(
+		code,
+		"function main()local n = 3;Log.info(string.format(\"%s items\",n))Metrics.record(\"items\",n)Log.flush()end;"
+	);
+
+	Ok(())
+}
+
+#[test]
+fn source_map() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::backend::{Ast, Options, SourceMap};
+
+	let source = "fn main() {\n\tlet x = 5;\n\tfoo(x);\n}";
+
+	let mut sources = SourceMap::new();
+	let file = sources.add("map.rs", source);
+
+	let ast = smyth::backend::Backend::generate_with(source, &Options { file, ..Default::default() })?;
+	let codegen = smyth::gen::lua::LuaCodegen::new().with_source_map(true);
+	let (code, map) = codegen.generate_with_map(&ast)?;
+
+	assert_eq!(code, "function main()\n\tlocal x = 5;\n\tfoo(x)\nend;");
+
+	let traceback = "out.lua:3: attempt to call a nil value (global 'foo')\nstack traceback:\n\tout.lua:3: in function 'main'";
+	assert_eq!(
+		map.rewrite_traceback(traceback, "out.lua", &sources),
+		"map.rs:3:2: attempt to call a nil value (global 'foo')\nstack traceback:\n\tmap.rs:3:2: in function 'main'"
+	);
+
+	let json = map.to_json("out.lua", &sources);
+	assert!(json.contains("\"sources\":[\"map.rs\"]"));
+	assert!(json.contains("\"mappings\":\"AAAA;CACC,UAAQ;CACR,IAAI\""), "{json}");
+
 	Ok(())
-}
\ No newline at end of file
+}
@@ -1 +1,7 @@
-mod lua;
\ No newline at end of file
+mod backend;
+mod interp;
+mod lua;
+mod opt;
+mod resolve;
+mod shadow;
+mod snapshot;
@@ -0,0 +1,232 @@
+use smyth::backend::{sexpr, Ast};
+use smyth::interp::Interpreter;
+
+const SOURCE: &str = r#"
+	fn main() {
+		let unused = 1 + 2;
+		let x = 2 * 3 + 1;
+		let label = format!("{}-{}", "x", 7);
+
+		while true {
+			break;
+		}
+
+		if false {
+			print("never");
+		} else {
+			if 1 < 2 {
+				print(label, x, true && x == 7);
+			} else {
+				print("also never");
+			}
+		}
+
+		for i in 5..1 {
+			print(i);
+		}
+
+		loop {
+			print(x / 0);
+			break;
+			print("dead");
+		}
+	}
+"#;
+
+#[test]
+fn simplify() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(SOURCE)?;
+	let items = smyth::opt::optimize(ast.items().clone());
+
+	let expected = sexpr::from_sexpr(
+		r#"
+		(fn main ()
+			(let x 7)
+			(let label "x-7")
+			(call print label x (== x 7))
+			(while true
+				(call print (// x 0))
+				(break)))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&items), sexpr::to_sexpr(&expected));
+
+	// Division by zero is left for runtime, where both of them should fail the same way.
+	let mut interp = Interpreter::new();
+	let out = interp.capture_print();
+	assert!(interp.run(ast.items()).is_err());
+
+	let mut optimized = Interpreter::new();
+	let optimized_out = optimized.capture_print();
+	assert!(optimized.run(&items).is_err());
+
+	assert_eq!(*out.borrow(), *optimized_out.borrow());
+	assert_eq!(*out.borrow(), "x-7\t7\ttrue\n");
+
+	// A loop that breaks right away still evaluates its condition once.
+	let ast = smyth::backend::Backend::generate("fn main() { while ready() { break; } while 1 < 2 { break; } }")?;
+	let items = smyth::opt::optimize(ast.items().clone());
+	assert_eq!(sexpr::to_sexpr(&items), "(fn main ()\n\t(call ready))\n");
+
+	Ok(())
+}
+
+#[test]
+fn typed_literals() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		fn main() {
+			let big = 1u64 << 63;
+			let small = -(3i8);
+			println!("{} {} {} {}", big, small, 300u16 as u8, (1u64 << 63) + 5);
+		}
+		"#,
+	)?;
+	let items = smyth::opt::optimize(ast.items().clone());
+
+	let expected = sexpr::from_sexpr(
+		r#"
+		(fn main ()
+			(let big 9223372036854775808u64)
+			(let small -3i8)
+			(builtin print (fstring ("" " " " " " " "") ("{0}" "{1}" "{2}" "{3}") (wrap u64 big) small 44u8 9223372036854775813u64)))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&items), sexpr::to_sexpr(&expected));
+
+	let mut interp = Interpreter::new();
+	let out = interp.capture_print();
+	interp.run(&items)?;
+	assert_eq!(*out.borrow(), "9223372036854775808 -3 44 9223372036854775813\n");
+
+	Ok(())
+}
+
+#[test]
+fn inline() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		fn square(x: i32) -> i32 {
+			x * x
+		}
+
+		fn report(label: String, value: i32) {
+			let doubled = value * 2;
+			print(label, doubled);
+		}
+
+		fn fact(n: i32) -> i32 {
+			if n == 0 { return 1; }
+			n * fact(n - 1)
+		}
+
+		pub fn exported(a: i32) -> i32 { a + 1 }
+
+		fn main() {
+			let doubled = 3;
+			let y = square(doubled);
+			report("y", y + square(2));
+			print(fact(3), exported(1), doubled);
+		}
+		"#,
+	)?;
+	let items = smyth::opt::inline(ast.items().clone());
+
+	let expected = sexpr::from_sexpr(
+		r#"
+		(fn fact (n)
+			(if (== n 0)
+				(then
+					(return 1)))
+			(return (* n (call fact (- n 1)))))
+		(fn pub exported (a)
+			(return (+ a 1)))
+		(fn main ()
+			(let doubled 3)
+			(let y (* doubled doubled))
+			(let __report_value_2 (+ y (* 2 2)))
+			(let __doubled_1 (* __report_value_2 2))
+			(call print "y" __doubled_1)
+			(call print (call fact 3) (call exported 1) doubled))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&items), sexpr::to_sexpr(&expected));
+
+	let mut interp = Interpreter::new();
+	let out = interp.capture_print();
+	interp.run(&items)?;
+	assert_eq!(*out.borrow(), "y\t26\n6\t2\t3\n");
+
+	Ok(())
+}
+
+#[test]
+fn blocks() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		fn twice(x: i32) -> i32 {
+			x * 2
+		}
+
+		fn main() {
+			let y = {
+				let unused = 1;
+				let a = 2 + 3;
+				twice(a)
+			};
+
+			{
+				print(y);
+			}
+
+			{
+				let y = 1;
+				print(y);
+				y
+			}
+		}
+		"#,
+	)?;
+	let items = smyth::opt::optimize(ast.items().clone());
+
+	let expected = sexpr::from_sexpr(
+		r#"
+		(fn main ()
+			(let y (block
+				(let a 5)
+				(* a 2)))
+			(call print y)
+			(block
+				(let y 1)
+				(call print y)))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&items), sexpr::to_sexpr(&expected));
+
+	let mut interp = Interpreter::new();
+	let out = interp.capture_print();
+	interp.run(&items)?;
+	assert_eq!(*out.borrow(), "10\n1\n");
+
+	Ok(())
+}
+
+/// Examples whose output depends on the values of blocks and `if`s print the same thing when optimised.
+#[cfg(all(feature = "lua-vm", not(lua51_vm)))]
+#[test]
+fn examples() -> Result<(), Box<dyn std::error::Error>> {
+	use smyth::gen::CodeGenerator;
+
+	let codegen = smyth::gen::lua::LuaCodegen::new();
+
+	for name in ["17", "18"] {
+		let path = std::path::Path::new("tests/lua/examples").join(name);
+		let ast = crate::snapshot::lower(&std::fs::read_to_string(path.with_extension("rs"))?)?;
+		let ast = smyth::backend::Backend::from_items(smyth::opt::optimize(ast.items().clone()));
+
+		let out = crate::lua::execute(&codegen.generate(&ast)?)?;
+		assert_eq!(out, std::fs::read_to_string(path.with_extension("expected"))?, "example {name}");
+	}
+
+	Ok(())
+}
@@ -0,0 +1,131 @@
+use smyth::backend::{sexpr, Ast};
+use smyth::resolve::{resolve, ResolveError};
+
+#[test]
+fn paths() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		mod a {
+			fn f(x: i32) {
+				let g = |y| helper(y);
+				g(x);
+			}
+
+			fn helper(x: i32) {
+				b::h();
+			}
+
+			mod b {
+				fn h() {
+					super::f(1);
+					crate::main();
+					self::h();
+				}
+			}
+		}
+
+		fn main() {
+			a::f(2);
+		}
+		"#,
+	)?;
+
+	let items = resolve(ast.items().clone()).map_err(|errors| format!("{errors:?}"))?;
+	let expected = sexpr::from_sexpr(
+		r#"
+		(mod a
+			(fn a::f (x)
+				(let g (closure (y)
+					(return (call a::helper y))))
+				(call g x))
+			(fn a::helper (x)
+				(call a::b::h))
+			(mod a::b
+				(fn a::b::h ()
+					(call a::f 1)
+					(call main)
+					(call a::b::h))))
+		(fn main ()
+			(call a::f 2))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&items), sexpr::to_sexpr(&expected));
+
+	// Already resolved names stay the same.
+	let again = resolve(items).map_err(|errors| format!("{errors:?}"))?;
+	assert_eq!(sexpr::to_sexpr(&again), sexpr::to_sexpr(&expected));
+
+	Ok(())
+}
+
+#[test]
+fn errors() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		mod a {
+			fn f() {}
+		}
+
+		fn main() {
+			let x = 1;
+			fn nested() {
+				print(x);
+			}
+
+			f();
+			a::missing();
+			let m = a;
+		}
+		"#,
+	)?;
+
+	let errors = resolve(ast.items().clone()).unwrap_err();
+	let errors: Vec<(String, usize)> = errors.iter().map(|e| (e.to_string(), e.span().unwrap().lo.line)).collect();
+	assert_eq!(
+		errors,
+		[
+			// Locals of the enclosing function aren't visible to items inside of it.
+			("Unresolved name `x`".to_owned(), 9),
+			("Unresolved name `f`".to_owned(), 12),
+			("Unresolved name `a::missing`".to_owned(), 13),
+			("Expected a value, found module `a`".to_owned(), 14),
+		]
+	);
+
+	assert!(matches!(resolve(ast.items().clone()).unwrap_err()[0], ResolveError::Unresolved { .. }));
+
+	Ok(())
+}
+
+#[test]
+fn builtins() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		fn write(x: i32) {}
+
+		fn main() {
+			write(1);
+			print!("a");
+			let print = |x| x;
+			print(2);
+		}
+		"#,
+	)?;
+
+	// Only calls that don't resolve to a user definition become builtins.
+	let items = resolve(ast.items().clone()).map_err(|errors| format!("{errors:?}"))?;
+	let expected = sexpr::from_sexpr(
+		r#"
+		(fn write (x))
+		(fn main ()
+			(call write 1)
+			(builtin write "a")
+			(let print (closure (x)
+				(return x)))
+			(call print 2))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&items), sexpr::to_sexpr(&expected));
+
+	Ok(())
+}
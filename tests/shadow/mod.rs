@@ -0,0 +1,49 @@
+use smyth::backend::{sexpr, Ast};
+use smyth::shadow::unshadow;
+
+#[test]
+fn renames() -> Result<(), Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(
+		r#"
+		mod shapes {
+			fn area(x: i32) {
+				let y_1 = x;
+			}
+		}
+
+		fn main() {
+			let y = 1;
+			let f = |x| y + x;
+			let y = y + 1;
+			let shapes = |y| y;
+			shapes::area(shapes(y));
+
+			fn inner(y: i32) {
+				let y = y;
+			}
+		}
+		"#,
+	)?;
+
+	let items = smyth::resolve::resolve(ast.items().clone()).map_err(|errors| format!("{errors:?}"))?;
+	let expected = sexpr::from_sexpr(
+		r#"
+		(mod shapes
+			(fn shapes::area (x)
+				(let y_1 x)))
+		(fn main ()
+			(let y 1)
+			(let f (closure (x)
+				(return (+ y x))))
+			(let y_2 (+ y 1))
+			(let shapes_1 (closure (y_3)
+				(return y_3)))
+			(call shapes::area (call shapes_1 y_2))
+			(fn inner (y)
+				(let y_4 y)))
+		"#,
+	)?;
+	assert_eq!(sexpr::to_sexpr(&unshadow(items)), sexpr::to_sexpr(&expected));
+
+	Ok(())
+}
@@ -0,0 +1,103 @@
+//! Golden file tests. Every `tests/<target>/examples/*.rs` is compiled with that target's generator
+//! and compared against the file next to it with the target's extension.
+//!
+//! Run with `SMYTH_BLESS=1` to write the current output instead of comparing against it.
+
+use std::path::{Path, PathBuf};
+
+use smyth::backend::Ast;
+use smyth::gen::CodeGenerator;
+
+type Generate = fn(&str) -> Result<String, Box<dyn std::error::Error>>;
+
+/// Target directory under `tests/`, extension of the expected output and the generator producing it.
+const TARGETS: &[(&str, &str, Generate)] = &[("lua", "lua", |source| {
+	Ok(smyth::gen::lua::LuaCodegen::new().generate(&lower(source)?)?)
+})];
+
+/// Parses and resolves an example, like the command line does before generating code.
+pub fn lower(source: &str) -> Result<smyth::backend::Backend, Box<dyn std::error::Error>> {
+	let ast = smyth::backend::Backend::generate(source)?;
+	match smyth::resolve::resolve(ast.items().clone()) {
+		Ok(items) => Ok(smyth::backend::Backend::from_items(items)),
+		Err(errors) => Err(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n").into()),
+	}
+}
+
+fn bless() -> bool {
+	std::env::var_os("SMYTH_BLESS").is_some_and(|v| v != "0")
+}
+
+/// Sorted `.rs` examples in `tests/<target>/examples`.
+pub fn examples(target: &str) -> std::io::Result<Vec<PathBuf>> {
+	let mut paths = vec![];
+	for entry in std::fs::read_dir(Path::new("tests").join(target).join("examples"))? {
+		let path = entry?.path();
+		if path.extension().is_some_and(|ext| ext == "rs") {
+			paths.push(path);
+		}
+	}
+
+	paths.sort();
+	Ok(paths)
+}
+
+/// Compares `actual` against the contents of `path`, returning a unified diff if they differ.
+/// In bless mode the file is overwritten instead.
+pub fn check(path: &Path, actual: &str) -> Result<(), String> {
+	if bless() {
+		return std::fs::write(path, actual).map_err(|e| format!("{}: {e}", path.display()));
+	}
+
+	let expected = match std::fs::read_to_string(path) {
+		Ok(expected) => expected,
+		Err(_) => return Err(format!("{}: missing, rerun with SMYTH_BLESS=1 to create it", path.display())),
+	};
+
+	if expected == actual {
+		return Ok(());
+	}
+
+	let diff = similar::TextDiff::from_lines(expected.as_str(), actual)
+		.unified_diff()
+		.header(&path.display().to_string(), "actual")
+		.to_string();
+
+	Err(diff)
+}
+
+/// Panics with every failure at once, so one broken example doesn't hide the rest.
+pub fn report(failures: Vec<String>) {
+	if !failures.is_empty() {
+		panic!("{} snapshot(s) failed:\n\n{}", failures.len(), failures.join("\n"));
+	}
+}
+
+#[test]
+fn golden() -> std::io::Result<()> {
+	let mut failures = vec![];
+
+	for entry in std::fs::read_dir("tests")? {
+		let dir = entry?.path();
+		if !dir.join("examples").is_dir() {
+			continue;
+		}
+
+		let target = dir.file_name().unwrap().to_string_lossy().into_owned();
+		let Some((_, extension, generate)) = TARGETS.iter().find(|(name, ..)| *name == target) else {
+			failures.push(format!("{}: no generator registered for this target", dir.display()));
+			continue;
+		};
+
+		for path in examples(&target)? {
+			let source = std::fs::read_to_string(&path)?;
+			match generate(&source) {
+				Ok(code) => failures.extend(check(&path.with_extension(extension), &code).err()),
+				Err(e) => failures.push(format!("{}: {e}", path.display())),
+			}
+		}
+	}
+
+	report(failures);
+	Ok(())
+}